@@ -0,0 +1,39 @@
+#version 450
+
+// lights up a pixel by how much of the beam's light falls on it from the line: the beam's
+// profile across the line, times the profile summed up over the length of the line. the sum is
+// worked out with the error function, so that lines that meet up join seamlessly
+
+layout(set = 0, binding = 0) uniform Beam {
+    vec2 size;
+    float radius;
+    float spread;
+    float scale;
+} beam;
+
+layout(location = 0) in vec2 place;
+layout(location = 1) flat in vec2 shape;
+
+layout(location = 0) out vec4 light;
+
+// Winitzki's approximation of the error function, which is off by less than 0.0002. its
+// constants are 4 / pi and 0.147
+float erf(float x) {
+    float x_squared = x * x;
+    float a_x_squared = 0.147 * x_squared;
+    float ratio = (1.2732395 + a_x_squared) / (1.0 + a_x_squared);
+    return sign(x) * sqrt(1.0 - exp(-(x_squared * ratio)));
+}
+
+void main() {
+    float along = place.x;
+    float across = place.y;
+    float line_length = shape.x;
+    float intensity = shape.y;
+    // the Gaussian profile across the line
+    float off_line = across * beam.spread;
+    float profile = exp(-(off_line * off_line));
+    // and summed up along it, from its start to its end
+    float coverage = erf(along * beam.spread) + erf((line_length - along) * beam.spread);
+    light = vec4(intensity * beam.scale * profile * coverage, 0.0, 0.0, 0.0);
+}
@@ -0,0 +1,42 @@
+#version 450
+
+// draws a quad around every line the beam traces, one instance a line, wide enough to take in
+// all the light the beam puts down around it. the quad is laid out along the line, so that the
+// fragment shader gets how far along and how far off the line every pixel is
+
+layout(set = 0, binding = 0) uniform Beam {
+    // the size of the canvas
+    vec2 size;
+    // how far off the line the beam reaches
+    float radius;
+    // the factors of the beam's profile
+    float spread;
+    float scale;
+} beam;
+
+// where the line starts and which way it goes, in pixels of the canvas
+layout(location = 0) in vec2 origin;
+layout(location = 1) in vec2 axis;
+// the length of the line, and how much light it puts down along every pixel of it
+layout(location = 2) in vec2 line;
+
+// how far along the line the pixel is, and how far to the side of it
+layout(location = 0) out vec2 place;
+layout(location = 1) flat out vec2 shape;
+
+void main() {
+    // the four corners of the quad, drawn as a strip: the ends along the line, and the sides
+    // across it
+    float end = float(gl_VertexIndex & 1);
+    float side = float(gl_VertexIndex >> 1);
+    float along = end * (line.x + beam.radius * 2.0) - beam.radius;
+    float across = (side * 2.0 - 1.0) * beam.radius;
+    vec2 normal = vec2(-axis.y, axis.x);
+    vec2 point = origin + axis * along + normal * across;
+    // the beam lights up pixels around their top left corner, which is half a pixel off from
+    // where they're rasterized
+    vec2 scaled = (point + 0.5) / beam.size * 2.0;
+    gl_Position = vec4(scaled.x - 1.0, 1.0 - scaled.y, 0.0, 1.0);
+    place = vec2(along, across);
+    shape = line;
+}
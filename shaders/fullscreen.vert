@@ -0,0 +1,9 @@
+#version 450
+
+// covers the whole target with a single triangle, for passes that work on every pixel
+
+void main() {
+    // the corners are at (-1, -1), (3, -1) and (-1, 3)
+    vec2 corner = vec2((gl_VertexIndex << 1) & 2, gl_VertexIndex & 2);
+    gl_Position = vec4(corner * 2.0 - 1.0, 0.0, 1.0);
+}
@@ -0,0 +1,41 @@
+#version 450
+#extension GL_EXT_samplerless_texture_functions : require
+
+// adds the colored trace of the canvas pixel under every pixel of the window to the frame the
+// CPU has drawn, or takes it away for palettes whose trace darkens the background. the colors
+// are added up in sRGB and cut off at full brightness, the same way the CPU renderer does it
+
+layout(set = 0, binding = 0) uniform texture2D trace;
+layout(set = 0, binding = 1) uniform Placement {
+    // the window pixel the canvas's top left corner is shown at
+    vec2 origin;
+    // how many pixels of the canvas there are to a pixel of the window
+    vec2 scale;
+    // 1 if the trace darkens the background, and 0 if it lights it up
+    float dark;
+} placement;
+// the frame the CPU has drawn, a pixel for every pixel of the window
+layout(set = 0, binding = 2) uniform texture2D frame;
+
+layout(location = 0) out vec4 color;
+
+vec3 encode_srgb(vec3 linear) {
+    vec3 curved = 1.055 * pow(linear, vec3(1.0 / 2.4)) - 0.055;
+    return mix(curved, linear * 12.92, lessThanEqual(linear, vec3(0.0031308)));
+}
+
+vec3 decode_srgb(vec3 srgb) {
+    vec3 curved = pow((srgb + 0.055) / 1.055, vec3(2.4));
+    return mix(curved, srgb / 12.92, lessThanEqual(srgb, vec3(0.04045)));
+}
+
+void main() {
+    ivec2 window = ivec2(gl_FragCoord.xy);
+    // the canvas pixel shown in this pixel of the window
+    vec2 canvas = (floor(gl_FragCoord.xy) - placement.origin) * placement.scale;
+    vec3 trace_color = texelFetch(trace, ivec2(canvas), 0).rgb;
+    vec4 background = texelFetch(frame, window, 0);
+    vec3 direction = vec3(1.0 - placement.dark * 2.0);
+    vec3 composited = clamp(encode_srgb(background.rgb) + trace_color * direction, 0.0, 1.0);
+    color = vec4(decode_srgb(composited), background.a);
+}
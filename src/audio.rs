@@ -0,0 +1,3567 @@
+use std::{
+    cmp::Reverse,
+    collections::VecDeque,
+    io::{ErrorKind, Read},
+    net::UdpSocket,
+    path::{Path, PathBuf},
+    sync::{
+        Arc,
+        atomic::{AtomicBool, AtomicUsize, Ordering},
+    },
+    time::{Duration, Instant},
+};
+#[cfg(unix)]
+use std::os::unix::{fs::FileTypeExt, net::UnixDatagram};
+
+use cpal::{
+    BuildStreamError, Device, Stream, StreamConfig, StreamError, StreamInstant, Sample, SampleRate,
+    SampleFormat, BufferSize, SupportedBufferSize, SupportedStreamConfigRange,
+    traits::{DeviceTrait, HostTrait, StreamTrait},
+};
+
+use anyhow::{anyhow, bail, Context};
+
+use crate::{
+    Cursor,
+    Measurement,
+    Pane,
+    Plot,
+    View,
+    pane_areas,
+    cli::{
+        AmplitudeScale, BufferSizeOption, ChannelPair, Command, CompareSource, DecimationOption,
+        DeviceSelector, DisplayMode, HueMode, MonoMode, Options, PathMode, RawFormat, SurroundLayout,
+        Trigger, ZMode,
+    },
+    http,
+    loudness::Loudness,
+    palette,
+    spectrogram::Spectrogram,
+    ring::{Consumer, Producer, ring_buffer},
+    wav::{Wav, WavWriter},
+};
+
+pub struct AudioState {
+    options: Options,
+    /// The currently open input. This is `None` while the device is gone.
+    input: Option<Input>,
+    /// The input shown over the one being displayed, if any.
+    comparison: Option<Comparison>,
+    last_reconnect_attempt: Instant,
+    /// Frames taken from the input that haven't been displayed yet.
+    samples_back: Vec<Frame>,
+    /// Frames currently being displayed.
+    samples_front: Vec<Frame>,
+    /// The frames of the channel pairs plotted alongside the selected one, taken and displayed
+    /// in step with `samples_back` and `samples_front`.
+    pairs: Vec<(Vec<Frame>, Vec<Frame>)>,
+    /// The number of frames the input produced before the first one of `samples_front`,
+    /// including the ones that were dropped.
+    front_start: u64,
+    /// The last point that was displayed with its Z value, and the number of the frame after it.
+    /// The beam only goes on from it if that's the first frame displayed next, so that it doesn't
+    /// draw a line across the screen where frames were dropped or skipped.
+    last_sample: Option<(u64, (f32, f32), f32)>,
+    /// How far apart two points can be before the beam is blanked between them, where 1 is from
+    /// the center to the edge.
+    max_line_length: Option<f32>,
+    z_mode: ZMode,
+    /// The number of overruns that were last reported.
+    last_overruns: usize,
+    /// The number of dropouts that were last reported.
+    last_dropouts: usize,
+    /// When input data was last lost, for showing the warning badge.
+    last_data_loss: Option<Instant>,
+    /// The number of frames the input has produced up to the end of `samples_back`, including
+    /// the ones that were dropped.
+    received: u64,
+    /// The file the input is being recorded to, if any.
+    recording: Option<WavWriter>,
+    oversampler: Oversampler,
+    path_mode: PathMode,
+    hue_mode: HueMode,
+    orientation: Orientation,
+    calibration: Calibration,
+    /// If set, the display gain follows the input's level.
+    agc: Option<Agc>,
+    display_mode: DisplayMode,
+    /// What every pane of the dashboard shows, in order.
+    panes: Vec<DisplayMode>,
+    sweeps: Sweeps,
+    spectrogram: Spectrogram,
+    /// The colors the spectrogram's levels are shown in, from quiet to loud.
+    spectrogram_colors: [(u8, u8, u8); 256],
+    waterfall: Waterfall,
+    /// If set, the farthest the picture has reached is outlined.
+    envelope: Option<Envelope>,
+    /// How long the envelope holds its peaks, and how long they take to shrink halfway back.
+    envelope_timing: (Duration, Duration),
+    stereo: Stereo,
+    /// Whether the correlation between the channels is shown under the picture.
+    show_correlation: bool,
+    /// Whether the width of the stereo image is shown under the picture.
+    show_width: bool,
+    levels: Levels,
+    /// Whether the level meters are shown on either side of the picture.
+    show_levels: bool,
+    /// Whether the balance between the channels is shown under the picture.
+    show_balance: bool,
+    /// Whether the mid and side signals are traced along the edges of the scope.
+    show_mid_side: bool,
+    /// Whether the loudness is measured and shown in the heads-up display.
+    show_loudness: bool,
+    /// The loudness measured since it was turned on or the input was replaced. This is `None`
+    /// until there's an input to measure.
+    loudness: Option<Loudness>,
+    surround: SurroundLevels,
+    /// How bright the beam is, relative to its usual brightness.
+    intensity: f32,
+}
+
+/// A frame of input: the X and Y channels, and the Z channel, which blanks the beam or sets its
+/// brightness. Inputs without a Z channel have it at 1.
+type Frame = (f32, f32, f32);
+
+/// The most channels a surround input can have.
+const MAX_SURROUND_CHANNELS: usize = 8;
+
+/// A frame of all the channels of a surround input, in the layout's order. Layouts with fewer
+/// channels leave the rest at 0.
+type SurroundFrame = [f32; MAX_SURROUND_CHANNELS];
+
+/// Gain and DC offset for each axis and a rotation, for framing a picture that's off center, the
+/// wrong size or turned.
+#[derive(Clone, Copy)]
+struct Calibration {
+    gain: (f32, f32),
+    /// The gain of both axes together, for matching the picture to the level of the source.
+    display_gain: f32,
+    /// How far the picture is moved along each axis, where 1 is from the center to the edge.
+    offset: (f32, f32),
+    /// How many degrees the picture is turned counterclockwise around the center.
+    rotation: f32,
+}
+
+/// How the channels are laid out on screen.
+#[derive(Clone, Copy)]
+struct Orientation {
+    /// Whether the polarity of the X and Y channels is inverted.
+    inverted: (bool, bool),
+    /// Whether the X channel goes on the Y axis and the other way around.
+    swapped: bool,
+    /// Whether the picture is turned 45°, with mid on the vertical axis and side on the
+    /// horizontal one.
+    goniometer: bool,
+    /// Whether the picture is mirrored horizontally and vertically.
+    mirrored: (bool, bool),
+}
+
+/// A change to how the channels are laid out on screen.
+#[derive(Clone, Copy)]
+pub enum Flip {
+    InvertX,
+    InvertY,
+    Swap,
+    Goniometer,
+    MirrorHorizontally,
+    MirrorVertically,
+}
+
+impl Orientation {
+    /// Applies the polarity inversion and swapping to a frame.
+    fn channels(self, (x, y): (f32, f32)) -> (f32, f32) {
+        let x = if self.inverted.0 { -x } else { x };
+        let y = if self.inverted.1 { -y } else { y };
+        if self.swapped { (y, x) } else { (x, y) }
+    }
+
+    /// Applies the rotation and mirroring to a point on screen. Sweeps aren't rotated, since
+    /// their X axis is time.
+    fn screen(self, (x, y): (f32, f32), sweep: bool) -> (f32, f32) {
+        // left goes to the upper left and right to the upper right, like on a goniometer
+        let (x, y) = match self.goniometer && !sweep {
+            true => ((y - x) * std::f32::consts::FRAC_1_SQRT_2, (x + y) * std::f32::consts::FRAC_1_SQRT_2),
+            false => (x, y),
+        };
+        (if self.mirrored.0 { -x } else { x }, if self.mirrored.1 { -y } else { y })
+    }
+}
+
+/// Automatic gain control, which follows the peak level of the input and scales the picture so
+/// that it fills about the same part of the screen however loud the input is.
+#[derive(Clone, Copy)]
+struct Agc {
+    /// The peak level, rising quickly when the input gets louder and falling slowly when it gets
+    /// quieter.
+    envelope: f32,
+    last_update: Instant,
+}
+
+impl Agc {
+    /// How far from the center the peaks are put, where 1 is the edge.
+    const TARGET: f32 = 0.8;
+    /// How long the envelope takes to get most of the way to a louder level.
+    const ATTACK: Duration = Duration::from_millis(50);
+    /// How long the envelope takes to get most of the way to a quieter level.
+    const RELEASE: Duration = Duration::from_secs(2);
+
+    fn new() -> Self {
+        Agc { envelope: Self::TARGET, last_update: Instant::now() }
+    }
+
+    /// Moves the envelope towards the given peak level, and returns the gain that puts the
+    /// envelope at the target.
+    fn update(&mut self, peak: f32) -> f32 {
+        let elapsed = self.last_update.elapsed().as_secs_f32();
+        self.last_update = Instant::now();
+        let time = match peak > self.envelope {
+            true => Self::ATTACK,
+            false => Self::RELEASE,
+        };
+        self.envelope += (peak - self.envelope) * (1.0 - (-elapsed / time.as_secs_f32()).exp());
+        // the gain is kept in range so that silence doesn't blow up the noise floor
+        let (min, max) = Options::GAIN_RANGE;
+        (Self::TARGET / self.envelope).clamp(min, max)
+    }
+}
+
+/// Plotting the channels against time, with sweeps started by a trigger like on an
+/// oscilloscope.
+struct Sweeps {
+    trigger: Trigger,
+    /// The level the X channel has to cross to trigger a sweep.
+    level: f32,
+    /// How long a sweep across the screen takes.
+    timebase: Duration,
+    /// The latest frames, enough for a sweep and a trigger before it.
+    history: Vec<Frame>,
+    /// The number of frames the input produced before the end of `history`.
+    history_end: u64,
+}
+
+impl Sweeps {
+    /// Adds the frames being displayed to the history, leaving out the ones it already has.
+    /// `start` is the number of frames the input produced before the first of them.
+    fn extend(&mut self, frames: &[Frame], start: u64, sweep_length: usize) {
+        let end = start + frames.len() as u64;
+        if start > self.history_end || end < self.history_end {
+            // frames were dropped or the input started over, so the history no longer leads up
+            // to these frames
+            self.history.clear();
+            self.history.extend_from_slice(frames);
+        } else {
+            self.history.extend_from_slice(&frames[(self.history_end - start) as usize..]);
+        }
+        self.history_end = end;
+        let excess = self.history.len().saturating_sub(sweep_length * 2);
+        self.history.drain(..excess);
+    }
+
+    /// Returns where the latest sweep that fits in the history starts, or `None` if there's
+    /// no edge to trigger on.
+    fn find_trigger(&self, channel: impl Fn(Frame) -> f32, sweep_length: usize) -> Option<usize> {
+        let last_start = self.history.len().checked_sub(sweep_length)?;
+        (1..=last_start).rev().find(|&i| {
+            let (before, after) = (channel(self.history[i - 1]), channel(self.history[i]));
+            match self.trigger {
+                Trigger::Rising => before < self.level && after >= self.level,
+                Trigger::Falling => before > self.level && after <= self.level,
+                Trigger::Off => false,
+            }
+        })
+    }
+}
+
+/// The latest pictures of the X channel against the Y channel, each made of an equal part of
+/// the input, for stacking them up in a waterfall.
+struct Waterfall {
+    /// How far back the slices go.
+    length: Duration,
+    /// How many slices are kept, including the one being filled.
+    slices: usize,
+    /// The slices that are filled, newest first, each thinned out to at most `MAX_POINTS`
+    /// frames.
+    finished: VecDeque<Vec<Frame>>,
+    /// The frames of the slice being filled.
+    current: Vec<Frame>,
+    /// The number of frames the input produced before the end of `current`.
+    current_end: u64,
+}
+
+impl Waterfall {
+    /// The most frames a finished slice keeps, so that a long waterfall doesn't take ages to
+    /// draw.
+    const MAX_POINTS: usize = 2048;
+
+    /// How many frames of the input every slice is made of.
+    fn slice_length(&self, sample_rate: u32) -> usize {
+        ((self.length.as_secs_f32() * sample_rate as f32 / self.slices as f32) as usize).max(1)
+    }
+
+    /// Returns how many of a slice's frames a frame it keeps stands for.
+    fn step(slice_length: usize) -> usize {
+        slice_length.div_ceil(Self::MAX_POINTS)
+    }
+
+    /// Adds the frames being displayed to the slices, leaving out the ones it already has.
+    /// `start` is the number of frames the input produced before the first of them.
+    fn extend(&mut self, frames: &[Frame], start: u64, slice_length: usize) {
+        let end = start + frames.len() as u64;
+        // dropped frames only leave a seam in a slice, but an input that started over leaves
+        // nothing to go by
+        let new = match start > self.current_end || end < self.current_end {
+            true => frames,
+            false => &frames[(self.current_end - start) as usize..],
+        };
+        self.current_end = end;
+        for &frame in new {
+            self.current.push(frame);
+            if self.current.len() >= slice_length {
+                let slice = self.current.iter().step_by(Self::step(slice_length)).copied().collect();
+                self.finished.push_front(slice);
+                self.current.clear();
+            }
+        }
+        self.finished.truncate(self.slices - 1);
+    }
+
+    fn clear(&mut self) {
+        self.finished.clear();
+        self.current.clear();
+        self.current_end = 0;
+    }
+}
+
+/// Running averages of the channels, from which the correlation between them and the width of
+/// the stereo image are worked out.
+struct Stereo {
+    /// How long the averages take to get most of the way to new values.
+    time: Duration,
+    /// Running averages of X, Y, their squares and their product. These are kept in double
+    /// precision, since every frame only moves them a little.
+    means: [f64; 5],
+    /// The number of frames the input produced before the last one that's been averaged.
+    end: u64,
+}
+
+impl Stereo {
+    /// How little variance is taken for silence, which leaves nothing to measure but rounding
+    /// errors.
+    const SILENCE: f64 = 1e-10;
+
+    fn new(time: Duration) -> Self {
+        Stereo { time, means: [0.0; 5], end: 0 }
+    }
+
+    /// Averages in the frames being displayed, leaving out the ones it's already had. `start` is
+    /// the number of frames the input produced before the first of them.
+    fn update(&mut self, frames: &[Frame], start: u64, sample_rate: u32) {
+        let end = start + frames.len() as u64;
+        let new = match start > self.end || end < self.end {
+            true => frames,
+            false => &frames[(self.end - start) as usize..],
+        };
+        self.end = end;
+        let weight = 1.0 - (-1.0 / (self.time.as_secs_f64() * sample_rate as f64)).exp();
+        for &(x, y, _) in new {
+            let (x, y) = (x as f64, y as f64);
+            for (mean, value) in self.means.iter_mut().zip([x, y, x * x, y * y, x * y]) {
+                *mean += (value - *mean) * weight;
+            }
+        }
+    }
+
+    /// Returns the correlation between the channels, which tells how alike they are: 1 when
+    /// they're the same, 0 when they have nothing to do with each other, and -1 when one is the
+    /// other upside down. It's 0 while either channel is silent.
+    fn correlation(&self) -> f32 {
+        let [x, y, xx, yy, xy] = self.means;
+        let (variance_x, variance_y) = (xx - x * x, yy - y * y);
+        if variance_x < Self::SILENCE || variance_y < Self::SILENCE {
+            return 0.0
+        }
+        ((xy - x * y) / (variance_x * variance_y).sqrt()).clamp(-1.0, 1.0) as f32
+    }
+
+    /// Returns the energy of the mid (X + Y) / 2 and side (X - Y) / 2 signals, or `None` while
+    /// the input is silent.
+    fn mid_side(&self) -> Option<(f32, f32)> {
+        let [_, _, xx, yy, xy] = self.means;
+        let mid = (xx + yy + 2.0 * xy) / 4.0;
+        let side = (xx + yy - 2.0 * xy) / 4.0;
+        (mid + side >= Self::SILENCE).then(|| (mid.max(0.0) as f32, side.max(0.0) as f32))
+    }
+}
+
+/// The levels of both channels, for the meters on either side of the picture: the RMS level,
+/// which rises and falls smoothly like on a VU meter, and the peak level, which holds on to peaks
+/// for a while and then falls.
+struct Levels {
+    /// The running average of every channel's square.
+    mean_squares: [f64; 2],
+    /// The highest peak of every channel lately, and when it was reached.
+    peaks: [(f32, Instant); 2],
+    /// How long a peak is held before it starts falling.
+    hold: Duration,
+    last_update: Instant,
+    /// The number of frames the input produced before the last one that's been measured.
+    end: u64,
+}
+
+impl Levels {
+    /// How long the RMS level takes to get most of the way to a new level.
+    const RMS_TIME: Duration = Duration::from_millis(300);
+    /// How fast a peak falls after the hold, in decibels per second.
+    const PEAK_FALL: f32 = 20.0;
+
+    fn new(hold: Duration) -> Self {
+        let now = Instant::now();
+        Levels { mean_squares: [0.0; 2], peaks: [(0.0, now); 2], hold, last_update: now, end: 0 }
+    }
+
+    /// Measures the frames being displayed, leaving out the ones it's already had. `start` is
+    /// the number of frames the input produced before the first of them.
+    fn update(&mut self, frames: &[Frame], start: u64, sample_rate: u32) {
+        let end = start + frames.len() as u64;
+        let new = match start > self.end || end < self.end {
+            true => frames,
+            false => &frames[(self.end - start) as usize..],
+        };
+        self.end = end;
+        let now = Instant::now();
+        let elapsed = (now - self.last_update).as_secs_f32();
+        self.last_update = now;
+        let weight = 1.0 - (-1.0 / (Self::RMS_TIME.as_secs_f64() * sample_rate as f64)).exp();
+        for channel in 0..2 {
+            let mut peak: f32 = 0.0;
+            for &(x, y, _) in new {
+                let sample = [x, y][channel];
+                peak = peak.max(sample.abs());
+                let mean_square = &mut self.mean_squares[channel];
+                *mean_square += ((sample * sample) as f64 - *mean_square) * weight;
+            }
+            let (held, time) = &mut self.peaks[channel];
+            if time.elapsed() > self.hold {
+                *held *= 10f32.powf(-Self::PEAK_FALL * elapsed / 20.0);
+            }
+            if peak >= *held {
+                *held = peak;
+                *time = now;
+            }
+        }
+    }
+
+    /// Returns the RMS and peak level of both channels, as amplitudes.
+    fn levels(&self) -> [(f32, f32); 2] {
+        [0, 1].map(|channel| (self.mean_squares[channel].sqrt() as f32, self.peaks[channel].0))
+    }
+
+    /// Returns how much louder the Y channel is than the X channel in decibels, going by their
+    /// RMS levels, or `None` while either is silent.
+    fn balance(&self) -> Option<f32> {
+        // silence leaves nothing to compare but rounding errors
+        const SILENCE: f64 = 1e-10;
+        let [x, y] = self.mean_squares;
+        (x >= SILENCE && y >= SILENCE).then(|| (10.0 * (y / x).log10()) as f32)
+    }
+}
+
+/// The RMS levels of all the channels of a surround input, which rise and fall smoothly.
+struct SurroundLevels {
+    /// The running average of every channel's square.
+    mean_squares: [f64; MAX_SURROUND_CHANNELS],
+    last_update: Instant,
+    /// The frames taken from the input since the last update.
+    frames: Vec<SurroundFrame>,
+}
+
+impl SurroundLevels {
+    fn new() -> Self {
+        SurroundLevels {
+            mean_squares: [0.0; MAX_SURROUND_CHANNELS],
+            last_update: Instant::now(),
+            frames: Vec::new(),
+        }
+    }
+
+    /// Measures the frames that have arrived from the input since the last update. They go in
+    /// all at once, weighed by how long it's been since then.
+    fn update(&mut self, input: &mut Consumer<SurroundFrame>) {
+        self.frames.clear();
+        input.drain_into(&mut self.frames);
+        let now = Instant::now();
+        let elapsed = (now - self.last_update).as_secs_f64();
+        self.last_update = now;
+        if self.frames.is_empty() {
+            return
+        }
+        let weight = 1.0 - (-elapsed / Levels::RMS_TIME.as_secs_f64()).exp();
+        for (channel, mean_square) in self.mean_squares.iter_mut().enumerate() {
+            let sum: f64 = self.frames.iter().map(|frame| (frame[channel] * frame[channel]) as f64).sum();
+            *mean_square += (sum / self.frames.len() as f64 - *mean_square) * weight;
+        }
+    }
+
+    /// Returns the RMS level of every channel, where a full scale sine wave is at 1.
+    fn levels(&self) -> [f32; MAX_SURROUND_CHANNELS] {
+        self.mean_squares.map(|mean_square| (mean_square * 2.0).sqrt() as f32)
+    }
+
+    /// Returns the direction the sound comes from in degrees clockwise from the front, and how
+    /// much of it comes from there, from 0 when it comes from all around evenly to 1 when it all
+    /// comes from that direction, or `None` while the input is silent.
+    fn direction(&self, layout: SurroundLayout) -> Option<(f32, f32)> {
+        // silence leaves no direction but one made of rounding errors
+        const SILENCE: f64 = 1e-10;
+        let (mut x, mut y, mut total) = (0.0, 0.0, 0.0);
+        for (&(_, angle), &mean_square) in layout.speakers().iter().zip(&self.mean_squares) {
+            if let Some(angle) = angle {
+                let (sin, cos) = (angle as f64).to_radians().sin_cos();
+                x += sin * mean_square;
+                y += cos * mean_square;
+                total += mean_square;
+            }
+        }
+        (total >= SILENCE).then(|| (x.atan2(y).to_degrees() as f32, (x.hypot(y) / total) as f32))
+    }
+}
+
+/// The farthest the picture has reached in every direction, which holds on to peaks for a while
+/// and then shrinks back.
+struct Envelope {
+    /// How far the picture has reached in each of a number of directions around the center, and
+    /// when that was.
+    peaks: Vec<(f32, Instant)>,
+    hold: Duration,
+    decay: Duration,
+    last_update: Instant,
+}
+
+impl Envelope {
+    /// How many directions the peaks are tracked in.
+    const DIRECTIONS: usize = 90;
+
+    fn new(hold: Duration, decay: Duration) -> Self {
+        let now = Instant::now();
+        Envelope { peaks: vec![(0.0, now); Self::DIRECTIONS], hold, decay, last_update: now }
+    }
+
+    /// Shrinks the peaks that are past their hold time.
+    fn decay(&mut self) {
+        let now = Instant::now();
+        let factor = 0.5f32.powf((now - self.last_update).as_secs_f32() / self.decay.as_secs_f32());
+        self.last_update = now;
+        for (peak, time) in &mut self.peaks {
+            if now - *time > self.hold {
+                *peak *= factor;
+            }
+        }
+    }
+
+    /// Takes a point of the picture into account.
+    fn update(&mut self, (x, y): (f32, f32)) {
+        let distance = x.hypot(y);
+        let angle = y.atan2(x).rem_euclid(std::f32::consts::TAU);
+        let direction = (angle / std::f32::consts::TAU * Self::DIRECTIONS as f32) as usize % Self::DIRECTIONS;
+        let peak = &mut self.peaks[direction];
+        if distance >= peak.0 {
+            *peak = (distance, Instant::now());
+        }
+    }
+
+    /// Returns the outline, as a point in every direction.
+    fn outline(&self) -> impl Iterator<Item = (f32, f32)> + '_ {
+        self.peaks.iter().enumerate().map(|(direction, &(peak, _))| {
+            let angle = (direction as f32 + 0.5) / Self::DIRECTIONS as f32 * std::f32::consts::TAU;
+            let (sin, cos) = angle.sin_cos();
+            (peak * cos, peak * sin)
+        })
+    }
+}
+
+/// An open capture stream, or a file being played.
+struct Input {
+    /// The capture stream, kept around so that it isn't dropped while we're still rendering. This
+    /// is `None` for inputs fed by a thread of their own, such as standard input.
+    stream: Option<Stream>,
+    /// The processed frames, as they arrive.
+    samples: Consumer<Frame>,
+    /// The processed frames of the channel pairs plotted alongside the selected one.
+    pairs: Vec<Consumer<Frame>>,
+    /// All the channels of a surround input, as they arrive, before decimation.
+    surround: Option<Consumer<SurroundFrame>>,
+    /// Tells which frames should be on screen at a given time. This is `None` for inputs that
+    /// don't come with timestamps, which are displayed as soon as enough frames arrive.
+    clock: Option<Clock>,
+    /// What the input is called, eg. the device's name or the file's path.
+    name: String,
+    sample_rate: u32,
+    mono: Option<MonoMode>,
+    status: Arc<StreamStatus>,
+    last_callbacks: usize,
+    last_activity: Instant,
+    /// A JACK client used for querying the transport state, if the scope follows the transport.
+    #[cfg(all(feature = "jack", any(target_os = "linux", target_os = "dragonfly", target_os = "freebsd")))]
+    transport_client: Option<jack::Client>,
+}
+
+/// An input shown over the one being displayed, for comparing the two, with a gain and a color
+/// of its own.
+struct Comparison {
+    input: Input,
+    /// Frames taken from the input that haven't been displayed yet.
+    samples_back: Vec<Frame>,
+    /// Frames currently being displayed.
+    samples_front: Vec<Frame>,
+    gain: f32,
+    color: (f32, f32, f32),
+}
+
+impl Comparison {
+    /// Takes the frames that have arrived, and flips buffers as soon as there are enough of them.
+    /// The compared input isn't lined up with the one being displayed, whose clock it doesn't
+    /// share, so its frames are displayed as soon as they arrive.
+    fn update(&mut self) {
+        self.input.samples.drain_into(&mut self.samples_back);
+        if self.samples_back.len() > AudioState::buffer_size(self.input.sample_rate) {
+            std::mem::swap(&mut self.samples_front, &mut self.samples_back);
+            self.samples_back.clear();
+        }
+    }
+}
+
+/// Status reported by a stream's callbacks.
+#[derive(Default)]
+struct StreamStatus {
+    /// Set by the error callback when the device goes away.
+    disconnected: AtomicBool,
+    /// Incremented on every data callback, so that streams that die silently can be detected.
+    callbacks: AtomicUsize,
+    /// Set when a file has been played to the end.
+    finished: AtomicBool,
+    /// Incremented whenever input data is lost before it reaches the scope, eg. on an xrun or
+    /// when an RTP packet goes missing.
+    dropouts: AtomicUsize,
+}
+
+/// A point on an input's timeline: how many frames the input had produced by the time a callback
+/// was invoked, in seconds since the input was opened.
+#[derive(Clone, Copy, Default)]
+struct Anchor {
+    frames: u64,
+    time: f64,
+}
+
+/// Sends anchors from the capture callback, based on the timestamps passed to it by the host.
+/// These are steadier than the time at which the callback actually gets to run.
+struct ClockWriter {
+    anchors: Producer<Anchor>,
+    epoch: Instant,
+    /// The timestamp of the first callback, and when it was invoked in seconds since the epoch.
+    start: Option<(StreamInstant, f64)>,
+    frames: u64,
+}
+
+/// Maps wall-clock time to positions on an input's timeline, so that the display moves through
+/// the input at a steady pace, no matter how many frames happen to arrive between two redraws.
+struct Clock {
+    anchors: Consumer<Anchor>,
+    epoch: Instant,
+    latest: Option<Anchor>,
+    /// How far behind the latest anchor the display is kept, in seconds. This is long enough for
+    /// the next callback to arrive before the display runs out of frames.
+    latency: f64,
+}
+
+impl ClockWriter {
+    /// Records that a callback invoked at the given instant produced the given number of frames.
+    fn tick(&mut self, callback: StreamInstant, frames: usize) {
+        self.frames += frames as u64;
+        let start_time = self.epoch.elapsed().as_secs_f64();
+        let (start, start_time) = *self.start.get_or_insert((callback, start_time));
+        let since_start = callback.duration_since(&start).unwrap_or_default();
+        self.anchors.push(Anchor { frames: self.frames, time: start_time + since_start.as_secs_f64() });
+    }
+}
+
+impl Clock {
+    /// How many anchors can be waiting to be read.
+    const ANCHOR_CAPACITY: usize = 256;
+
+    fn new() -> (ClockWriter, Clock) {
+        let (producer, consumer) = ring_buffer(Self::ANCHOR_CAPACITY);
+        let epoch = Instant::now();
+        let writer = ClockWriter { anchors: producer, epoch, start: None, frames: 0 };
+        (writer, Clock { anchors: consumer, epoch, latest: None, latency: 0.0 })
+    }
+
+    /// Returns the position of the frame that should be at the end of the display at this point,
+    /// or `None` if no callbacks have happened yet.
+    fn position(&mut self, sample_rate: u32) -> Option<u64> {
+        let mut anchors = Vec::new();
+        self.anchors.drain_into(&mut anchors);
+        for anchor in anchors {
+            if let Some(previous) = self.latest {
+                let period = (anchor.frames - previous.frames) as f64 / sample_rate as f64;
+                let period = period.max(anchor.time - previous.time);
+                self.latency = self.latency.max(period * 2.0);
+            }
+            self.latest = Some(anchor);
+        }
+        let latest = self.latest?;
+        let elapsed = self.epoch.elapsed().as_secs_f64() - latest.time - self.latency;
+        Some((latest.frames as f64 + elapsed * sample_rate as f64).max(0.0) as u64)
+    }
+}
+
+/// Points the ALSA pulse plugin at the monitor of the default sink for as long as it's kept
+/// around. The plugin records from the source named in `PULSE_SOURCE` when a device is opened, so
+/// the variable is only set while the loopback device is being opened, and put back afterwards.
+#[cfg(any(target_os = "linux", target_os = "dragonfly", target_os = "freebsd"))]
+struct MonitorSource {
+    previous: Option<std::ffi::OsString>,
+}
+
+#[cfg(any(target_os = "linux", target_os = "dragonfly", target_os = "freebsd"))]
+impl MonitorSource {
+    const VARIABLE: &'static str = "PULSE_SOURCE";
+
+    fn select() -> Self {
+        let previous = std::env::var_os(Self::VARIABLE);
+        std::env::set_var(Self::VARIABLE, "@DEFAULT_MONITOR@");
+        MonitorSource { previous }
+    }
+}
+
+#[cfg(any(target_os = "linux", target_os = "dragonfly", target_os = "freebsd"))]
+impl Drop for MonitorSource {
+    fn drop(&mut self) {
+        match self.previous.take() {
+            Some(previous) => std::env::set_var(Self::VARIABLE, previous),
+            None => std::env::remove_var(Self::VARIABLE),
+        }
+    }
+}
+
+impl Input {
+    /// How long a stream may go without delivering any data before it's considered dead.
+    const STALL_TIMEOUT: Duration = Duration::from_secs(2);
+
+    fn is_alive(&mut self) -> bool {
+        let callbacks = self.status.callbacks.load(Ordering::Relaxed);
+        if callbacks != self.last_callbacks {
+            self.last_callbacks = callbacks;
+            self.last_activity = Instant::now();
+        }
+        let disconnected = self.status.disconnected.load(Ordering::Relaxed);
+        // inputs fed by a thread of their own may go quiet for a while without anything being
+        // wrong, eg. when there's nothing to read from a pipe
+        let stalled = self.stream.is_some() && self.last_activity.elapsed() >= Self::STALL_TIMEOUT;
+        !disconnected && !stalled
+    }
+
+    /// Returns whether the scope follows the JACK transport and the transport is stopped.
+    fn is_transport_stopped(&self) -> bool {
+        #[cfg(all(feature = "jack", any(target_os = "linux", target_os = "dragonfly", target_os = "freebsd")))]
+        if let Some(client) = &self.transport_client {
+            return matches!(client.transport().query_state(), Ok(jack::TransportState::Stopped))
+        }
+        false
+    }
+}
+
+/// Decodes raw interleaved samples into the buffer, for inputs that don't come from an audio
+/// device.
+struct RawDecoder {
+    format: RawFormat,
+    channels: usize,
+    /// The input's sample rate, before decimation.
+    sample_rate: u32,
+    pair: ChannelPair,
+    processor: InputProcessor,
+    samples: Producer<Frame>,
+    pairs: PairCapture,
+    downmix: Option<Downmix>,
+    status: Arc<StreamStatus>,
+}
+
+impl RawDecoder {
+    fn frame_size(&self) -> usize {
+        self.format.sample_size() * self.channels
+    }
+
+    /// Decodes the whole frames at the start of the given bytes, and returns how many frames
+    /// there were.
+    fn push(&mut self, bytes: &[u8]) -> usize {
+        let (frame_size, sample_size) = (self.frame_size(), self.format.sample_size());
+        let mut frames = 0;
+        let format = self.format;
+        for frame in bytes.chunks_exact(frame_size) {
+            let sample = |channel: u16| format.decode(&frame[channel as usize * sample_size..]);
+            let (x, y) = match &mut self.downmix {
+                Some(downmix) => downmix.process(sample),
+                None => (sample(self.pair.x), sample(self.pair.y)),
+            };
+            let z = self.pair.z.map_or(1.0, sample);
+            if let Some(frame) = self.processor.process(x, y, z) {
+                self.samples.push(frame);
+            }
+            self.pairs.push(sample);
+            frames += 1;
+        }
+        self.status.callbacks.fetch_add(1, Ordering::Relaxed);
+        frames
+    }
+}
+
+/// Feeds a file's samples into the buffer as it plays.
+struct FilePlayer {
+    wav: Arc<Wav>,
+    /// The index of the next frame to play.
+    position: usize,
+    pair: ChannelPair,
+    processor: InputProcessor,
+    samples: Producer<Frame>,
+    pairs: PairCapture,
+    downmix: Option<Downmix>,
+    status: Arc<StreamStatus>,
+    /// Converts the file to the output device's sample rate, if the device can't play the file at
+    /// its own.
+    resampler: Option<Resampler>,
+}
+
+/// Converts a file's frames to another sample rate, by interpolating between each two of them.
+struct Resampler {
+    file_rate: u64,
+    output_rate: u64,
+    /// How far the next output frame is past `previous`, in 1/`output_rate`ths of a file frame.
+    phase: u64,
+    previous: Vec<f32>,
+    next: Vec<f32>,
+    /// The file's frames played for the output frames being converted.
+    played: Vec<f32>,
+    frame: Vec<f32>,
+}
+
+impl FilePlayer {
+    /// Plays up to the given number of frames, passing each one to `output` as it goes.
+    fn play(&mut self, frame_count: usize, mut output: impl FnMut(&[f32])) {
+        let channels = self.wav.channels as usize;
+        let end = (self.position + frame_count).min(self.wav.samples.len() / channels);
+        for frame in self.wav.samples[self.position * channels..end * channels].chunks_exact(channels) {
+            let sample = |channel: u16| frame[channel as usize];
+            let (x, y) = match &mut self.downmix {
+                Some(downmix) => downmix.process(sample),
+                None => (sample(self.pair.x), sample(self.pair.y)),
+            };
+            let z = self.pair.z.map_or(1.0, sample);
+            if let Some(processed) = self.processor.process(x, y, z) {
+                self.samples.push(processed);
+            }
+            self.pairs.push(sample);
+            output(frame);
+        }
+        self.position = end;
+        self.status.callbacks.fetch_add(1, Ordering::Relaxed);
+        if end * channels >= self.wav.samples.len() {
+            self.status.finished.store(true, Ordering::Relaxed);
+        }
+    }
+
+    /// Plays the given number of frames at the output device's sample rate, passing each one to
+    /// `output`.
+    fn play_output(&mut self, frame_count: usize, mut output: impl FnMut(&[f32])) {
+        let mut resampler = match self.resampler.take() {
+            Some(resampler) => resampler,
+            None => return self.play(frame_count, output),
+        };
+        resampler.played.clear();
+        self.play(resampler.needed(frame_count), |frame| resampler.played.extend_from_slice(frame));
+        resampler.convert(frame_count, &mut output);
+        self.resampler = Some(resampler);
+    }
+}
+
+impl Resampler {
+    fn new(file_rate: u32, output_rate: u32, channels: u16) -> Self {
+        Resampler {
+            file_rate: file_rate as u64,
+            output_rate: output_rate as u64,
+            // the first two frames are played before the first output frame
+            phase: output_rate as u64 * 2,
+            previous: vec![0.0; channels as usize],
+            next: vec![0.0; channels as usize],
+            played: Vec::new(),
+            frame: vec![0.0; channels as usize],
+        }
+    }
+
+    /// Returns how many of the file's frames go into the given number of output frames.
+    fn needed(&self, frame_count: usize) -> usize {
+        match frame_count {
+            0 => 0,
+            _ => ((self.phase + (frame_count as u64 - 1) * self.file_rate) / self.output_rate) as usize,
+        }
+    }
+
+    /// Converts the frames that were played to the given number of output frames, passing each one
+    /// to `output`. The end of the file is followed by silence.
+    fn convert(&mut self, frame_count: usize, output: &mut impl FnMut(&[f32])) {
+        let mut played = self.played.chunks_exact(self.frame.len());
+        for _ in 0..frame_count {
+            while self.phase >= self.output_rate {
+                std::mem::swap(&mut self.previous, &mut self.next);
+                match played.next() {
+                    Some(frame) => self.next.copy_from_slice(frame),
+                    None => self.next.fill(0.0),
+                }
+                self.phase -= self.output_rate;
+            }
+            let t = self.phase as f32 / self.output_rate as f32;
+            for ((sample, previous), next) in self.frame.iter_mut().zip(&self.previous).zip(&self.next) {
+                *sample = previous + (next - previous) * t;
+            }
+            output(&self.frame);
+            self.phase += self.file_rate;
+        }
+    }
+}
+
+/// Captures the channel pairs plotted alongside the selected one, each into a buffer of its own.
+/// They're processed like the selected pair, but without a Z channel.
+struct PairCapture {
+    pairs: Vec<(ChannelPair, InputProcessor, Producer<Frame>)>,
+}
+
+impl PairCapture {
+    /// Captures a frame of input, given the sample of each of its channels.
+    fn push(&mut self, sample: impl Fn(u16) -> f32) {
+        for (pair, processor, samples) in &mut self.pairs {
+            if let Some(frame) = processor.process(sample(pair.x), sample(pair.y), 1.0) {
+                samples.push(frame);
+            }
+        }
+    }
+}
+
+/// Mixes the channels of a surround input down to stereo, to be plotted in place of a pair of
+/// channels, and captures all of them for the surround display.
+struct Downmix {
+    /// How much of every channel goes into the left and the right side of the mix.
+    weights: Vec<(f32, f32)>,
+    channels: Producer<SurroundFrame>,
+}
+
+impl Downmix {
+    /// Mixes a frame of input down, given the sample of each of its channels, and returns the
+    /// left and right side of the mix.
+    fn process(&mut self, sample: impl Fn(u16) -> f32) -> (f32, f32) {
+        let mut frame = [0.0; MAX_SURROUND_CHANNELS];
+        let (mut left, mut right) = (0.0, 0.0);
+        for (channel, &(to_left, to_right)) in self.weights.iter().enumerate() {
+            frame[channel] = sample(channel as u16);
+            left += frame[channel] * to_left;
+            right += frame[channel] * to_right;
+        }
+        self.channels.push(frame);
+        (left, right)
+    }
+}
+
+/// Processes frames captured from the selected pair of channels before they're plotted.
+struct InputProcessor {
+    mid_side: bool,
+    decimator: Decimator,
+}
+
+impl InputProcessor {
+    fn new(options: &Options, decimation: u32) -> Self {
+        InputProcessor {
+            mid_side: options.mid_side,
+            decimator: Decimator::new(decimation),
+        }
+    }
+
+    /// Processes a frame, returning a frame to plot, if any.
+    fn process(&mut self, x: f32, y: f32, z: f32) -> Option<Frame> {
+        let (x, y) = match self.mid_side {
+            // mid is on X and side is on Y, the decoded left and right channels go on X and Y
+            true => (x + y, x - y),
+            false => (x, y),
+        };
+        self.decimator.push((x, y, z))
+    }
+}
+
+/// Reduces the sample rate by averaging every `factor` frames into one.
+struct Decimator {
+    factor: u32,
+    count: u32,
+    sum: Frame,
+}
+
+impl Decimator {
+    fn new(factor: u32) -> Self {
+        Decimator {
+            factor,
+            count: 0,
+            sum: (0.0, 0.0, 0.0),
+        }
+    }
+
+    /// Adds a frame, and returns the averaged frame once enough frames have been collected.
+    fn push(&mut self, (x, y, z): Frame) -> Option<Frame> {
+        self.sum.0 += x;
+        self.sum.1 += y;
+        self.sum.2 += z;
+        self.count += 1;
+        if self.count < self.factor {
+            return None
+        }
+        let factor = self.factor as f32;
+        let average = (self.sum.0 / factor, self.sum.1 / factor, self.sum.2 / factor);
+        self.count = 0;
+        self.sum = (0.0, 0.0, 0.0);
+        Some(average)
+    }
+}
+
+/// Interpolates points between frames with a windowed sinc, so that the beam follows the
+/// smooth path the signal takes between samples instead of cutting straight across.
+struct Oversampler {
+    factor: usize,
+    /// The interpolation filter, for every point between two frames. Each point is made out of
+    /// the frames from `TAPS - 1` before it to `TAPS` after it.
+    weights: Vec<[f32; Oversampler::TAPS * 2]>,
+    points: Vec<Frame>,
+}
+
+impl Oversampler {
+    /// How many frames on each side of a point are used to interpolate it.
+    const TAPS: usize = 4;
+
+    fn new(factor: u32) -> Self {
+        let factor = factor.max(1) as usize;
+        // a Lanczos window, which keeps the filter short without ringing too much
+        let sinc = |x: f32| match x == 0.0 {
+            true => 1.0,
+            false => (std::f32::consts::PI * x).sin() / (std::f32::consts::PI * x),
+        };
+        let lanczos = |x: f32| sinc(x) * sinc(x / Self::TAPS as f32);
+        let weights = (0..factor)
+            .map(|point| {
+                let offset = point as f32 / factor as f32;
+                let mut weights = [0.0; Self::TAPS * 2];
+                for (tap, weight) in weights.iter_mut().enumerate() {
+                    *weight = lanczos(offset - (tap as f32 - (Self::TAPS - 1) as f32));
+                }
+                // the weights have to add up to 1, or the picture would grow or shrink
+                let sum: f32 = weights.iter().sum();
+                weights.iter_mut().for_each(|weight| *weight /= sum);
+                weights
+            })
+            .collect();
+        Oversampler { factor, weights, points: Vec::new() }
+    }
+
+    /// Returns the frames with the interpolated points in between. Frames past the ends are
+    /// taken to be the same as the ones at the ends.
+    fn process<'a>(&'a mut self, frames: &'a [Frame]) -> &'a [Frame] {
+        if self.factor == 1 {
+            return frames
+        }
+        self.points.clear();
+        let last = frames.len() as isize - 1;
+        for i in 0..frames.len() as isize {
+            for weights in &self.weights {
+                let mut point = (0.0, 0.0, 0.0);
+                for (tap, weight) in weights.iter().enumerate() {
+                    let index = (i + tap as isize - (Self::TAPS - 1) as isize).clamp(0, last);
+                    let frame = frames[index as usize];
+                    point.0 += frame.0 * weight;
+                    point.1 += frame.1 * weight;
+                    point.2 += frame.2 * weight;
+                }
+                self.points.push(point);
+            }
+        }
+        &self.points
+    }
+}
+
+impl AudioState {
+    /// The name the scope identifies itself with to sound servers, unless another one is given in
+    /// the options.
+    #[cfg(all(feature = "jack", any(target_os = "linux", target_os = "dragonfly", target_os = "freebsd")))]
+    const DEFAULT_CLIENT_NAME: &'static str = "vectorscope";
+    /// ALSA devices that route to a sound server, in order of preference, along with the name of
+    /// the sound server.
+    #[cfg(any(target_os = "linux", target_os = "dragonfly", target_os = "freebsd"))]
+    const SOUND_SERVER_DEVICES: &'static [(&'static str, &'static str)] = &[
+        ("pipewire", "PipeWire"),
+        ("pulse", "PulseAudio"),
+    ];
+
+    /// How often to look for a device when waiting for one to appear, or for a lost device to come
+    /// back.
+    const DEVICE_POLL_INTERVAL: Duration = Duration::from_secs(1);
+
+    /// Sample rates we'd like to capture at, in order of preference. Some devices (notably a lot of
+    /// Mac hardware) only do 44.1 kHz.
+    const PREFERRED_SAMPLE_RATES: &'static [SampleRate] = &[SampleRate(48000), SampleRate(44100)];
+    /// The buffer size we'd like to capture with in automatic mode, if the device supports it.
+    const PREFERRED_BUFFER_SIZE: u32 = 128;
+    /// How often a file being played feeds its samples into the buffer.
+    const FILE_FEED_INTERVAL: Duration = Duration::from_millis(5);
+    /// The largest datagram that can be received from a socket.
+    const MAX_DATAGRAM_SIZE: usize = 65536;
+    /// How much audio the ring buffer between the input and the renderer can hold, in seconds.
+    /// This only has to cover a few frames' worth, for when rendering lags behind.
+    const RING_DURATION: f32 = 0.5;
+    /// The sample rate I/Q input is assumed to have unless it's given, which is what rtl_sdr
+    /// samples at by default.
+    const IQ_SAMPLE_RATE: u32 = 2048000;
+    /// The most channels an HTTP stream can have. Even surround streams have far fewer, so a
+    /// stream with more is broken, and each read would take a lot of memory.
+    const MAX_STREAM_CHANNELS: u16 = 64;
+    /// The color the compared input is drawn in, unless the options say otherwise.
+    const COMPARE_COLOR: (u8, u8, u8) = (0xff, 0x60, 0x40);
+    /// The amount of audio that's displayed at once. This is a duration rather than a sample
+    /// count, so that the picture looks the same regardless of sample rate.
+    const BUFFER_DURATION: f32 = 1024.0 / 48000.0;
+    /// The hues the channel pairs plotted alongside the selected one are drawn in, in order.
+    const PAIR_HUES: [f32; 3] = [200.0, 30.0, 300.0];
+    /// The length of the straight lines splines are made out of, in pixels.
+    const SPLINE_STEP_LENGTH: f32 = 4.0;
+    /// The most straight lines a spline between two points can be made out of.
+    const MAX_SPLINE_STEPS: usize = 64;
+    /// How long the warning badge stays on screen after input data is lost.
+    const DATA_LOSS_BADGE_DURATION: Duration = Duration::from_secs(2);
+
+    pub fn new(options: &Options) -> anyhow::Result<Self> {
+        let input = Self::open_input(options, false)?;
+        let envelope_timing = (
+            options.envelope_hold.unwrap_or(Duration::from_secs(1)),
+            options.envelope_decay.unwrap_or(Duration::from_secs(1)),
+        );
+        let mut state = AudioState {
+            options: options.clone(),
+            input: Some(input),
+            comparison: Self::open_comparison(options)?,
+            last_reconnect_attempt: Instant::now(),
+            samples_back: Vec::new(),
+            samples_front: Vec::new(),
+            pairs: Vec::new(),
+            front_start: 0,
+            last_sample: None,
+            max_line_length: options.blank,
+            z_mode: options.z_mode,
+            last_overruns: 0,
+            last_dropouts: 0,
+            last_data_loss: None,
+            received: 0,
+            recording: None,
+            oversampler: Oversampler::new(options.oversampling.unwrap_or(1)),
+            path_mode: options.path_mode,
+            hue_mode: options.hue_mode,
+            orientation: Orientation {
+                inverted: (options.invert_x, options.invert_y),
+                swapped: options.swap_axes,
+                goniometer: options.goniometer,
+                mirrored: (options.mirror_x, options.mirror_y),
+            },
+            calibration: Calibration {
+                gain: (options.gain_x.unwrap_or(1.0), options.gain_y.unwrap_or(1.0)),
+                display_gain: options.gain.unwrap_or(1.0),
+                offset: (options.offset_x.unwrap_or(0.0), options.offset_y.unwrap_or(0.0)),
+                rotation: options.rotation.unwrap_or(0.0),
+            },
+            agc: options.agc.then(Agc::new),
+            envelope: options.envelope.then(|| Envelope::new(envelope_timing.0, envelope_timing.1)),
+            envelope_timing,
+            stereo: Stereo::new(options.correlation_time.unwrap_or(Duration::from_millis(300))),
+            show_correlation: options.correlation,
+            show_width: options.stereo_width,
+            levels: Levels::new(options.peak_hold.unwrap_or(Duration::from_millis(1500))),
+            show_levels: options.levels,
+            show_balance: options.balance,
+            show_mid_side: options.mid_side_traces,
+            show_loudness: options.loudness,
+            loudness: None,
+            surround: SurroundLevels::new(),
+            display_mode: options.display_mode,
+            panes: options.panes.clone()
+                .unwrap_or_else(|| vec![DisplayMode::Xy, DisplayMode::Yt, DisplayMode::Spectrogram]),
+            sweeps: Sweeps {
+                trigger: options.trigger,
+                level: options.trigger_level.unwrap_or(0.0),
+                timebase: options.timebase.unwrap_or(Duration::from_millis(20)),
+                history: Vec::new(),
+                history_end: 0,
+            },
+            spectrogram: Spectrogram::new(
+                options.fft_size.unwrap_or(2048),
+                options.overlap.unwrap_or(0.75),
+            ),
+            spectrogram_colors: options.spectrogram_colormap.ramp(),
+            waterfall: Waterfall {
+                length: options.waterfall_length.unwrap_or(Duration::from_secs(3)),
+                slices: options.waterfall_slices.unwrap_or(24),
+                finished: VecDeque::new(),
+                current: Vec::new(),
+                current_end: 0,
+            },
+            intensity: options.intensity.unwrap_or(1.0),
+        };
+        if options.record {
+            state.start_recording();
+        }
+        Ok(state)
+    }
+
+    /// Returns how the channels are being plotted.
+    pub fn display_mode(&self) -> DisplayMode {
+        self.display_mode
+    }
+
+    /// Returns how many panes the dashboard is split into.
+    pub fn pane_count(&self) -> usize {
+        self.panes.len()
+    }
+
+    /// Returns how the channels are put on screen, which is always like on a goniometer in the
+    /// composite layout.
+    fn orientation(&self) -> Orientation {
+        let mut orientation = self.orientation;
+        orientation.goniometer |= self.display_mode == DisplayMode::Composite;
+        orientation
+    }
+
+    /// Switches between plotting the channels against each other and against time.
+    pub fn cycle_display_mode(&mut self) {
+        let index = DisplayMode::ALL.iter().position(|&mode| mode == self.display_mode).unwrap_or(0);
+        self.display_mode = DisplayMode::ALL[(index + 1) % DisplayMode::ALL.len()];
+        // there's nothing to show on the surround display without a surround input
+        if self.display_mode == DisplayMode::Surround && self.options.surround.is_none() {
+            self.display_mode = DisplayMode::ALL[(index + 2) % DisplayMode::ALL.len()];
+        }
+        eprintln!("display: {}", self.display_mode.name());
+    }
+
+    /// Multiplies the timebase by a factor, within the allowed range, and returns the new
+    /// timebase.
+    pub fn scale_timebase(&mut self, factor: f32) -> Duration {
+        let (min, max) = Options::TIMEBASE_RANGE;
+        let timebase = &mut self.sweeps.timebase;
+        *timebase = timebase.mul_f32(factor).clamp(min, max);
+        eprintln!("timebase: {:.1} ms", timebase.as_secs_f32() * 1000.0);
+        *timebase
+    }
+
+    /// Switches to the next way of drawing the beam's path.
+    pub fn cycle_path_mode(&mut self) {
+        let index = PathMode::ALL.iter().position(|&mode| mode == self.path_mode).unwrap_or(0);
+        self.path_mode = PathMode::ALL[(index + 1) % PathMode::ALL.len()];
+        eprintln!("drawing the beam's path as {}", self.path_mode.name());
+    }
+
+    /// Changes how the channels are laid out on screen, eg. to track down a miswired cable.
+    pub fn flip(&mut self, flip: Flip) {
+        let orientation = &mut self.orientation;
+        let on_off = |on: bool| if on { "on" } else { "off" };
+        match flip {
+            Flip::InvertX => {
+                orientation.inverted.0 = !orientation.inverted.0;
+                eprintln!("X channel polarity inverted: {}", on_off(orientation.inverted.0));
+            },
+            Flip::InvertY => {
+                orientation.inverted.1 = !orientation.inverted.1;
+                eprintln!("Y channel polarity inverted: {}", on_off(orientation.inverted.1));
+            },
+            Flip::Swap => {
+                orientation.swapped = !orientation.swapped;
+                eprintln!("channels swapped: {}", on_off(orientation.swapped));
+            },
+            Flip::Goniometer => {
+                orientation.goniometer = !orientation.goniometer;
+                match orientation.goniometer {
+                    true => eprintln!("showing mid and side on the axes"),
+                    false => eprintln!("showing left and right on the axes"),
+                }
+            },
+            Flip::MirrorHorizontally => {
+                orientation.mirrored.0 = !orientation.mirrored.0;
+                eprintln!("mirrored horizontally: {}", on_off(orientation.mirrored.0));
+            },
+            Flip::MirrorVertically => {
+                orientation.mirrored.1 = !orientation.mirrored.1;
+                eprintln!("mirrored vertically: {}", on_off(orientation.mirrored.1));
+            },
+        }
+    }
+
+    /// Moves the picture by the given amount along each axis.
+    pub fn nudge_offset(&mut self, x: f32, y: f32) {
+        let offset = &mut self.calibration.offset;
+        let (min, max) = Options::OFFSET_RANGE;
+        *offset = ((offset.0 + x).clamp(min, max), (offset.1 + y).clamp(min, max));
+        eprintln!("offset: X {:+.2}, Y {:+.2}", offset.0, offset.1);
+    }
+
+    /// Sets the gain and offset options to what they've been adjusted to while running. The
+    /// display gain is left alone while it's controlled automatically.
+    pub fn record_calibration(&self, options: &mut Options) -> anyhow::Result<()> {
+        let Calibration { gain, display_gain, offset, .. } = self.calibration;
+        if self.agc.is_none() {
+            options.set_value("gain", &display_gain.to_string())?;
+        }
+        options.set_value("gain-x", &gain.0.to_string())?;
+        options.set_value("gain-y", &gain.1.to_string())?;
+        options.set_value("offset-x", &offset.0.to_string())?;
+        options.set_value("offset-y", &offset.1.to_string())?;
+        Ok(())
+    }
+
+    /// Multiplies the gain of each axis by the given factor.
+    pub fn scale_gain(&mut self, x: f32, y: f32) {
+        let gain = &mut self.calibration.gain;
+        let (min, max) = Options::AXIS_GAIN_RANGE;
+        *gain = ((gain.0 * x).clamp(min, max), (gain.1 * y).clamp(min, max));
+        eprintln!("gain: X {:.2}, Y {:.2}", gain.0, gain.1);
+    }
+
+    /// Multiplies the gain of both axes by the given factor, and returns the new gain.
+    pub fn scale_display_gain(&mut self, factor: f32) -> f32 {
+        let gain = &mut self.calibration.display_gain;
+        let (min, max) = Options::GAIN_RANGE;
+        *gain = (*gain * factor).clamp(min, max);
+        eprintln!("display gain: {:.2}x", gain);
+        *gain
+    }
+
+    /// Multiplies the beam's intensity by a factor, within the allowed range, and returns the
+    /// new intensity.
+    pub fn scale_intensity(&mut self, factor: f32) -> f32 {
+        let (min, max) = Options::INTENSITY_RANGE;
+        self.intensity = (self.intensity * factor).clamp(min, max);
+        eprintln!("intensity: {:.2}x", self.intensity);
+        self.intensity
+    }
+
+    /// Shows or hides the outline of the farthest the picture has reached.
+    pub fn toggle_envelope(&mut self) {
+        self.envelope = match self.envelope {
+            Some(_) => None,
+            None => Some(Envelope::new(self.envelope_timing.0, self.envelope_timing.1)),
+        };
+        eprintln!("envelope: {}", if self.envelope.is_some() { "on" } else { "off" });
+    }
+
+    /// Shows or hides the correlation meter.
+    pub fn toggle_correlation(&mut self) {
+        self.show_correlation = !self.show_correlation;
+        eprintln!("correlation: {}", if self.show_correlation { "on" } else { "off" });
+    }
+
+    /// Shows or hides the stereo width meter.
+    pub fn toggle_width(&mut self) {
+        self.show_width = !self.show_width;
+        eprintln!("stereo width: {}", if self.show_width { "on" } else { "off" });
+    }
+
+    /// Shows or hides the level meters.
+    pub fn toggle_levels(&mut self) {
+        self.show_levels = !self.show_levels;
+        eprintln!("level meters: {}", if self.show_levels { "on" } else { "off" });
+    }
+
+    /// Shows or hides the balance meter.
+    pub fn toggle_balance(&mut self) {
+        self.show_balance = !self.show_balance;
+        eprintln!("balance: {}", if self.show_balance { "on" } else { "off" });
+    }
+
+    /// Shows or hides the traces of the mid and side signals.
+    pub fn toggle_mid_side(&mut self) {
+        self.show_mid_side = !self.show_mid_side;
+        eprintln!("mid/side traces: {}", if self.show_mid_side { "on" } else { "off" });
+    }
+
+    /// Starts or stops measuring the loudness. The integrated loudness starts over every time
+    /// it's turned on.
+    pub fn toggle_loudness(&mut self) {
+        self.show_loudness = !self.show_loudness;
+        self.loudness = None;
+        eprintln!("loudness: {}", if self.show_loudness { "on" } else { "off" });
+    }
+
+    /// Turns automatic gain control on or off.
+    pub fn toggle_agc(&mut self) {
+        self.agc = match self.agc {
+            Some(_) => None,
+            None => Some(Agc::new()),
+        };
+        eprintln!("automatic gain control: {}", if self.agc.is_some() { "on" } else { "off" });
+    }
+
+    /// Turns the picture counterclockwise by the given number of degrees.
+    pub fn rotate(&mut self, degrees: f32) {
+        let rotation = &mut self.calibration.rotation;
+        *rotation = (*rotation + degrees).rem_euclid(360.0);
+        eprintln!("rotation: {:.0}°", rotation);
+    }
+
+    /// Switches to the next way of coloring the trace.
+    pub fn cycle_hue_mode(&mut self) {
+        let index = HueMode::ALL.iter().position(|&mode| mode == self.hue_mode).unwrap_or(0);
+        self.hue_mode = HueMode::ALL[(index + 1) % HueMode::ALL.len()];
+        eprintln!("coloring the trace by hue: {}", self.hue_mode.name());
+    }
+
+    /// Returns whether any of the trace is drawn in colors of its own rather than with the
+    /// palette: colored by hue, or the traces of other channel pairs or a compared input.
+    pub fn colors_trace(&self) -> bool {
+        self.hue_mode != HueMode::Off || !self.pairs.is_empty() || self.comparison.is_some()
+    }
+
+    /// Starts recording the input to a new file in the recording directory, or stops recording
+    /// if it's already being recorded.
+    pub fn toggle_recording(&mut self) {
+        match self.recording.take() {
+            Some(recording) => Self::stop_recording(recording),
+            None => self.start_recording(),
+        }
+    }
+
+    fn start_recording(&mut self) {
+        let sample_rate = match &self.input {
+            Some(input) => input.sample_rate,
+            None => {
+                eprintln!("cannot record while there's no input");
+                return
+            },
+        };
+        let path = crate::timestamped_path(self.options.record_dir.as_deref(), "wav");
+        // the Z channel is only recorded if there is one
+        let channels = if self.options.channels.z.is_some() { 3 } else { 2 };
+        match WavWriter::create(&path, sample_rate, channels) {
+            Ok(recording) => {
+                eprintln!("recording to {}", path.display());
+                self.recording = Some(recording);
+            },
+            Err(error) => eprintln!("cannot start recording: {:#}", error),
+        }
+    }
+
+    fn stop_recording(mut recording: WavWriter) {
+        match recording.finish() {
+            Ok(()) => eprintln!("stopped recording"),
+            Err(error) => eprintln!("cannot finish the recording: {:#}", error),
+        }
+    }
+
+    /// Returns how many frames of input of the given sample rate are displayed at once.
+    fn buffer_size(sample_rate: u32) -> usize {
+        (sample_rate as f32 * Self::BUFFER_DURATION) as usize
+    }
+
+    /// Returns how many frames of the given sample rate the ring buffer between the input and
+    /// the renderer should hold.
+    fn ring_capacity(sample_rate: u32) -> usize {
+        (sample_rate as f32 * Self::RING_DURATION) as usize
+    }
+
+    /// Opens the input selected by the options and starts capturing samples.
+    fn open_input(options: &Options, reconnecting: bool) -> anyhow::Result<Input> {
+        if options.raw_channels.is_some() && !Self::is_raw_input(options) {
+            bail!("a channel count only applies to raw input, select the channels to plot with <x>,<y>");
+        }
+        match &options.command {
+            Command::Play(path) => Self::open_file(options, path),
+            _ if options.stdin => Self::open_stdin(options),
+            #[cfg(unix)]
+            _ if options.socket.is_some() => Self::open_socket(options, options.socket.as_deref().unwrap()),
+            #[cfg(not(unix))]
+            _ if options.socket.is_some() => bail!("--socket is only supported on Unix-like systems"),
+            _ if options.url.is_some() => Self::open_url(options, options.url.as_deref().unwrap()),
+            _ if options.listen.is_some() => Self::open_udp(options, options.listen.as_deref().unwrap()),
+            // I/Q samples come from standard input unless they're sent some other way
+            _ if options.iq => Self::open_stdin(options),
+            _ => Self::open_device(options, reconnecting),
+        }
+    }
+
+    /// Returns whether the input selected by the options is raw samples, whose channel count
+    /// has to be given.
+    fn is_raw_input(options: &Options) -> bool {
+        match &options.command {
+            Command::Play(_) => false,
+            _ => options.stdin
+                || options.socket.is_some()
+                || (options.url.is_none() && (options.listen.is_some() || options.iq)),
+        }
+    }
+
+    /// Opens the input to compare against the one being displayed, if the options select one.
+    /// Files are played silently, and only the selected pair of channels is compared.
+    fn open_comparison(options: &Options) -> anyhow::Result<Option<Comparison>> {
+        let source = match &options.compare {
+            Some(source) => source,
+            None => return Ok(None),
+        };
+        // the compared input is opened like the input, but from the source it's given
+        let mut compared = options.clone();
+        compared.stdin = false;
+        compared.socket = None;
+        compared.url = None;
+        compared.listen = None;
+        compared.loopback = false;
+        compared.pairs = None;
+        compared.surround = None;
+        compared.follow_transport = false;
+        // --iq describes the main input's stream, not the compared one
+        compared.iq = false;
+        compared.raw_channels = None;
+        compared.raw_format = Default::default();
+        compared.decimation = Default::default();
+        match source {
+            CompareSource::File(path) => {
+                compared.command = Command::Play(path.clone());
+                compared.mute = true;
+            },
+            CompareSource::Device(device) => {
+                compared.command = Command::Scope;
+                compared.device = Some(device.clone());
+            },
+        }
+        let input = Self::open_input(&compared, false).context("cannot open the input to compare")?;
+        let color = options.compare_color.unwrap_or(Self::COMPARE_COLOR);
+        Ok(Some(Comparison {
+            input,
+            samples_back: Vec::new(),
+            samples_front: Vec::new(),
+            gain: options.compare_gain.unwrap_or(1.0),
+            color: (color.0 as f32 / 255.0, color.1 as f32 / 255.0, color.2 as f32 / 255.0),
+        }))
+    }
+
+    /// Opens the input device selected by the options and starts capturing samples.
+    fn open_device(options: &Options, reconnecting: bool) -> anyhow::Result<Input> {
+        // the host only matters for JACK
+        // the monitor is only selected until the stream is open, so that devices opened later (eg.
+        // the compared one) still record from the default source
+        #[cfg(any(target_os = "linux", target_os = "dragonfly", target_os = "freebsd"))]
+        let _monitor = match options.loopback {
+            true => Some(MonitorSource::select()),
+            false => None,
+        };
+        #[cfg_attr(not(all(feature = "jack", any(target_os = "linux", target_os = "dragonfly", target_os = "freebsd"))), allow(unused_variables))]
+        let (device, host_id) = Self::find_input_device(options, reconnecting)?;
+        let has_channels = Self::has_channels(&device, options, options.channels.required_channels())?;
+        let (channels, mono) = Self::select_channels(options, has_channels);
+        // the other pairs are only captured if the device has the channels for all of them
+        let all_pairs = Self::pair_channels(options, channels, mono);
+        let required = match options.surround {
+            Some(layout) => layout.speakers().len() as u16,
+            None if Self::has_channels(&device, options, all_pairs)? => all_pairs,
+            None => channels.required_channels(),
+        };
+        let (config, sample_format) = Self::negotiate_config(&device, options, required)?;
+        let weights = Self::downmix_weights(options, config.channels)?;
+        let name = device.name().unwrap_or_else(|_| "<unknown device>".into());
+        eprintln!(
+            "capturing from {} ({} channels, {} Hz, {:?})",
+            name,
+            config.channels,
+            config.sample_rate.0,
+            sample_format,
+        );
+
+        let decimation = Self::decimation(options, config.sample_rate.0);
+
+        let status = Arc::new(StreamStatus::default());
+        let build_stream = |config: &StreamConfig| {
+            let capacity = Self::ring_capacity(config.sample_rate.0 / decimation);
+            let (producer, consumer) = ring_buffer(capacity);
+            let (pairs, pair_consumers) =
+                Self::capture_pairs(options, (channels, mono), config.channels, decimation, capacity);
+            let (downmix, surround) =
+                Self::downmix(weights.as_deref(), Self::ring_capacity(config.sample_rate.0));
+            let (clock_writer, clock) = Clock::new();
+            let (processor, status) = (InputProcessor::new(options, decimation), status.clone());
+            let output = (producer, (pairs, downmix), clock_writer, status);
+            let stream = match sample_format {
+                SampleFormat::F32 => Self::build_stream::<f32>(&device, config, channels, processor, output),
+                SampleFormat::I16 => Self::build_stream::<i16>(&device, config, channels, processor, output),
+                SampleFormat::U16 => Self::build_stream::<u16>(&device, config, channels, processor, output),
+            };
+            stream.map(|stream| (stream, consumer, (pair_consumers, surround), clock))
+        };
+        let (stream, samples, (pairs, surround), clock) = match build_stream(&config) {
+            // some devices advertise buffer sizes they can't actually be opened with
+            Err(error) if config.buffer_size != BufferSize::Default => {
+                eprintln!(
+                    "cannot capture with a buffer size of {:?} ({}), using the default buffer size instead",
+                    config.buffer_size,
+                    error,
+                );
+                build_stream(&StreamConfig { buffer_size: BufferSize::Default, ..config.clone() })?
+            },
+            result => result?,
+        };
+        stream.play()?;
+        #[cfg(all(feature = "jack", any(target_os = "linux", target_os = "dragonfly", target_os = "freebsd")))]
+        if host_id == cpal::HostId::Jack {
+            Self::set_up_jack_ports(&device, options);
+        }
+
+        Ok(Input {
+            stream: Some(stream),
+            samples,
+            pairs,
+            surround,
+            clock: Some(clock),
+            name,
+            sample_rate: config.sample_rate.0 / decimation,
+            mono,
+            status,
+            last_callbacks: 0,
+            last_activity: Instant::now(),
+            #[cfg(all(feature = "jack", any(target_os = "linux", target_os = "dragonfly", target_os = "freebsd")))]
+            transport_client: match host_id == cpal::HostId::Jack && options.follow_transport {
+                true => Self::jack_transport_client(&device),
+                false => None,
+            },
+        })
+    }
+
+    /// Sets up an input fed with raw samples in the format given in the options, returning the
+    /// decoder to feed it through.
+    fn open_raw_input(options: &Options, source: &str) -> anyhow::Result<(RawDecoder, Input)> {
+        let channels = options.raw_channels.unwrap_or(2);
+        if channels == 0 {
+            bail!("raw input must have at least one channel");
+        }
+        let sample_rate = options.sample_rate.unwrap_or(match options.iq {
+            true => Self::IQ_SAMPLE_RATE,
+            false => Self::PREFERRED_SAMPLE_RATES[0].0,
+        });
+        if sample_rate == 0 {
+            bail!("raw input must have a sample rate above 0 Hz");
+        }
+        let has_channels = channels >= options.channels.required_channels();
+        let (pair, mono) = Self::select_channels(options, has_channels);
+        Self::check_channels(pair, channels)?;
+        eprintln!("reading from {} ({} channels, {} Hz)", source, channels, sample_rate);
+        let decimation = Self::decimation(options, sample_rate);
+
+        let status = Arc::new(StreamStatus::default());
+        let capacity = Self::ring_capacity(sample_rate / decimation);
+        let (producer, consumer) = ring_buffer(capacity);
+        let (pairs, pair_consumers) =
+            Self::capture_pairs(options, (pair, mono), channels, decimation, capacity);
+        let weights = Self::downmix_weights(options, channels)?;
+        let (downmix, surround) = Self::downmix(weights.as_deref(), Self::ring_capacity(sample_rate));
+        let decoder = RawDecoder {
+            format: options.raw_format,
+            channels: channels as usize,
+            sample_rate,
+            pair,
+            processor: InputProcessor::new(options, decimation),
+            samples: producer,
+            pairs,
+            downmix,
+            status: status.clone(),
+        };
+        let input = Input {
+            stream: None,
+            samples: consumer,
+            pairs: pair_consumers,
+            surround,
+            clock: None,
+            name: source.to_owned(),
+            sample_rate: sample_rate / decimation,
+            mono,
+            status,
+            last_callbacks: 0,
+            last_activity: Instant::now(),
+            #[cfg(all(feature = "jack", any(target_os = "linux", target_os = "dragonfly", target_os = "freebsd")))]
+            transport_client: None,
+        };
+        Ok((decoder, input))
+    }
+
+    /// Starts reading raw samples from standard input into the given buffer.
+    fn open_stdin(options: &Options) -> anyhow::Result<Input> {
+        let (decoder, input) = Self::open_raw_input(options, "standard input")?;
+        Self::spawn_stream_reader(std::io::stdin(), decoder, "standard input");
+        Ok(input)
+    }
+
+    /// Connects to an HTTP audio stream (eg. an Icecast mount) and starts reading its samples into
+    /// the given buffer. Only uncompressed WAV and L16 streams can be decoded.
+    fn open_url(options: &Options, url: &str) -> anyhow::Result<Input> {
+        let mut response = http::get(url)?;
+        let mut parameters = response.content_type.split(';').map(str::trim);
+        let media_type = parameters.next().unwrap_or_default().to_ascii_lowercase();
+        // the stream's format overrides the raw input options
+        let mut options = options.clone();
+        match media_type.as_str() {
+            "audio/wav" | "audio/wave" | "audio/x-wav" | "audio/vnd.wave" => {
+                let (format, _) = Wav::read_header(&mut response.body)
+                    .with_context(|| format!("cannot decode the stream from {}", url))?;
+                options.raw_format = format.sample_format;
+                options.raw_channels = Some(format.channels);
+                options.sample_rate = Some(format.sample_rate);
+            },
+            "audio/l16" => {
+                options.raw_format = RawFormat::S16Be;
+                // L16 is mono unless stated otherwise
+                options.raw_channels = Some(1);
+                for parameter in parameters {
+                    match parameter.split_once('=') {
+                        Some(("rate", rate)) => match rate.parse() {
+                            Ok(rate) if rate > 0 => options.sample_rate = Some(rate),
+                            _ => bail!("the stream from {} has an invalid sample rate: {}", url, rate),
+                        },
+                        Some(("channels", channels)) => match channels.parse() {
+                            Ok(channels) if channels > 0 => options.raw_channels = Some(channels),
+                            _ => bail!("the stream from {} has an invalid channel count: {}", url, channels),
+                        },
+                        _ => (),
+                    }
+                }
+            },
+            _ => bail!(
+                "cannot decode {} streams, only uncompressed WAV and L16 streams are supported",
+                response.content_type,
+            ),
+        }
+        let channels = options.raw_channels.unwrap_or_default();
+        if channels > Self::MAX_STREAM_CHANNELS {
+            bail!("the stream from {} has too many channels ({})", url, channels);
+        }
+        let (decoder, input) = Self::open_raw_input(&options, url)?;
+        Self::spawn_stream_reader(response.body, decoder, "the stream");
+        Ok(input)
+    }
+
+    /// Starts a thread reading raw samples from a stream until it ends. Input that arrives faster
+    /// than real time (eg. a file piped in, or the burst a streaming server starts with) is read
+    /// at its sample rate.
+    fn spawn_stream_reader(
+        mut reader: impl Read + Send + 'static,
+        mut decoder: RawDecoder,
+        source: &'static str,
+    ) {
+        std::thread::spawn(move || {
+            let frame_size = decoder.frame_size();
+            let mut buffer = vec![0; frame_size * 1024];
+            // the number of bytes at the start of the buffer left over from a partial frame
+            let mut pending = 0;
+            let start = Instant::now();
+            let mut frames_read = 0;
+            loop {
+                let length = match reader.read(&mut buffer[pending..]) {
+                    Ok(0) => break,
+                    Ok(length) => pending + length,
+                    Err(error) if error.kind() == ErrorKind::Interrupted => continue,
+                    Err(error) => {
+                        eprintln!("cannot read from {}: {}", source, error);
+                        break
+                    },
+                };
+                let frames = decoder.push(&buffer[..length]);
+                buffer.copy_within(frames * frame_size..length, 0);
+                pending = length - frames * frame_size;
+                frames_read += frames;
+
+                let position = Duration::from_secs_f64(frames_read as f64 / decoder.sample_rate as f64);
+                if let Some(ahead) = position.checked_sub(start.elapsed()) {
+                    std::thread::sleep(ahead);
+                }
+            }
+        });
+    }
+
+    /// Binds a Unix datagram socket at the given path and starts reading raw samples sent to it
+    /// into the given buffer. Every datagram holds a number of whole frames.
+    #[cfg(unix)]
+    fn open_socket(options: &Options, path: &Path) -> anyhow::Result<Input> {
+        // a socket file left behind by a previous run would make binding fail
+        if std::fs::metadata(path).is_ok_and(|metadata| metadata.file_type().is_socket()) {
+            std::fs::remove_file(path)?;
+        }
+        let socket = UnixDatagram::bind(path)
+            .with_context(|| format!("cannot bind a socket at {}", path.display()))?;
+        let source = path.display().to_string();
+        let (decoder, input) = Self::open_raw_input(options, &source)?;
+        Self::spawn_datagram_reader(move |buffer| socket.recv(buffer), decoder, false);
+        Ok(input)
+    }
+
+    /// Binds a UDP socket at the given address and starts reading raw samples sent to it into the
+    /// given buffer. Every datagram holds a number of whole frames, optionally preceded by an RTP
+    /// header.
+    fn open_udp(options: &Options, address: &str) -> anyhow::Result<Input> {
+        let socket = UdpSocket::bind(address)
+            .with_context(|| format!("cannot listen on {}", address))?;
+        let source = format!("UDP {}", socket.local_addr()?);
+        let (decoder, input) = Self::open_raw_input(options, &source)?;
+        Self::spawn_datagram_reader(move |buffer| socket.recv(buffer), decoder, options.rtp);
+        Ok(input)
+    }
+
+    /// Starts a thread receiving datagrams and decoding the samples in them. With `rtp`, the
+    /// datagrams are RTP packets, whose headers are skipped.
+    fn spawn_datagram_reader(
+        mut receive: impl FnMut(&mut [u8]) -> std::io::Result<usize> + Send + 'static,
+        mut decoder: RawDecoder,
+        rtp: bool,
+    ) {
+        std::thread::spawn(move || {
+            let mut buffer = vec![0; Self::MAX_DATAGRAM_SIZE];
+            let mut next_sequence = None;
+            loop {
+                let datagram = match receive(&mut buffer) {
+                    Ok(length) => &buffer[..length],
+                    Err(error) if error.kind() == ErrorKind::Interrupted => continue,
+                    Err(error) => {
+                        eprintln!("cannot receive samples: {}", error);
+                        break
+                    },
+                };
+                let payload = match rtp {
+                    true => Self::rtp_payload(datagram).map(|(sequence, payload)| {
+                        // packets that arrive late or twice are decoded anyway, only the ones
+                        // skipped over count as lost
+                        let skipped = sequence.wrapping_sub(next_sequence.unwrap_or(sequence));
+                        if skipped != 0 && skipped < 0x8000 {
+                            decoder.status.dropouts.fetch_add(1, Ordering::Relaxed);
+                        }
+                        next_sequence = Some(sequence.wrapping_add(1));
+                        payload
+                    }),
+                    false => Some(datagram),
+                };
+                if let Some(payload) = payload {
+                    decoder.push(payload);
+                }
+            }
+        });
+    }
+
+    /// Returns the sequence number and payload of an RTP packet, or `None` if the packet is
+    /// malformed.
+    fn rtp_payload(packet: &[u8]) -> Option<(u16, &[u8])> {
+        const HEADER_SIZE: usize = 12;
+        let first = *packet.first()?;
+        let (has_padding, has_extension) = (first & 0x20 != 0, first & 0x10 != 0);
+        let csrc_count = (first & 0x0F) as usize;
+        let mut start = HEADER_SIZE + csrc_count * 4;
+        if has_extension {
+            let extension = packet.get(start..start + 4)?;
+            let extension_words = u16::from_be_bytes([extension[2], extension[3]]) as usize;
+            start += 4 + extension_words * 4;
+        }
+        let mut end = packet.len();
+        if has_padding {
+            end = end.checked_sub(*packet.last()? as usize)?;
+        }
+        let sequence = u16::from_be_bytes([*packet.get(2)?, *packet.get(3)?]);
+        Some((sequence, packet.get(start..end)?))
+    }
+
+    /// Opens a WAV file and starts feeding its samples into the given buffer as it plays. Unless
+    /// muted, the file is played through the default output device, whose stream then drives the
+    /// playback so that the picture stays in sync with the sound.
+    fn open_file(options: &Options, path: &Path) -> anyhow::Result<Input> {
+        let wav = Arc::new(Wav::read(path)?);
+        let has_channels = wav.channels >= options.channels.required_channels();
+        let (pair, mono) = Self::select_channels(options, has_channels);
+        Self::check_channels(pair, wav.channels)?;
+        eprintln!("playing {} ({} channels, {} Hz)", path.display(), wav.channels, wav.sample_rate);
+        let decimation = Self::decimation(options, wav.sample_rate);
+
+        let weights = Self::downmix_weights(options, wav.channels)?;
+        let status = Arc::new(StreamStatus::default());
+        let new_player = || {
+            let capacity = Self::ring_capacity(wav.sample_rate / decimation);
+            let (producer, consumer) = ring_buffer(capacity);
+            let (pairs, pair_consumers) =
+                Self::capture_pairs(options, (pair, mono), wav.channels, decimation, capacity);
+            let (downmix, surround) =
+                Self::downmix(weights.as_deref(), Self::ring_capacity(wav.sample_rate));
+            let player = FilePlayer {
+                wav: wav.clone(),
+                position: 0,
+                pair,
+                processor: InputProcessor::new(options, decimation),
+                samples: producer,
+                pairs,
+                downmix,
+                status: status.clone(),
+                resampler: None,
+            };
+            (player, (consumer, pair_consumers, surround))
+        };
+        let output = match options.mute {
+            true => None,
+            false => {
+                let (player, consumer) = new_player();
+                match Self::build_output_stream(wav.sample_rate, player) {
+                    Ok(stream) => Some((stream, consumer)),
+                    Err(error) => {
+                        eprintln!("cannot play the file through the output device: {}", error);
+                        eprintln!("the file will be displayed without sound");
+                        None
+                    },
+                }
+            },
+        };
+        let (stream, (samples, pairs, surround)) = match output {
+            Some((stream, consumers)) => (Some(stream), consumers),
+            None => {
+                let (player, consumers) = new_player();
+                Self::spawn_file_feeder(player, wav.sample_rate);
+                (None, consumers)
+            },
+        };
+
+        Ok(Input {
+            stream,
+            samples,
+            pairs,
+            surround,
+            clock: None,
+            name: path.display().to_string(),
+            sample_rate: wav.sample_rate / decimation,
+            mono,
+            status,
+            last_callbacks: 0,
+            last_activity: Instant::now(),
+            #[cfg(all(feature = "jack", any(target_os = "linux", target_os = "dragonfly", target_os = "freebsd")))]
+            transport_client: None,
+        })
+    }
+
+    /// Starts a thread that plays a file silently, at its sample rate.
+    fn spawn_file_feeder(mut player: FilePlayer, sample_rate: u32) {
+        std::thread::spawn(move || {
+            let start = Instant::now();
+            while !player.status.finished.load(Ordering::Relaxed) {
+                std::thread::sleep(Self::FILE_FEED_INTERVAL);
+                let due = (start.elapsed().as_secs_f64() * sample_rate as f64) as usize;
+                player.play(due.saturating_sub(player.position), |_| ());
+            }
+        });
+    }
+
+    /// Builds and starts a stream playing a file through the default output device, at the file's
+    /// sample rate if the device supports it, or else converted to the closest one it does.
+    fn build_output_stream(sample_rate: u32, mut player: FilePlayer) -> anyhow::Result<Stream> {
+        let device = cpal::default_host().default_output_device()
+            .ok_or_else(|| anyhow!("there is no output device"))?;
+        let (range, rate) = device.supported_output_configs()?
+            .map(|range| {
+                let rate = sample_rate.clamp(range.min_sample_rate().0, range.max_sample_rate().0);
+                (range, rate)
+            })
+            .max_by_key(|(range, rate)| (
+                Reverse(rate.abs_diff(sample_rate)),
+                range.channels() == 2,
+                range.sample_format() == SampleFormat::F32,
+            ))
+            .ok_or_else(|| anyhow!("the output device does not support any output configs"))?;
+        if rate != sample_rate {
+            eprintln!("the output device cannot play at {} Hz, converting to {} Hz", sample_rate, rate);
+            player.resampler = Some(Resampler::new(sample_rate, rate, player.wav.channels));
+        }
+        let sample_format = range.sample_format();
+        let config = range.with_sample_rate(SampleRate(rate)).config();
+        let stream = match sample_format {
+            SampleFormat::F32 => Self::build_output_stream_of::<f32>(&device, &config, player)?,
+            SampleFormat::I16 => Self::build_output_stream_of::<i16>(&device, &config, player)?,
+            SampleFormat::U16 => Self::build_output_stream_of::<u16>(&device, &config, player)?,
+        };
+        stream.play()?;
+        Ok(stream)
+    }
+
+    fn build_output_stream_of<T: Sample>(
+        device: &Device,
+        config: &StreamConfig,
+        mut player: FilePlayer,
+    ) -> Result<Stream, BuildStreamError> {
+        let channels = config.channels as usize;
+        device.build_output_stream(
+            config,
+            move |out_samples: &mut [T], _info| {
+                let mut out_frames = out_samples.chunks_exact_mut(channels);
+                player.play_output(out_frames.len(), |frame| {
+                    if let Some(out_frame) = out_frames.next() {
+                        // files with fewer channels than the device have their last channel
+                        // repeated, so that mono files play on both speakers
+                        for (channel, sample) in out_frame.iter_mut().enumerate() {
+                            *sample = T::from(&frame[channel.min(frame.len() - 1)]);
+                        }
+                    }
+                });
+                // silence after the end of the file
+                for sample in out_frames.flatten() {
+                    *sample = T::from(&0.0f32);
+                }
+            },
+            |error| eprintln!("audio error: {}", error),
+        )
+    }
+
+    /// Picks the pair of channels to capture and the mono mode. Inputs that don't have enough
+    /// channels for the selected pair have a single channel plotted diagonally.
+    fn select_channels(options: &Options, has_channels: bool) -> (ChannelPair, Option<MonoMode>) {
+        let mut mono = options.mono;
+        if mono.is_none() && !has_channels {
+            eprintln!("the input doesn't have enough channels, plotting a single channel diagonally");
+            mono = Some(MonoMode::Diagonal);
+        }
+        // in mono mode, the X channel is captured for both axes
+        let channels = match mono {
+            Some(_) => ChannelPair { y: options.channels.x, ..options.channels },
+            None => options.channels,
+        };
+        (channels, mono)
+    }
+
+    /// Makes sure the input has the channels selected to be plotted, which the mono fallback can
+    /// leave beyond its last channel when the X or Z channel is.
+    fn check_channels(pair: ChannelPair, channels: u16) -> anyhow::Result<()> {
+        if pair.required_channels() > channels {
+            bail!(
+                "the input has {} channels, but channel {} is selected",
+                channels,
+                pair.required_channels(),
+            );
+        }
+        Ok(())
+    }
+
+    /// Returns the number of channels that need to be captured to get all the channel pairs to
+    /// plot, starting at the selected one.
+    fn pair_channels(options: &Options, pair: ChannelPair, mono: Option<MonoMode>) -> u16 {
+        let last = match mono {
+            Some(_) => pair,
+            None => pair.shift(options.pairs.unwrap_or(1) - 1),
+        };
+        last.required_channels().max(pair.required_channels())
+    }
+
+    /// Sets up the capture of the channel pairs plotted alongside the selected one, as many of
+    /// them as the input has the channels for, returning the buffers they're captured into.
+    fn capture_pairs(
+        options: &Options,
+        (pair, mono): (ChannelPair, Option<MonoMode>),
+        channels: u16,
+        decimation: u32,
+        capacity: usize,
+    ) -> (PairCapture, Vec<Consumer<Frame>>) {
+        let wanted = options.pairs.unwrap_or(1) - 1;
+        let pairs: Vec<ChannelPair> = match (mono, options.surround) {
+            (None, None) => (1..=wanted)
+                .map(|i| pair.shift(i))
+                .take_while(|pair| pair.required_channels() <= channels)
+                .collect(),
+            // a single channel is captured in mono mode, and a surround input is mixed down into
+            // a single pair
+            _ => Vec::new(),
+        };
+        if pairs.len() < wanted as usize {
+            eprintln!("the input only has the channels for {} channel pairs", pairs.len() + 1);
+        }
+        let (pairs, consumers) = pairs.into_iter()
+            .map(|pair| {
+                let (producer, consumer) = ring_buffer(capacity);
+                ((pair, InputProcessor::new(options, decimation), producer), consumer)
+            })
+            .unzip();
+        (PairCapture { pairs }, consumers)
+    }
+
+    /// Works out how much of every channel of a surround input goes into either side of the
+    /// stereo mix, if the options say the input is one.
+    fn downmix_weights(options: &Options, channels: u16) -> anyhow::Result<Option<Vec<(f32, f32)>>> {
+        let layout = match options.surround {
+            Some(layout) => layout,
+            None => return Ok(None),
+        };
+        let speakers = layout.speakers();
+        if (channels as usize) < speakers.len() {
+            let (name, count) = (layout.name(), speakers.len());
+            bail!("the input has {} channels, but {} surround has {}", channels, name, count);
+        }
+        let coefficients = options.downmix.as_deref().unwrap_or_else(|| layout.downmix());
+        if coefficients.len() != speakers.len() {
+            bail!(
+                "{} surround takes {} downmix coefficients, not {}",
+                layout.name(),
+                speakers.len(),
+                coefficients.len(),
+            );
+        }
+        let weights = speakers.iter()
+            .zip(coefficients)
+            .map(|(&(_, angle), &coefficient)| match angle {
+                Some(angle) if angle < 0.0 => (coefficient, 0.0),
+                Some(angle) if angle > 0.0 => (0.0, coefficient),
+                // the center and the LFE go into both sides
+                _ => (coefficient, coefficient),
+            })
+            .collect();
+        Ok(Some(weights))
+    }
+
+    /// Sets up the mixdown of a surround input with the given weights, if it is one, returning
+    /// the buffer all of its channels are captured into.
+    fn downmix(
+        weights: Option<&[(f32, f32)]>,
+        capacity: usize,
+    ) -> (Option<Downmix>, Option<Consumer<SurroundFrame>>) {
+        weights
+            .map(|weights| {
+                let (producer, consumer) = ring_buffer(capacity);
+                (Downmix { weights: weights.to_vec(), channels: producer }, consumer)
+            })
+            .unzip()
+    }
+
+    /// Returns the factor to decimate input of the given sample rate by.
+    fn decimation(options: &Options, sample_rate: u32) -> u32 {
+        let decimation = match options.decimation {
+            DecimationOption::Off => 1,
+            DecimationOption::Auto =>
+                (sample_rate as f32 / Self::PREFERRED_SAMPLE_RATES[0].0 as f32).round() as u32,
+            DecimationOption::Factor(factor) => factor,
+        }.max(1);
+        if decimation > 1 {
+            eprintln!("decimating by a factor of {}", decimation);
+        }
+        decimation
+    }
+
+    /// Builds a stream capturing samples of the device's native format. The selected pair of
+    /// channels is converted to f32 and processed in the callback, and the processed frames are
+    /// sent out along with the callback's timestamps.
+    fn build_stream<T: Sample>(
+        device: &Device,
+        config: &StreamConfig,
+        pair: ChannelPair,
+        mut processor: InputProcessor,
+        (mut samples, (mut pairs, mut downmix), mut clock, status):
+            (Producer<Frame>, (PairCapture, Option<Downmix>), ClockWriter, Arc<StreamStatus>),
+    ) -> Result<Stream, BuildStreamError> {
+        let error_status = status.clone();
+        let channels = config.channels as usize;
+        device.build_input_stream(
+            config,
+            move |in_samples: &[T], info| {
+                status.callbacks.fetch_add(1, Ordering::Relaxed);
+                let mut frames = 0;
+                // only the selected pairs of channels are plotted, the rest is thrown away
+                for frame in in_samples.chunks_exact(channels) {
+                    let sample = |channel: u16| frame[channel as usize].to_f32();
+                    let (x, y) = match &mut downmix {
+                        Some(downmix) => downmix.process(sample),
+                        None => (sample(pair.x), sample(pair.y)),
+                    };
+                    let z = pair.z.map_or(1.0, sample);
+                    if let Some(processed) = processor.process(x, y, z) {
+                        samples.push(processed);
+                        frames += 1;
+                    }
+                    pairs.push(sample);
+                }
+                clock.tick(info.timestamp().callback, frames);
+            },
+            move |error| {
+                eprintln!("audio error: {}", error);
+                match error {
+                    StreamError::DeviceNotAvailable =>
+                        error_status.disconnected.store(true, Ordering::Relaxed),
+                    // the stream keeps going after other errors, which are mostly xruns, but some
+                    // data has been lost
+                    StreamError::BackendSpecific { .. } => {
+                        error_status.dropouts.fetch_add(1, Ordering::Relaxed);
+                    },
+                }
+            },
+        )
+    }
+
+    /// Checks whether the input is still alive, and if it isn't, periodically tries to open it
+    /// again.
+    fn check_input(&mut self) {
+        if let Some(comparison) = &mut self.comparison {
+            if !comparison.input.is_alive() {
+                eprintln!("lost the input being compared, showing the input on its own");
+                self.comparison = None;
+            }
+        }
+        if let Some(input) = &mut self.input {
+            if input.is_alive() {
+                return
+            }
+            eprintln!("lost the input device, waiting for it to come back");
+            self.replace_input(None);
+            self.last_reconnect_attempt = Instant::now();
+        }
+        if self.last_reconnect_attempt.elapsed() >= Self::DEVICE_POLL_INTERVAL {
+            self.last_reconnect_attempt = Instant::now();
+            if let Ok(input) = Self::open_input(&self.options, true) {
+                self.replace_input(Some(input));
+            }
+        }
+    }
+
+    /// Switches to playing the given file, in place of the current input. The current input is
+    /// kept if the file cannot be played.
+    pub fn play_file(&mut self, path: PathBuf) {
+        let mut options = self.options.clone();
+        options.command = Command::Play(path);
+        match Self::open_input(&options, false) {
+            Ok(input) => {
+                self.options = options;
+                self.replace_input(Some(input));
+            },
+            Err(error) => eprintln!("cannot play the file: {:#}", error),
+        }
+    }
+
+    /// Replaces the input, clearing everything that's left of the old one.
+    fn replace_input(&mut self, input: Option<Input>) {
+        // the new input may have a different sample rate, which a recording can't change midway
+        if let Some(recording) = self.recording.take() {
+            Self::stop_recording(recording);
+        }
+        let pairs = input.as_ref().map_or(0, |input| input.pairs.len());
+        self.input = input;
+        self.samples_back.clear();
+        self.samples_front.clear();
+        self.pairs = vec![Default::default(); pairs];
+        self.last_sample = None;
+        self.sweeps.history.clear();
+        self.spectrogram.clear();
+        self.waterfall.clear();
+        self.loudness = None;
+        self.surround = SurroundLevels::new();
+        self.last_overruns = 0;
+        self.last_dropouts = 0;
+        self.received = 0;
+    }
+
+    /// Finds the input device to capture from, as selected by the options. If no host or device
+    /// is selected, the backend is picked automatically.
+    ///
+    /// When reconnecting, failures aren't logged and this never waits for a device to appear.
+    fn find_input_device(options: &Options, reconnecting: bool) -> anyhow::Result<(Device, cpal::HostId)> {
+        if options.loopback {
+            return Self::find_loopback_device()
+        }
+        let host = match &options.host {
+            Some(name) => {
+                let id = cpal::available_hosts()
+                    .into_iter()
+                    .find(|id| id.name().eq_ignore_ascii_case(name))
+                    .ok_or_else(|| anyhow!("the {} host is not available", name))?;
+                #[cfg(all(feature = "jack", any(target_os = "linux", target_os = "dragonfly", target_os = "freebsd")))]
+                if id == cpal::HostId::Jack {
+                    return Self::jack_input_device(options)
+                        .map(|device| (device, id))
+                        .ok_or_else(|| anyhow!("could not connect to the JACK server"));
+                }
+                cpal::host_from_id(id)?
+            },
+            None if options.device.is_some() => cpal::default_host(),
+            None => return Self::find_input_device_automatically(options, reconnecting),
+        };
+        let device = match &options.device {
+            Some(selector) => Self::select_device(&host, selector, !reconnecting)?,
+            None => host.default_input_device()
+                .ok_or_else(|| anyhow!("{} does not have a default input device", host.id().name()))?,
+        };
+        Ok((device, host.id()))
+    }
+
+    /// Finds the device that captures what the system is playing: the default output device on
+    /// Windows, which WASAPI captures in loopback mode, or the monitor of the default sink through
+    /// PulseAudio (or PipeWire's PulseAudio server) on Linux.
+    fn find_loopback_device() -> anyhow::Result<(Device, cpal::HostId)> {
+        #[cfg(target_os = "windows")]
+        {
+            let host = cpal::default_host();
+            let device = host.default_output_device()
+                .ok_or_else(|| anyhow!("there is no output device to capture from"))?;
+            Ok((device, host.id()))
+        }
+        #[cfg(any(target_os = "linux", target_os = "dragonfly", target_os = "freebsd"))]
+        {
+            // the pulse device records from the monitor while a MonitorSource is around
+            let host = cpal::default_host();
+            let device = Self::find_device_by_name(&host, "pulse")?
+                .ok_or_else(|| anyhow!("loopback capture requires PulseAudio or PipeWire"))?;
+            Ok((device, host.id()))
+        }
+        #[cfg(not(any(target_os = "windows", target_os = "linux", target_os = "dragonfly", target_os = "freebsd")))]
+        bail!("capturing what the system is playing is not supported on this platform")
+    }
+
+    /// Goes through the backends in order of preference and picks the first one that's usable:
+    /// JACK (including PipeWire's JACK implementation), PipeWire, PulseAudio, the default host's
+    /// default device (ALSA on Linux, WASAPI on Windows, CoreAudio on macOS), and finally any
+    /// input device on any available host.
+    fn find_input_device_automatically(
+        #[cfg_attr(not(all(feature = "jack", any(target_os = "linux", target_os = "dragonfly", target_os = "freebsd"))), allow(unused_variables))]
+        options: &Options,
+        reconnecting: bool,
+    ) -> anyhow::Result<(Device, cpal::HostId)> {
+        #[cfg(all(feature = "jack", any(target_os = "linux", target_os = "dragonfly", target_os = "freebsd")))]
+        match Self::jack_input_device(options) {
+            Some(device) => {
+                eprintln!("using the JACK backend");
+                return Ok((device, cpal::HostId::Jack))
+            },
+            None if !reconnecting => eprintln!("JACK server is not running"),
+            None => (),
+        }
+
+        // an ASIO driver is usually the interface the DAW is using, so prefer it over WASAPI
+        #[cfg(all(windows, feature = "asio"))]
+        match cpal::host_from_id(cpal::HostId::Asio).ok().and_then(|host| host.default_input_device()) {
+            Some(device) => {
+                eprintln!("using the ASIO backend");
+                return Ok((device, cpal::HostId::Asio))
+            },
+            None if !reconnecting => eprintln!("no ASIO driver is available"),
+            None => (),
+        }
+
+        let host = cpal::default_host();
+        #[cfg(any(target_os = "linux", target_os = "dragonfly", target_os = "freebsd"))]
+        for &(device_name, backend_name) in Self::SOUND_SERVER_DEVICES {
+            match Self::find_device_by_name(&host, device_name)? {
+                Some(device) => {
+                    eprintln!("using the {} backend", backend_name);
+                    return Ok((device, host.id()))
+                },
+                None if !reconnecting => eprintln!("{} is not available", backend_name),
+                None => (),
+            }
+        }
+        match host.default_input_device() {
+            Some(device) => {
+                eprintln!("using the {} backend", host.id().name());
+                return Ok((device, host.id()))
+            },
+            None if !reconnecting => eprintln!("{} does not have a default input device", host.id().name()),
+            None => (),
+        }
+
+        // machines without a microphone may not have a default input device, yet still have
+        // other capture devices (eg. a line in or stereo mix) that are perfectly usable
+        for id in cpal::available_hosts() {
+            let host = match cpal::host_from_id(id) {
+                Ok(host) => host,
+                Err(_) => continue,
+            };
+            if let Some(device) = host.input_devices().ok().and_then(|mut devices| devices.next()) {
+                eprintln!("using the {} backend", id.name());
+                return Ok((device, id))
+            }
+        }
+        bail!("could not find an input device on any host")
+    }
+
+    /// Creates the JACK client's input device, or returns `None` if the JACK server isn't
+    /// running.
+    #[cfg(all(feature = "jack", any(target_os = "linux", target_os = "dragonfly", target_os = "freebsd")))]
+    fn jack_input_device(options: &Options) -> Option<Device> {
+        // the client name is what shows up as the node name in patchbays such as Catia,
+        // Helvum or qpwgraph
+        let mut host = cpal::platform::JackHost::new().ok()?;
+        // the input ports are connected to the system's capture ports, unless they're meant to
+        // be connected somewhere else
+        host.set_connect_automatically(options.jack_connect.is_empty());
+        let name = options.jack_name.as_deref().unwrap_or(Self::DEFAULT_CLIENT_NAME);
+        host.input_device_with_name(name).map(Device::from)
+    }
+
+    /// Renames the device's JACK input ports to the names given in the options, and connects the
+    /// ports given in the options to them, in order. The ports being connected to may not exist
+    /// yet (eg. if the DAW isn't running), so failures are only logged.
+    #[cfg(all(feature = "jack", any(target_os = "linux", target_os = "dragonfly", target_os = "freebsd")))]
+    fn set_up_jack_ports(device: &Device, options: &Options) {
+        if options.jack_ports.is_empty() && options.jack_connect.is_empty() {
+            return
+        }
+        let client_name = match device.name() {
+            Ok(name) => name,
+            Err(_) => return,
+        };
+        // the ports belong to cpal's client, so they're set up through a separate one
+        let name = format!("{}_setup", client_name);
+        let client = match jack::Client::new(&name, jack::ClientOptions::NO_START_SERVER) {
+            Ok((client, _)) => client,
+            Err(error) => {
+                eprintln!("cannot set up the JACK ports: {}", error);
+                return
+            },
+        };
+        let mut port_names = Vec::new();
+        for index in 0.. {
+            let cpal_name = format!("{}:in_{}", client_name, index);
+            let mut port = match client.port_by_name(&cpal_name) {
+                Some(port) => port,
+                None => break,
+            };
+            match options.jack_ports.get(index) {
+                Some(short_name) => match port.set_name(short_name) {
+                    Ok(()) => port_names.push(format!("{}:{}", client_name, short_name)),
+                    Err(error) => {
+                        eprintln!("cannot rename {} to {}: {}", cpal_name, short_name, error);
+                        port_names.push(cpal_name);
+                    },
+                },
+                None => port_names.push(cpal_name),
+            }
+        }
+        for (source, destination) in options.jack_connect.iter().zip(&port_names) {
+            match client.connect_ports_by_name(source, destination) {
+                Ok(()) => eprintln!("connected {} to {}", source, destination),
+                Err(error) => eprintln!("cannot connect {} to {}: {}", source, destination, error),
+            }
+        }
+    }
+
+    /// Creates a JACK client for following the transport.
+    #[cfg(all(feature = "jack", any(target_os = "linux", target_os = "dragonfly", target_os = "freebsd")))]
+    fn jack_transport_client(device: &Device) -> Option<jack::Client> {
+        let name = format!("{}_transport", device.name().ok()?);
+        match jack::Client::new(&name, jack::ClientOptions::NO_START_SERVER) {
+            Ok((client, _)) => Some(client),
+            Err(error) => {
+                eprintln!("cannot follow the JACK transport: {}", error);
+                None
+            },
+        }
+    }
+
+    fn find_device_by_name(host: &cpal::Host, name: &str) -> anyhow::Result<Option<Device>> {
+        Ok(host.input_devices()?.find(|device| matches!(device.name(), Ok(n) if n == name)))
+    }
+
+    fn select_device(host: &cpal::Host, selector: &DeviceSelector, wait: bool) -> anyhow::Result<Device> {
+        match selector {
+            DeviceSelector::Index(index) => host.input_devices()?.nth(*index)
+                .ok_or_else(|| anyhow!("there is no input device with index {}", index)),
+            DeviceSelector::Name(name) => Self::find_device_by_name(host, name)?
+                .ok_or_else(|| anyhow!("there is no input device named {}", name)),
+            DeviceSelector::Pattern(regex) => {
+                let mut waiting = false;
+                loop {
+                    let device = host.input_devices()?
+                        .find(|device| matches!(device.name(), Ok(n) if regex.is_match(&n)));
+                    if let Some(device) = device {
+                        return Ok(device)
+                    }
+                    if !wait {
+                        bail!("there is no input device matching {}", regex);
+                    }
+                    if !waiting {
+                        eprintln!("waiting for a device matching {} to appear", regex);
+                        waiting = true;
+                    }
+                    std::thread::sleep(Self::DEVICE_POLL_INTERVAL);
+                }
+            },
+        }
+    }
+
+    /// Picks a stream config and sample format supported by the device. The fewest channels that
+    /// include the given number of channels (usually stereo) and f32 samples are preferred, at the
+    /// sample rate requested in the options or, failing that, 48 or 44.1 kHz (or whatever's
+    /// closest to that). The buffer size is picked according to the options.
+    fn negotiate_config(
+        device: &Device,
+        options: &Options,
+        channels: u16,
+    ) -> anyhow::Result<(StreamConfig, SampleFormat)> {
+        let ranges = Self::supported_configs(device, options)?
+            .into_iter()
+            .filter(|range| range.channels() >= channels);
+        let (range, sample_rate) = match options.sample_rate {
+            Some(rate) => {
+                let rate = SampleRate(rate);
+                let range = ranges
+                    .filter(|range| Self::supports_sample_rate(range, rate))
+                    .max_by_key(|range| {
+                        (Reverse(range.channels()), range.sample_format() == SampleFormat::F32)
+                    })
+                    .ok_or_else(|| {
+                        anyhow!(
+                            "the input device cannot capture {} channels at {} Hz",
+                            channels,
+                            rate.0,
+                        )
+                    })?;
+                (range, rate)
+            },
+            None => {
+                let range = ranges
+                    .max_by_key(|range| {
+                        (
+                            Reverse(range.channels()),
+                            Self::preferred_sample_rate(range).is_some(),
+                            range.sample_format() == SampleFormat::F32,
+                        )
+                    })
+                    .ok_or_else(|| {
+                        anyhow!("the input device cannot capture {} channels", channels)
+                    })?;
+                let rate = Self::preferred_sample_rate(&range).unwrap_or_else(|| {
+                    let closest = Self::PREFERRED_SAMPLE_RATES[0].0
+                        .clamp(range.min_sample_rate().0, range.max_sample_rate().0);
+                    SampleRate(closest)
+                });
+                (range, rate)
+            },
+        };
+        let buffer_size = match (options.buffer_size, range.buffer_size()) {
+            (BufferSizeOption::Auto, &SupportedBufferSize::Range { min, max }) =>
+                BufferSize::Fixed(Self::PREFERRED_BUFFER_SIZE.max(min).min(max)),
+            (BufferSizeOption::Auto, SupportedBufferSize::Unknown) | (BufferSizeOption::Default, _) =>
+                BufferSize::Default,
+            (BufferSizeOption::Fixed(frames), _) => BufferSize::Fixed(frames),
+        };
+
+        let config = StreamConfig {
+            channels: range.channels(),
+            sample_rate,
+            buffer_size,
+        };
+        Ok((config, range.sample_format()))
+    }
+
+    /// Returns the most preferred sample rate supported by the given config range.
+    fn preferred_sample_rate(range: &SupportedStreamConfigRange) -> Option<SampleRate> {
+        Self::PREFERRED_SAMPLE_RATES.iter()
+            .copied()
+            .find(|&rate| Self::supports_sample_rate(range, rate))
+    }
+
+    /// Returns the configs the device can be captured with. On Windows, loopback devices are
+    /// output devices, so they're captured with their output configs.
+    fn supported_configs(
+        device: &Device,
+        options: &Options,
+    ) -> anyhow::Result<Vec<SupportedStreamConfigRange>> {
+        Ok(match options.loopback && cfg!(target_os = "windows") {
+            true => device.supported_output_configs()?.collect(),
+            false => device.supported_input_configs()?.collect(),
+        })
+    }
+
+    fn has_channels(device: &Device, options: &Options, channels: u16) -> anyhow::Result<bool> {
+        Ok(Self::supported_configs(device, options)?.iter().any(|range| range.channels() >= channels))
+    }
+
+    fn supports_sample_rate(range: &SupportedStreamConfigRange, rate: SampleRate) -> bool {
+        (range.min_sample_rate()..=range.max_sample_rate()).contains(&rate)
+    }
+
+    /// Maps a frame to a point of the window, given the center of the window and the scope's
+    /// half-size.
+    fn get_xy(
+        center: (f32, f32),
+        radius: (f32, f32),
+        calibration: Calibration,
+        view: View,
+        left: f32,
+        right: f32,
+    ) -> (f32, f32) {
+        let (left, right) = view.apply(Self::calibrate(calibration, left, right));
+        let x = left * radius.0 + center.0;
+        let y = -right * radius.1 + center.1;
+        (x, y)
+    }
+
+    /// Applies the gain, offset and rotation to a frame, which gives its place in the picture.
+    fn calibrate(calibration: Calibration, left: f32, right: f32) -> (f32, f32) {
+        let left = left * calibration.gain.0 * calibration.display_gain + calibration.offset.0;
+        let right = right * calibration.gain.1 * calibration.display_gain + calibration.offset.1;
+        let (sin, cos) = calibration.rotation.to_radians().sin_cos();
+        (left * cos - right * sin, left * sin + right * cos)
+    }
+
+    /// Estimates the frequency of the signal at every frame, from how far apart the upward zero
+    /// crossings around it are. Where there aren't two crossings to go by, the frequency is 0.
+    fn frequencies(frames: &[Frame], sample_rate: u32) -> Vec<f32> {
+        // the signal has to dip below this before going up through zero again counts as another
+        // crossing, so that noise around zero doesn't look like a high frequency
+        const HYSTERESIS: f32 = 0.001;
+        let mut crossings = Vec::new();
+        let mut armed = false;
+        for (i, &(left, right, _)) in frames.iter().enumerate() {
+            let value = (left + right) / 2.0;
+            if value < -HYSTERESIS {
+                armed = true;
+            } else if armed && value >= 0.0 {
+                crossings.push(i);
+                armed = false;
+            }
+        }
+        let mut frequencies = vec![0.0; frames.len()];
+        for (n, pair) in crossings.windows(2).enumerate() {
+            let frequency = sample_rate as f32 / (pair[1] - pair[0]) as f32;
+            // the frames before the first crossing and after the last one take the frequency of
+            // the nearest period
+            let start = if n == 0 { 0 } else { pair[0] };
+            let end = if n == crossings.len() - 2 { frames.len() } else { pair[1] };
+            frequencies[start..end].fill(frequency);
+        }
+        frequencies
+    }
+
+    /// Returns the color a frequency is drawn in, going from red for bass through the rainbow to
+    /// blue for treble.
+    fn frequency_color(frequency: f32) -> (f32, f32, f32) {
+        const LOWEST: f32 = 50.0;
+        const HIGHEST: f32 = 5000.0;
+        let position = ((frequency / LOWEST).ln() / (HIGHEST / LOWEST).ln()).clamp(0.0, 1.0);
+        palette::hue(position * 240.0)
+    }
+
+    /// Formats an amplitude as a level in dBFS, for showing on screen.
+    fn format_level(amplitude: f32) -> String {
+        match amplitude > 0.0 {
+            true => format!("{:6.1} dB", 20.0 * amplitude.log10()),
+            false => "  -inf dB".to_owned(),
+        }
+    }
+
+    /// Returns the color the beam is drawn in at a speed, in half the picture's size per frame,
+    /// going from red where it's slow through the rainbow to blue where it's fast.
+    fn velocity_color(speed: f32) -> (f32, f32, f32) {
+        const SLOWEST: f32 = 0.002;
+        const FASTEST: f32 = 0.2;
+        let position = ((speed / SLOWEST).ln() / (FASTEST / SLOWEST).ln()).clamp(0.0, 1.0);
+        palette::hue(position * 240.0)
+    }
+
+    pub fn render(&mut self, mut plot: Plot) {
+        self.check_input();
+        plot.no_input = self.input.is_none();
+        plot.data_lost = self.last_data_loss
+            .is_some_and(|time| time.elapsed() < Self::DATA_LOSS_BADGE_DURATION);
+        if let Some(input) = &mut self.input {
+            if input.is_transport_stopped() {
+                // samples captured while the transport is stopped are dropped, and the picture
+                // stays as it was when the transport stopped
+                self.received += input.samples.clear() as u64;
+                self.samples_back.clear();
+                self.samples_front.clear();
+                for (samples, (back, front)) in input.pairs.iter_mut().zip(&mut self.pairs) {
+                    samples.clear();
+                    back.clear();
+                    front.clear();
+                }
+                plot.frozen = true;
+            }
+        }
+
+        let center = plot.center();
+        let (xs, ys) = plot.scope();
+        let radius = (xs.len() as f32 / 2.0, ys.len() as f32 / 2.0);
+        if let Some(input) = &mut self.input {
+            // the clock has to be read before the samples, so that the samples it points to are
+            // there by the time they're displayed
+            let sample_rate = input.sample_rate;
+            let position = input.clock.as_mut().and_then(|clock| clock.position(sample_rate));
+            let overruns = input.samples.overruns();
+            if overruns != self.last_overruns {
+                // the dropped frames leave a gap, so the frames before it are thrown away too
+                self.samples_back.clear();
+                self.pairs.iter_mut().for_each(|(back, _)| back.clear());
+                self.received += (overruns - self.last_overruns) as u64;
+            }
+            let dropouts = input.status.dropouts.load(Ordering::Relaxed);
+            if (overruns, dropouts) != (self.last_overruns, self.last_dropouts) {
+                eprintln!(
+                    "lost input data: {} dropouts in the input, {} frames dropped by the display so far",
+                    dropouts,
+                    overruns,
+                );
+                self.last_overruns = overruns;
+                self.last_dropouts = dropouts;
+                self.last_data_loss = Some(Instant::now());
+            }
+            let length = self.samples_back.len();
+            input.samples.drain_into(&mut self.samples_back);
+            self.received += (self.samples_back.len() - length) as u64;
+            // the pairs are captured along with the selected one, so they have the same frames
+            // in their buffers
+            for (samples, (back, _)) in input.pairs.iter_mut().zip(&mut self.pairs) {
+                samples.drain_into(back);
+            }
+            if let Some(recording) = &mut self.recording {
+                let channels = if self.options.channels.z.is_some() { 3 } else { 2 };
+                let samples = self.samples_back[length..].iter()
+                    .flat_map(|&(x, y, z)| IntoIterator::into_iter([x, y, z]).take(channels));
+                if let Err(error) = recording.write(samples) {
+                    eprintln!("cannot record: {:#}", error);
+                    Self::stop_recording(self.recording.take().unwrap());
+                }
+            }
+
+            let buffer_size = Self::buffer_size(input.sample_rate);
+            match position {
+                // display the frames leading up to the clock's position
+                Some(position) => {
+                    let first = self.received - self.samples_back.len() as u64;
+                    let end = (position.clamp(first, self.received) - first) as usize;
+                    let start = end.saturating_sub(buffer_size);
+                    self.samples_front.clear();
+                    self.samples_front.extend_from_slice(&self.samples_back[start..end]);
+                    self.front_start = first + start as u64;
+                    self.samples_back.drain(..start);
+                    for (back, front) in &mut self.pairs {
+                        let (start, end) = (start.min(back.len()), end.min(back.len()));
+                        front.clear();
+                        front.extend_from_slice(&back[start..end]);
+                        back.drain(..start);
+                    }
+                },
+                // flip buffers as soon as enough frames are collected
+                None => if self.samples_back.len() > buffer_size {
+                    self.front_start = self.received - self.samples_back.len() as u64;
+                    std::mem::swap(&mut self.samples_front, &mut self.samples_back);
+                    self.samples_back.clear();
+                    for (back, front) in &mut self.pairs {
+                        std::mem::swap(front, back);
+                        back.clear();
+                    }
+                },
+            }
+        }
+        if let Some(comparison) = &mut self.comparison {
+            comparison.update();
+        }
+        if let Some(surround) = self.input.as_mut().and_then(|input| input.surround.as_mut()) {
+            self.surround.update(surround);
+        }
+        // the automatic gain goes on top of the gain that's been set, so that the two can be used
+        // together to put the peaks somewhere else than the default
+        let mut calibration = self.calibration;
+        if let Some(agc) = &mut self.agc {
+            let gain = calibration.gain;
+            let peak = self.samples_front.iter()
+                .fold(0.0, |peak: f32, &(x, y, _)| peak.max((x * gain.0).abs()).max((y * gain.1).abs()));
+            calibration.display_gain *= agc.update(peak);
+        }
+        // the averages are kept up to date even while the meters are hidden, so that they're
+        // right as soon as they're shown
+        let sample_rate = self.input.as_ref().map_or(48000, |input| input.sample_rate);
+        self.stereo.update(&self.samples_front, self.front_start, sample_rate);
+        self.levels.update(&self.samples_front, self.front_start, sample_rate);
+        // the composite layout always shows the correlation and the levels
+        let composite = self.display_mode == DisplayMode::Composite;
+        if self.show_correlation || composite {
+            plot.correlation = Some(self.stereo.correlation());
+        }
+        if self.show_width {
+            plot.stereo_width = Some(self.stereo.mid_side());
+        }
+        if self.show_levels || composite {
+            plot.levels = Some(self.levels.levels());
+        }
+        if self.show_balance {
+            plot.balance = Some(self.levels.balance());
+        }
+        if self.show_mid_side {
+            plot.mid_side = Some(self.trace_mid_side(&plot, calibration.display_gain));
+        }
+        if let (true, Some(input)) = (self.show_loudness, &self.input) {
+            let loudness = match &mut self.loudness {
+                Some(loudness) if loudness.sample_rate() == input.sample_rate => loudness,
+                loudness => loudness.insert(Loudness::new(input.sample_rate)),
+            };
+            loudness.push(&self.samples_front, self.front_start);
+        }
+        if let Some(hud) = &mut plot.hud {
+            match &self.input {
+                Some(input) => {
+                    hud.push(input.name.clone());
+                    hud.push(format!("{} Hz", input.sample_rate));
+                    let axes = match self.options.iq {
+                        true => [("I", 0), ("Q", 1)],
+                        false => [("X", 0), ("Y", 1)],
+                    };
+                    for (axis, channel) in axes {
+                        let samples = self.samples_front.iter().map(|&(x, y, _)| [x, y][channel]);
+                        let peak = samples.clone().fold(0.0, |peak: f32, sample| peak.max(sample.abs()));
+                        let power = samples.map(|sample| sample * sample).sum::<f32>()
+                            / self.samples_front.len().max(1) as f32;
+                        hud.push(format!(
+                            "{}  peak {}  rms {}",
+                            axis,
+                            Self::format_level(peak),
+                            Self::format_level(power.sqrt()),
+                        ));
+                    }
+                },
+                None => hud.push("no input".to_owned()),
+            }
+            if let Some(comparison) = &self.comparison {
+                hud.push(format!("compared with {}", comparison.input.name));
+            }
+            if plot.view.zoom != 1.0 {
+                hud.push(format!("zoom {:.1}x", plot.view.zoom));
+            }
+            if calibration.rotation != 0.0 {
+                hud.push(format!("rotation {:.0} deg", calibration.rotation));
+            }
+            if let Some(loudness) = self.loudness.as_ref().filter(|_| self.show_loudness) {
+                let lufs = |value: Option<f32>| match value {
+                    Some(value) => format!("{:.1} LUFS", value),
+                    None => "- LUFS".to_owned(),
+                };
+                hud.push(format!("momentary {}", lufs(loudness.momentary())));
+                hud.push(format!("short-term {}", lufs(loudness.short_term())));
+                hud.push(format!("integrated {}", lufs(loudness.integrated())));
+            }
+            match self.agc {
+                Some(_) => hud.push(format!("gain {:.2}x (auto)", calibration.display_gain)),
+                None if calibration.display_gain != 1.0 => {
+                    hud.push(format!("gain {:.2}x", calibration.display_gain));
+                },
+                None => (),
+            }
+        }
+
+        match self.display_mode {
+            // every pane takes its part of the window as the scope
+            DisplayMode::Dashboard => {
+                let panes = self.panes.clone();
+                let areas = pane_areas(plot.width, plot.height, panes.len());
+                for (&mode, area) in panes.iter().zip(areas) {
+                    plot.pane = Some(area.clone());
+                    let center = plot.center();
+                    let (xs, ys) = plot.scope();
+                    let radius = (xs.len() as f32 / 2.0, ys.len() as f32 / 2.0);
+                    self.plot_mode(mode, &mut plot, calibration, center, radius);
+                    let (image, polar) = (plot.image.take(), std::mem::take(&mut plot.polar));
+                    let speakers = std::mem::take(&mut plot.speakers);
+                    plot.panes.push(Pane { area, image, polar, speakers });
+                }
+                plot.pane = None;
+            },
+            mode => self.plot_mode(mode, &mut plot, calibration, center, radius),
+        }
+        plot.done();
+    }
+
+    /// Plots the samples in the scope the way a display mode shows them.
+    fn plot_mode(
+        &mut self,
+        mode: DisplayMode,
+        plot: &mut Plot,
+        calibration: Calibration,
+        center: (f32, f32),
+        radius: (f32, f32),
+    ) {
+        match mode {
+            DisplayMode::Xy | DisplayMode::Composite => self.plot_xy(plot, calibration, center, radius),
+            DisplayMode::Yt | DisplayMode::Dual => self.plot_sweep(mode, plot, calibration, center, radius),
+            DisplayMode::Waterfall => self.plot_waterfall(plot, calibration, center, radius),
+            DisplayMode::Spectrogram => self.plot_spectrogram(plot),
+            DisplayMode::Polar => self.plot_polar(plot, calibration, center, radius),
+            DisplayMode::Surround => self.plot_surround(plot, calibration, center, radius),
+            // panes don't hold dashboards of their own
+            DisplayMode::Dashboard => (),
+        }
+    }
+
+    /// Plots the X channel against the Y channel.
+    fn plot_xy(
+        &mut self,
+        plot: &mut Plot,
+        calibration: Calibration,
+        center: (f32, f32),
+        radius: (f32, f32),
+    ) {
+        // plot the samples
+        let sweep = matches!(self.input, Some(Input { mono: Some(MonoMode::Sweep), .. }));
+        // the beam puts out the same energy per frame no matter how many points it's split into,
+        // and more of it with supersampling, which makes it wider. the energy is for 48 kHz, and
+        // higher sample rates put out less per frame, so that they aren't any brighter
+        let sample_rate = self.input.as_ref().map_or(48000, |input| input.sample_rate);
+        let energy = Plot::BEAM_ENERGY * self.intensity * plot.supersampling as f32
+            / self.oversampler.factor as f32
+            * 48000.0 / sample_rate as f32;
+        let orientation = self.orientation();
+        let points = self.oversampler.process(&self.samples_front);
+        let frame_count = points.len();
+        let mut path = Vec::with_capacity(frame_count + 1);
+        // the Z value of every point of the path
+        let mut z_values = Vec::with_capacity(frame_count + 1);
+        // every sweep starts anew at the left edge, without a line from where the last one ended
+        let front_start = self.front_start;
+        let carried_over = self.last_sample.filter(|&(next, _, _)| next == front_start && !sweep);
+        if let Some((_, point, z)) = carried_over {
+            path.push(point);
+            z_values.push(z);
+        }
+        path.extend(points.iter().enumerate().map(|(i, &(x, y, _))| {
+            let (x, y) = orientation.channels((x, y));
+            match sweep {
+                true => (i as f32 / frame_count as f32 * 2.0 - 1.0, x),
+                false => (x, y),
+            }
+        }));
+        z_values.extend(points.iter().map(|&(_, _, z)| z));
+        let next = front_start + self.samples_front.len() as u64;
+        self.last_sample = path.last().zip(z_values.last()).map(|(&last, &z)| (next, last, z));
+        // how bright the beam is over every line, as set by the Z channel. it's blanked over lines
+        // that are too long, like a scope's retrace
+        let (max_line_length, z_mode) = (self.max_line_length, self.z_mode);
+        let intensities: Vec<f32> = path.windows(2)
+            .zip(z_values.windows(2))
+            .map(|(line, z)| {
+                let too_long = match max_line_length {
+                    Some(max) => (line[1].0 - line[0].0).hypot(line[1].1 - line[0].1) > max,
+                    None => false,
+                };
+                let (start, end) = (z_mode.intensity(z[0]), z_mode.intensity(z[1]));
+                match too_long || start == 0.0 || end == 0.0 {
+                    true => 0.0,
+                    false => (start + end) / 2.0,
+                }
+            })
+            .collect();
+        let (scale, db_range) = (plot.amplitude_scale, plot.db_range);
+        // the envelope has no use for a sweep, whose X axis is time
+        let mut envelope = self.envelope.as_mut().filter(|_| !sweep);
+        if let Some(envelope) = &mut envelope {
+            envelope.decay();
+        }
+        for point in &mut path {
+            *point = orientation.screen(*point, sweep);
+            let (x, y) = match scale {
+                AmplitudeScale::Linear => *point,
+                // the time axis of a sweep stays linear
+                _ if sweep => (point.0, point.1.signum() * scale.apply(point.1.abs(), db_range)),
+                _ => Self::scale_radially(scale, db_range, *point),
+            };
+            if let Some(envelope) = &mut envelope {
+                envelope.update((x, y));
+            }
+            *point = Self::get_xy(center, radius, calibration, plot.view, x, y);
+        }
+        if let Some(envelope) = envelope {
+            plot.envelope = envelope.outline()
+                .map(|(x, y)| Self::get_xy(center, radius, calibration, plot.view, x, y))
+                .collect();
+        }
+        // the color every point of the path is drawn in, if the trace is colored by hue
+        let colors: Vec<(f32, f32, f32)> = match self.hue_mode {
+            HueMode::Off => Vec::new(),
+            HueMode::Frequency => {
+                let frequencies = Self::frequencies(&self.samples_front, sample_rate);
+                let factor = self.oversampler.factor;
+                // the point carried over from the last frame takes the color of the first one
+                let offset = path.len() - frame_count;
+                (0..path.len())
+                    .map(|i| frequencies.get(i.saturating_sub(offset) / factor).copied().unwrap_or(0.0))
+                    .map(Self::frequency_color)
+                    .collect()
+            },
+            HueMode::Velocity => {
+                // the speed is measured per frame and relative to the size of the picture, so
+                // that the colors don't change with the oversampling or the window's size
+                let scale = self.oversampler.factor as f32 / radius.0.min(radius.1);
+                let speeds: Vec<f32> = path.windows(2)
+                    .map(|line| (line[1].0 - line[0].0).hypot(line[1].1 - line[0].1) * scale)
+                    .collect();
+                // the first point takes the color of the line leading out of it
+                speeds.first()
+                    .into_iter()
+                    .chain(&speeds)
+                    .map(|&speed| Self::velocity_color(speed))
+                    .collect()
+            },
+            HueMode::Z => z_values.iter().map(|&z| palette::hue(z.clamp(0.0, 1.0) * 240.0)).collect(),
+        };
+        plot.color = match self.hue_mode {
+            HueMode::Off => None,
+            _ => Some(colors.first().copied().unwrap_or_else(|| palette::hue(0.0))),
+        };
+        match self.path_mode {
+            PathMode::Lines => for (i, line) in path.windows(2).enumerate() {
+                if let Some(&color) = colors.get(i + 1) {
+                    plot.color = Some(color);
+                }
+                if intensities[i] > 0.0 {
+                    plot.dot(line[0].0, line[0].1, line[1].0, line[1].1, energy * intensities[i]);
+                }
+            },
+            PathMode::Spline => Self::plot_spline(plot, &path, &colors, &intensities, energy),
+            // the point carried over from the last frame has already been drawn
+            PathMode::Dots => for i in path.len() - frame_count..path.len() {
+                if let Some(&color) = colors.get(i) {
+                    plot.color = Some(color);
+                }
+                let intensity = z_mode.intensity(z_values[i]);
+                if intensity > 0.0 {
+                    let (x, y) = path[i];
+                    plot.spot(x, y, energy * intensity);
+                }
+            },
+        }
+        // the heatmap counts where the points are, however they're joined up
+        let weight = 1.0 / self.oversampler.factor as f32;
+        for (&(x, y), &z) in path.iter().zip(&z_values).skip(path.len() - frame_count) {
+            if z_mode.intensity(z) > 0.0 {
+                plot.hit(x, y, weight);
+            }
+        }
+        if !sweep {
+            self.plot_pairs(plot, calibration, center, radius, energy * self.oversampler.factor as f32);
+            if let Some(comparison) = &self.comparison {
+                // the compared input may have a different sample rate
+                let energy = energy * self.oversampler.factor as f32
+                    * sample_rate as f32 / comparison.input.sample_rate as f32;
+                let trace = (&comparison.samples_front[..], comparison.gain, comparison.color);
+                self.plot_trace(plot, trace, calibration, center, radius, energy);
+            }
+        }
+        if plot.markers.iter().any(Option::is_some) {
+            plot.measurement = Some(self.measure(plot, calibration, center, radius));
+        }
+        if plot.crosshair {
+            plot.cursor = path.last()
+                .zip(self.samples_front.last())
+                .map(|(&position, &(x, y, _))| Cursor { position, values: (x, y) });
+        }
+    }
+
+    /// Plots the channel pairs captured alongside the selected one over its trace, each in a color
+    /// of its own.
+    fn plot_pairs(
+        &self,
+        plot: &mut Plot,
+        calibration: Calibration,
+        center: (f32, f32),
+        radius: (f32, f32),
+        energy: f32,
+    ) {
+        for ((_, frames), &hue) in self.pairs.iter().zip(&Self::PAIR_HUES) {
+            let trace = (&frames[..], 1.0, palette::hue(hue));
+            self.plot_trace(plot, trace, calibration, center, radius, energy);
+        }
+    }
+
+    /// Plots frames scaled by a gain in a single color over the trace. They're drawn as they
+    /// are, without the Z channel or oversampling.
+    fn plot_trace(
+        &self,
+        plot: &mut Plot,
+        (frames, gain, color): (&[Frame], f32, (f32, f32, f32)),
+        calibration: Calibration,
+        center: (f32, f32),
+        radius: (f32, f32),
+        energy: f32,
+    ) {
+        let orientation = self.orientation();
+        let (scale, db_range) = (plot.amplitude_scale, plot.db_range);
+        plot.color = Some(color);
+        let path: Vec<(f32, f32)> = frames.iter()
+            .map(|&(x, y, _)| {
+                let point = orientation.screen(orientation.channels((x * gain, y * gain)), false);
+                let (x, y) = Self::scale_radially(scale, db_range, point);
+                Self::get_xy(center, radius, calibration, plot.view, x, y)
+            })
+            .collect();
+        match self.path_mode {
+            PathMode::Dots => for &(x, y) in &path {
+                plot.spot(x, y, energy);
+            },
+            _ => for line in path.windows(2) {
+                plot.dot(line[0].0, line[0].1, line[1].0, line[1].1, energy);
+            },
+        }
+    }
+
+    /// Scales a point's distance from the center by the amplitude scale, keeping its direction.
+    fn scale_radially(scale: AmplitudeScale, db_range: f32, (x, y): (f32, f32)) -> (f32, f32) {
+        let amplitude = x.hypot(y);
+        match (scale, amplitude > 0.0) {
+            (AmplitudeScale::Linear, _) | (_, false) => (x, y),
+            _ => {
+                let factor = scale.apply(amplitude, db_range) / amplitude;
+                (x * factor, y * factor)
+            },
+        }
+    }
+
+    /// Plots every frame as a spot at the stereo position it comes from, on a half disc with the
+    /// mid at the top, the channels at 45 degrees to either side and the side at the bottom
+    /// corners, and its amplitude going out from the middle of the bottom edge. Frames and the
+    /// same frames upside down come from the same position, so the lower half of the picture is
+    /// folded over the upper half.
+    fn plot_polar(
+        &mut self,
+        plot: &mut Plot,
+        calibration: Calibration,
+        center: (f32, f32),
+        radius: (f32, f32),
+    ) {
+        plot.polar = true;
+        let sample_rate = self.input.as_ref().map_or(48000, |input| input.sample_rate);
+        let energy = Plot::BEAM_ENERGY * self.intensity * plot.supersampling as f32
+            * 48000.0 / sample_rate as f32;
+        let (scale, db_range) = (plot.amplitude_scale, plot.db_range);
+        let orientation = self.orientation;
+        let gain = (
+            calibration.gain.0 * calibration.display_gain,
+            calibration.gain.1 * calibration.display_gain,
+        );
+        for &(x, y, z) in &self.samples_front {
+            let intensity = self.z_mode.intensity(z);
+            if intensity == 0.0 {
+                continue
+            }
+            let (x, y) = orientation.channels((x, y));
+            let (x, y) = (x * gain.0, y * gain.1);
+            // a full scale mono frame reaches the outer ring
+            let (mid, side) = ((x + y) / 2.0, (y - x) / 2.0);
+            let (mid, side) = if mid < 0.0 { (-mid, -side) } else { (mid, side) };
+            let amplitude = scale.apply(mid.hypot(side), db_range);
+            // the X channel alone is at -45 degrees, and the Y channel alone at 45
+            let (sin, cos) = side.atan2(mid).sin_cos();
+            // the half disc's radius is the scope's height
+            let (x, y) = plot.view.apply((amplitude * sin, amplitude * cos * 2.0 - 1.0));
+            plot.spot(x * radius.0 + center.0, -y * radius.1 + center.1, energy * intensity);
+        }
+    }
+
+    /// Plots the levels of a surround input's channels as a shape around the center, which
+    /// reaches out towards every speaker as far as its channel's level and goes straight from one
+    /// speaker to the next, along with a needle pointing to where the sound comes from.
+    fn plot_surround(
+        &mut self,
+        plot: &mut Plot,
+        calibration: Calibration,
+        center: (f32, f32),
+        radius: (f32, f32),
+    ) {
+        // how many lines the shape is made of
+        const STEPS: usize = 180;
+        let layout = match self.options.surround {
+            Some(layout) => layout,
+            None => return,
+        };
+        let levels = self.surround.levels();
+        let (scale, db_range) = (plot.amplitude_scale, plot.db_range);
+        // the speakers going clockwise from the back, and how far out the shape reaches at each
+        let mut speakers: Vec<(f32, f32)> = layout.speakers().iter()
+            .zip(levels)
+            .filter_map(|(&(_, angle), level)| {
+                let level = (level * calibration.display_gain).min(1.0);
+                angle.map(|angle| (angle, scale.apply(level, db_range)))
+            })
+            .collect();
+        speakers.sort_by(|a, b| a.0.total_cmp(&b.0));
+        plot.speakers = layout.speakers().iter()
+            .filter_map(|&(name, angle)| angle.map(|angle| (name, angle)))
+            .collect();
+
+        // the shape gets as much energy as a frame's points would, however many lines it's made of
+        let sample_rate = self.input.as_ref().map_or(48000, |input| input.sample_rate);
+        let energy = Plot::BEAM_ENERGY * self.intensity * plot.supersampling as f32
+            * 48000.0 / sample_rate as f32
+            * self.samples_front.len() as f32 / STEPS as f32;
+        let view = plot.view;
+        let point = |angle: f32, distance: f32| {
+            let (sin, cos) = angle.to_radians().sin_cos();
+            let (x, y) = view.apply((distance * sin, distance * cos));
+            (x * radius.0 + center.0, -y * radius.1 + center.1)
+        };
+        // how many degrees clockwise it is from one angle to another
+        let clockwise = |from: f32, to: f32| (to - from).rem_euclid(360.0);
+        let shape: Vec<(f32, f32)> = (0..=STEPS)
+            .map(|step| {
+                let angle = step as f32 / STEPS as f32 * 360.0 - 180.0;
+                // the nearest speakers on either side
+                let previous = speakers.iter()
+                    .min_by(|a, b| clockwise(a.0, angle).total_cmp(&clockwise(b.0, angle)));
+                let next = speakers.iter()
+                    .min_by(|a, b| clockwise(angle, a.0).total_cmp(&clockwise(angle, b.0)));
+                let distance = match previous.zip(next) {
+                    Some((previous, next)) => {
+                        let span = clockwise(previous.0, next.0);
+                        let t = if span > 0.0 { clockwise(previous.0, angle) / span } else { 0.0 };
+                        previous.1 + (next.1 - previous.1) * t
+                    },
+                    None => 0.0,
+                };
+                point(angle, distance)
+            })
+            .collect();
+        for line in shape.windows(2) {
+            plot.dot(line[0].0, line[0].1, line[1].0, line[1].1, energy);
+        }
+        let direction = self.surround.direction(layout);
+        if let Some((angle, focus)) = direction {
+            let (start, end) = (point(angle, 0.0), point(angle, focus));
+            plot.dot(start.0, start.1, end.0, end.1, energy * STEPS as f32 / 4.0);
+        }
+        if let Some(hud) = &mut plot.hud {
+            match direction {
+                Some((angle, focus)) => hud.push(format!("direction {:+.0} deg, focus {:.2}", angle, focus)),
+                None => hud.push("direction -".to_owned()),
+            }
+        }
+    }
+
+    /// Traces the mid (X + Y) / 2 and side (X - Y) / 2 signals of the frames being displayed
+    /// against time: the mid along the bottom edge of the scope with time going right, and the
+    /// side along the right edge with time going down.
+    fn trace_mid_side(&self, plot: &Plot, gain: f32) -> [Vec<(f32, f32)>; 2] {
+        // how much of the scope the traces take up across
+        const STRIP: f32 = 0.12;
+        let (xs, ys) = plot.scope();
+        let (width, height) = (xs.len() as f32, ys.len() as f32);
+        let frames = &self.samples_front;
+        // there's no use in more points than there are pixels along the edge
+        let step = (frames.len() / xs.len().max(ys.len()).max(1)).max(1);
+        let count = frames.len().saturating_sub(1).max(1) as f32;
+        let signals = frames.iter().enumerate().step_by(step).map(|(i, &(x, y, _))| {
+            let (x, y) = (x * gain, y * gain);
+            (i as f32 / count, ((x + y) / 2.0).clamp(-1.0, 1.0), ((x - y) / 2.0).clamp(-1.0, 1.0))
+        });
+        let (mid_y, side_x) = (ys.end as f32 - height * STRIP / 2.0, xs.end as f32 - width * STRIP / 2.0);
+        let mid = signals.clone()
+            .map(|(time, mid, _)| (xs.start as f32 + time * width, mid_y - mid * height * STRIP / 2.0))
+            .collect();
+        let side = signals
+            .map(|(time, _, side)| (side_x + side * width * STRIP / 2.0, ys.start as f32 + time * height))
+            .collect();
+        [mid, side]
+    }
+
+    /// Draws the spectrogram of the channels' mix into the scope.
+    fn plot_spectrogram(&mut self, plot: &mut Plot) {
+        let sample_rate = self.input.as_ref().map_or(48000, |input| input.sample_rate);
+        let (xs, ys) = plot.scope();
+        let mix: Vec<f32> = self.samples_front.iter().map(|&(x, y, _)| (x + y) / 2.0).collect();
+        self.spectrogram.push(&mix, self.front_start, xs.len());
+        let (width, height) = (xs.len() as u32, ys.len() as u32);
+        let colors = &self.spectrogram_colors;
+        plot.image = Some(self.spectrogram.draw(width, height, sample_rate, colors));
+    }
+
+    /// Plots the channels against time, in a sweep that starts at the latest trigger, or at the
+    /// latest frames if there isn't one.
+    fn plot_sweep(
+        &mut self,
+        mode: DisplayMode,
+        plot: &mut Plot,
+        calibration: Calibration,
+        center: (f32, f32),
+        radius: (f32, f32),
+    ) {
+        let sample_rate = self.input.as_ref().map_or(48000, |input| input.sample_rate);
+        let sweep_length = ((self.sweeps.timebase.as_secs_f32() * sample_rate as f32) as usize).max(2);
+        self.sweeps.extend(&self.samples_front, self.front_start, sweep_length);
+        let orientation = self.orientation;
+        let trigger = self.sweeps.find_trigger(|(x, y, _)| orientation.channels((x, y)).0, sweep_length);
+        if let Some(hud) = &mut plot.hud {
+            hud.push(format!("timebase {:.1} ms", self.sweeps.timebase.as_secs_f32() * 1000.0));
+            let (edge, level) = (self.sweeps.trigger.name(), self.sweeps.level);
+            match (self.sweeps.trigger, trigger) {
+                (Trigger::Off, _) => hud.push("free running".to_owned()),
+                (_, Some(_)) => hud.push(format!("{} trigger at {:+.2}", edge, level)),
+                (_, None) => hud.push("not triggered".to_owned()),
+            }
+        }
+        let history = &self.sweeps.history;
+        let start = trigger.unwrap_or_else(|| history.len().saturating_sub(sweep_length));
+        let frames = &history[start..(start + sweep_length).min(history.len())];
+
+        // the beam puts as much energy into a frame's sweep as it would into the frame's points,
+        // however many points the sweep is made of
+        let energy = Plot::BEAM_ENERGY * self.intensity * plot.supersampling as f32
+            * 48000.0 / sample_rate as f32
+            * self.samples_front.len() as f32 / sweep_length as f32;
+        // dual traces are each squeezed into half of the screen. the time axis isn't scaled by
+        // the gain, and the picture isn't turned
+        let traces: &[(usize, f32, f32)] = match mode {
+            DisplayMode::Dual => &[(0, 0.5, 0.5), (1, 0.5, -0.5)],
+            _ => &[(0, 1.0, 0.0)],
+        };
+        let (scale, db_range) = (plot.amplitude_scale, plot.db_range);
+        for &(channel, size, position) in traces {
+            let gain = [calibration.gain.0, calibration.gain.1][channel] * calibration.display_gain;
+            let mut previous = None;
+            for (i, &(x, y, z)) in frames.iter().enumerate() {
+                let (x, y) = orientation.channels((x, y));
+                let value = [x, y][channel];
+                let value = value.signum() * scale.apply(value.abs(), db_range);
+                let time = i as f32 / sweep_length as f32 * 2.0 - 1.0 + calibration.offset.0;
+                let value = position + size * (value * gain + calibration.offset.1);
+                let (time, value) = plot.view.apply((time, value));
+                let (x, y) = (time * radius.0 + center.0, -value * radius.1 + center.1);
+                let intensity = self.z_mode.intensity(z);
+                if let Some((previous_x, previous_y)) = previous {
+                    if intensity > 0.0 {
+                        plot.dot(previous_x, previous_y, x, y, energy * intensity);
+                    }
+                }
+                previous = Some((x, y));
+            }
+        }
+    }
+
+    /// Plots the latest slices of the input as a waterfall, with the slice being filled at the
+    /// bottom of the screen and the older ones shrinking towards the top as they go back into
+    /// it, and fading away.
+    fn plot_waterfall(
+        &mut self,
+        plot: &mut Plot,
+        calibration: Calibration,
+        center: (f32, f32),
+        radius: (f32, f32),
+    ) {
+        // the newest slice fills the bottom half of the screen, and the slices converge on a
+        // vanishing point at the top edge
+        const VANISHING_POINT: f32 = 1.0;
+        const FRONT_SIZE: f32 = 0.5;
+        const FRONT_POSITION: f32 = -0.5;
+        // how much farther away than the front slice the oldest one is
+        const DEPTH: f32 = 2.0;
+
+        let sample_rate = self.input.as_ref().map_or(48000, |input| input.sample_rate);
+        let slice_length = self.waterfall.slice_length(sample_rate);
+        self.waterfall.extend(&self.samples_front, self.front_start, slice_length);
+        if let Some(hud) = &mut plot.hud {
+            let (length, slices) = (self.waterfall.length.as_secs_f32(), self.waterfall.slices);
+            hud.push(format!("waterfall {:.1} s, {} slices", length, slices));
+        }
+
+        // every slice gets as much energy as a frame's points would, however many points the
+        // slice is made of
+        let step = Waterfall::step(slice_length);
+        let energy = Plot::BEAM_ENERGY * self.intensity * plot.supersampling as f32
+            * 48000.0 / sample_rate as f32
+            * self.samples_front.len() as f32 / slice_length as f32 * step as f32;
+        let (scale, db_range) = (plot.amplitude_scale, plot.db_range);
+        let orientation = self.orientation;
+        // the slices move back smoothly as the one in front fills up
+        let filled = self.waterfall.current.len() as f32 / slice_length as f32;
+        let current = self.waterfall.current.iter().step_by(step).copied().collect::<Vec<_>>();
+        let slices = std::iter::once(&current).chain(&self.waterfall.finished);
+        for (age, slice) in slices.enumerate() {
+            let depth = match age {
+                0 => 0.0,
+                _ => (age as f32 - 1.0 + filled) / (self.waterfall.slices - 1) as f32,
+            };
+            let perspective = 1.0 / (1.0 + DEPTH * depth);
+            let fade = 1.0 - depth.min(1.0) * 0.8;
+            let mut previous = None;
+            for &(x, y, z) in slice {
+                let (x, y) = orientation.screen((x, y), false);
+                let (x, y) = Self::scale_radially(scale, db_range, (x, y));
+                let (x, y) = Self::calibrate(calibration, x, y);
+                let (x, y) = (x * FRONT_SIZE, y * FRONT_SIZE + FRONT_POSITION);
+                let (x, y) = (x * perspective, VANISHING_POINT + (y - VANISHING_POINT) * perspective);
+                let (x, y) = plot.view.apply((x, y));
+                let (x, y) = (x * radius.0 + center.0, -y * radius.1 + center.1);
+                let intensity = self.z_mode.intensity(z);
+                if let Some((previous_x, previous_y)) = previous {
+                    if intensity > 0.0 {
+                        plot.dot(previous_x, previous_y, x, y, energy * intensity * fade);
+                    }
+                }
+                previous = Some((x, y));
+            }
+        }
+    }
+
+    /// Works out where the measurement markers are in the frame, and the amplitudes they point
+    /// at, by undoing what's done to a frame to plot it. The amplitudes are along the axes of the
+    /// screen, which are the X and Y channels unless the picture is turned into a goniometer.
+    fn measure(
+        &self,
+        plot: &Plot,
+        calibration: Calibration,
+        center: (f32, f32),
+        radius: (f32, f32),
+    ) -> Measurement {
+        let (scale, db_range) = (plot.amplitude_scale, plot.db_range);
+        let mirrored = self.orientation().mirrored;
+        let amplitudes = plot.markers.map(|marker| marker.map(|(x, y)| {
+            let (sin, cos) = calibration.rotation.to_radians().sin_cos();
+            let (x, y) = (x * cos + y * sin, -x * sin + y * cos);
+            let x = (x - calibration.offset.0) / (calibration.gain.0 * calibration.display_gain);
+            let y = (y - calibration.offset.1) / (calibration.gain.1 * calibration.display_gain);
+            let (x, y) = match scale {
+                AmplitudeScale::Linear => (x, y),
+                _ => {
+                    let distance = x.hypot(y);
+                    match distance > 0.0 {
+                        true => {
+                            let factor = scale.invert(distance, db_range) / distance;
+                            (x * factor, y * factor)
+                        },
+                        false => (0.0, 0.0),
+                    }
+                },
+            };
+            (if mirrored.0 { -x } else { x }, if mirrored.1 { -y } else { y })
+        }));
+        let dbfs = |amplitude: f32| match amplitude {
+            _ if amplitude == 0.0 => "-inf dBFS".to_owned(),
+            _ => format!("{:.1} dBFS", 20.0 * amplitude.log10()),
+        };
+        let mut readout = Vec::new();
+        for (amplitude, name) in amplitudes.iter().zip(["A", "B"]) {
+            if let Some((x, y)) = amplitude {
+                readout.push(format!("{} x {:+.3} y {:+.3} {}", name, x, y, dbfs(x.hypot(*y))));
+            }
+        }
+        if let [Some(a), Some(b)] = amplitudes {
+            let (a_amplitude, b_amplitude) = (a.0.hypot(a.1), b.0.hypot(b.1));
+            let ratio = match a_amplitude > 0.0 && b_amplitude > 0.0 {
+                true => format!("{:+.1} dB", 20.0 * (b_amplitude / a_amplitude).log10()),
+                false => "- dB".to_owned(),
+            };
+            readout.push(format!("B-A x {:+.3} y {:+.3} {}", b.0 - a.0, b.1 - a.1, ratio));
+        }
+        Measurement {
+            positions: plot.markers.map(|marker| marker.map(|point| {
+                let (x, y) = plot.view.apply(point);
+                (x * radius.0 + center.0, -y * radius.1 + center.1)
+            })),
+            readout,
+        }
+    }
+
+    /// Plots a Catmull-Rom spline through the given points. The spline is drawn as a number of
+    /// straight lines between every two points, depending on how far apart they are. If colors
+    /// are given, every part of the spline is drawn in the color of the point it leads to, and
+    /// with the intensity of the line it follows, which leaves it out where that's 0.
+    fn plot_spline(
+        plot: &mut Plot,
+        path: &[(f32, f32)],
+        colors: &[(f32, f32, f32)],
+        intensities: &[f32],
+        energy: f32,
+    ) {
+        for i in 1..path.len() {
+            if let Some(&color) = colors.get(i) {
+                plot.color = Some(color);
+            }
+            let energy = energy * intensities[i - 1];
+            if energy <= 0.0 {
+                continue
+            }
+            // the points at the ends are repeated to get the curve going
+            let (p0, p1, p2) = (path[i.saturating_sub(2)], path[i - 1], path[i]);
+            let p3 = path[(i + 1).min(path.len() - 1)];
+            let length = ((p2.0 - p1.0).powi(2) + (p2.1 - p1.1).powi(2)).sqrt();
+            let steps = ((length / Self::SPLINE_STEP_LENGTH).ceil() as usize)
+                .clamp(1, Self::MAX_SPLINE_STEPS);
+            let spline = |p0: f32, p1: f32, p2: f32, p3: f32, t: f32| {
+                0.5 * (2.0 * p1
+                    + (p2 - p0) * t
+                    + (2.0 * p0 - 5.0 * p1 + 4.0 * p2 - p3) * t * t
+                    + (3.0 * p1 - p0 - 3.0 * p2 + p3) * t * t * t)
+            };
+            let mut previous = p1;
+            for step in 1..=steps {
+                let t = step as f32 / steps as f32;
+                let point = (spline(p0.0, p1.0, p2.0, p3.0, t), spline(p0.1, p1.1, p2.1, p3.1, t));
+                plot.dot(previous.0, previous.1, point.0, point.1, energy / steps as f32);
+                previous = point;
+            }
+        }
+    }
+}
+
+/// Prints every available host along with its input devices and their supported configs.
+pub fn list_devices() -> anyhow::Result<()> {
+    for id in cpal::available_hosts() {
+        println!("{}", id.name());
+        let host = match cpal::host_from_id(id) {
+            Ok(host) => host,
+            Err(error) => {
+                println!("  unavailable: {}", error);
+                continue
+            },
+        };
+        for (index, device) in host.input_devices()?.enumerate() {
+            let name = device.name().unwrap_or_else(|_| "<unknown device>".into());
+            println!("  {}: {}", index, name);
+            let configs = match device.supported_input_configs() {
+                Ok(configs) => configs,
+                Err(error) => {
+                    println!("       unavailable: {}", error);
+                    continue
+                },
+            };
+            for config in configs {
+                let (min_rate, max_rate) = (config.min_sample_rate().0, config.max_sample_rate().0);
+                let rates = if min_rate == max_rate {
+                    format!("{} Hz", min_rate)
+                } else {
+                    format!("{}-{} Hz", min_rate, max_rate)
+                };
+                println!("       {} channels, {}, {:?}", config.channels(), rates, config.sample_format());
+            }
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Resampler;
+
+    /// Converts a mono file to another sample rate, the given number of output frames at a time.
+    fn resample(file: &[f32], file_rate: u32, output_rate: u32, chunks: &[usize]) -> Vec<f32> {
+        let mut resampler = Resampler::new(file_rate, output_rate, 1);
+        let (mut position, mut out) = (0, Vec::new());
+        for &frame_count in chunks {
+            let end = (position + resampler.needed(frame_count)).min(file.len());
+            resampler.played = file[position..end].to_vec();
+            position = end;
+            resampler.convert(frame_count, &mut |frame| out.push(frame[0]));
+        }
+        out
+    }
+
+    #[test]
+    fn resampling() {
+        let file = [0.0, 1.0, 2.0, 3.0];
+        assert_eq!(resample(&file, 1, 2, &[3, 1, 4]), [0.0, 0.5, 1.0, 1.5, 2.0, 2.5, 3.0, 1.5]);
+        assert_eq!(resample(&file, 2, 1, &[1, 1, 1]), [0.0, 2.0, 0.0]);
+        assert_eq!(resample(&file, 3, 2, &[2, 2]), [0.0, 1.5, 3.0, 0.0]);
+        assert_eq!(resample(&file, 5, 5, &[5]), [0.0, 1.0, 2.0, 3.0, 0.0]);
+        // every frame is played exactly once, no matter how the output is split up
+        let file: Vec<f32> = (0..44100).map(|i| i as f32 * 0.001).collect();
+        let resampler = Resampler::new(44100, 48000, 1);
+        assert_eq!(resampler.needed(48000), 44101);
+        let out = resample(&file, 44100, 48000, &[480; 100]);
+        assert_eq!(out.len(), 48000);
+        // the last output frame is past the last frame of the file
+        let step = 0.001 * 44100.0 / 48000.0;
+        assert!(out[..47999].windows(2).all(|pair| (pair[1] - pair[0] - step).abs() < 1e-5));
+    }
+}
@@ -0,0 +1,115 @@
+use std::ops::Range;
+
+/// The frame the picture is drawn in before it's scaled to fit the window. It's either the size
+/// of the window or a fixed size, so that the picture looks the same in any window, and either
+/// way it can be supersampled: drawn bigger and scaled down, for smoother lines.
+pub struct Canvas {
+    pub width: u32,
+    pub height: u32,
+    /// How many pixels of the canvas go into a pixel of the picture along each axis.
+    pub supersampling: u32,
+    /// The picture, in RGBA.
+    pub frame: Vec<u8>,
+}
+
+impl Canvas {
+    /// Creates a canvas for a picture of the given resolution.
+    pub fn new((width, height): (u32, u32), supersampling: u32) -> Self {
+        let (width, height) = (width * supersampling, height * supersampling);
+        Canvas { width, height, supersampling, frame: vec![0; (width * height * 4) as usize] }
+    }
+
+    /// Returns where the canvas is shown in a window of the given size: the left and top edges,
+    /// and the width and height. The canvas is made as big as fits, keeping its aspect ratio.
+    pub fn placement(&self, window_width: u32, window_height: u32) -> (u32, u32, u32, u32) {
+        let (canvas_width, canvas_height) = (self.width as u64, self.height as u64);
+        let narrower = window_width as u64 * canvas_height <= window_height as u64 * canvas_width;
+        let (width, height) = match narrower {
+            // the window is narrower than the canvas, so the canvas takes up its whole width
+            true => (window_width, (window_width as u64 * canvas_height / canvas_width) as u32),
+            false => ((window_height as u64 * canvas_width / canvas_height) as u32, window_height),
+        };
+        ((window_width - width) / 2, (window_height - height) / 2, width, height)
+    }
+
+    /// Maps a position in a window of the given size to the pixel of the canvas shown there.
+    pub fn window_to_canvas(
+        &self,
+        window_width: u32,
+        window_height: u32,
+        (x, y): (f32, f32),
+    ) -> (f32, f32) {
+        let (left, top, width, height) = self.placement(window_width, window_height);
+        (
+            (x - left as f32) * self.width as f32 / width as f32,
+            (y - top as f32) * self.height as f32 / height as f32,
+        )
+    }
+
+    /// Scales the canvas into the RGBA frame of a window of the given size, with black bars on
+    /// the sides that the canvas doesn't reach.
+    pub fn present(&self, frame: &mut [u8], window_width: u32, window_height: u32) {
+        if (window_width, window_height) == (self.width, self.height) {
+            frame.copy_from_slice(&self.frame);
+            return
+        }
+        let (left, top, width, height) = self.placement(window_width, window_height);
+        // every pixel of the window is the average of the pixels of the canvas it covers, or of
+        // the one it's in when the canvas is scaled up
+        let covered = |i: u32, from: u32, to: u32| -> Range<u32> {
+            let start = (i as u64 * from as u64 / to as u64) as u32;
+            let end = ((i as u64 + 1) * from as u64 / to as u64) as u32;
+            start..end.max(start + 1)
+        };
+        let columns: Vec<Range<u32>> = (0..width).map(|x| covered(x, self.width, width)).collect();
+        for (y, row) in frame.chunks_exact_mut(window_width as usize * 4).enumerate() {
+            let y = y as u32;
+            if !(top..top + height).contains(&y) {
+                row.fill(0);
+                continue
+            }
+            let rows = covered(y - top, self.height, height);
+            for (x, pixel) in row.chunks_exact_mut(4).enumerate() {
+                let x = x as u32;
+                if !(left..left + width).contains(&x) {
+                    pixel.fill(0);
+                    continue
+                }
+                let columns = &columns[(x - left) as usize];
+                let mut sum = [0u32; 3];
+                for source_y in rows.clone() {
+                    for source_x in columns.clone() {
+                        let i = ((source_x + source_y * self.width) * 4) as usize;
+                        for (sum, &value) in sum.iter_mut().zip(&self.frame[i..i + 3]) {
+                            *sum += value as u32;
+                        }
+                    }
+                }
+                let count = rows.len() as u32 * columns.len() as u32;
+                for (value, sum) in pixel[..3].iter_mut().zip(sum) {
+                    *value = (sum / count) as u8;
+                }
+            }
+        }
+    }
+}
+
+/// Scales a buffer with `components` values for every pixel of a picture to another size, by
+/// taking the nearest pixel, so that what's been drawn survives the window being resized. The
+/// buffer can hold any number of pictures one after another.
+pub fn rescale(values: &[f32], from: (u32, u32), to: (u32, u32), components: usize) -> Vec<f32> {
+    let from_size = (from.0 * from.1) as usize * components;
+    let to_size = (to.0 * to.1) as usize * components;
+    let mut scaled = Vec::with_capacity(values.len() / from_size * to_size);
+    for picture in values.chunks_exact(from_size) {
+        for y in 0..to.1 {
+            let source_y = (y as u64 * from.1 as u64 / to.1 as u64) as u32;
+            for x in 0..to.0 {
+                let source_x = (x as u64 * from.0 as u64 / to.0 as u64) as u32;
+                let i = (source_x + source_y * from.0) as usize * components;
+                scaled.extend_from_slice(&picture[i..i + components]);
+            }
+        }
+    }
+    scaled
+}
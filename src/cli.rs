@@ -0,0 +1,1960 @@
+use std::{
+    path::{Path, PathBuf},
+    str::FromStr,
+    time::Duration,
+};
+
+use anyhow::{anyhow, bail, Context};
+
+use crate::{palette, regex::Regex};
+
+const USAGE: &str = "\
+usage: vectorscope [command] [options]
+
+commands:
+  scope                display the vectorscope (default)
+  list-devices         list available hosts, capture devices and their configs
+  play <file>          display a WAV file as it plays, instead of capturing from a device.
+                       files can also be dropped onto the window to play them
+
+options:
+  --config <path>      config file to read options from. defaults to vectorscope/config in
+                       the user's config directory
+  --host <name>        audio host to capture with, eg. ALSA, JACK or ASIO
+  --device <device>    capture device, by name or by index in the host's device list
+  --device-match <re>  capture from the first device whose name matches the regex, waiting
+                       for one to appear if there isn't any yet
+  --loopback           capture what the system is playing instead of an input device. uses
+                       WASAPI loopback on Windows and the default sink's monitor through
+                       PulseAudio or PipeWire on Linux
+  --stdin              read raw interleaved samples from standard input instead of capturing
+                       from a device, eg. piped from ffmpeg or sox
+  --socket <path>      bind a Unix datagram socket at the path and read raw interleaved samples
+                       sent to it, so that other programs can feed the scope directly. every
+                       datagram must hold whole frames
+  --url <url>          display an HTTP audio stream, eg. an Icecast mount. only uncompressed
+                       WAV and L16 streams can be decoded, and https is not supported
+  --listen <address>   listen for raw interleaved samples sent over UDP, eg. 0.0.0.0:9000.
+                       every datagram must hold whole frames
+  --rtp                expect RTP packets when listening over UDP. L16 streams are read with
+                       --format s16be
+  --format <format>    sample format of raw input: u8, s8, s16le, s16be, s24le, s32le, s32be,
+                       f32le (default), f32be or f64le
+  --iq <format>        read complex I/Q samples from a software defined radio, eg. piped from
+                       rtl_sdr, and plot I on X and Q on Y to see the constellation. the
+                       format is `cu8` (rtl_sdr), `cs8` (HackRF), `cs16` or `cf32`. the
+                       samples come from standard input unless --socket or --listen is given,
+                       at 2.048 MHz unless --sample-rate is given. this sets --format,
+                       --channels 2, --decimate auto and --path dots, which can be changed by
+                       giving them after --iq
+  --sample-rate <hz>   sample rate to capture at. picked automatically if not given. raw input
+                       is assumed to be 48 kHz unless given. --rate is the same
+  --buffer-size <size> capture buffer size in frames, or one of:
+                         auto     a small buffer if the device supports it (default)
+                         default  the host's default buffer size
+  --channels <x>,<y>[,<z>] | <count>
+                       input channels to plot on the X and Y axes, counting from 1, and
+                       optionally a Z channel that blanks the beam wherever it isn't positive,
+                       like the Z input of an XY scope. defaults to 1,2. a single number is
+                       the number of channels in raw input instead, which defaults to 2
+  --pairs <count>      plot this many channel pairs as overlapping traces in different colors:
+                       the one selected with --channels, then the pair of channels after it,
+                       and so on, eg. 1-2, 3-4 and 5-6 with `--pairs 3`. from 1 (default) to 4
+  --z-mode <mode>      whether the Z channel turns the beam on and off (`blank`, default), or
+                       sets its `brightness`, from off at 0 to fully on at 1
+  --surround <layout>  take a `5.1` or `7.1` input, in the WAV channel order (L, R, C, LFE,
+                       then the surround channels), mixed down to stereo for plotting. the
+                       surround display shows all of its channels
+  --downmix <coefficients>
+                       how much of every surround channel goes into the stereo mix,
+                       separated by commas in channel order. the center and LFE go into
+                       both sides. defaults to 1,1,0.707,0,0.707,0.707 for 5.1 and
+                       1,1,0.707,0,0.707,0.707,0.707,0.707 for 7.1
+  --mono <mode>        plot a single input channel (the X channel), either on both axes
+                       (`diagonal`) or against a time sweep (`sweep`). mono devices are
+                       plotted diagonally even without this option
+  --display <mode>     how the channels are shown. press Y in the window to switch
+                       - `xy` (default): the X channel against the Y channel
+                       - `yt`: the X channel against time, like an oscilloscope
+                       - `dual`: both channels against time, one above the other
+                       - `spectrogram`: a scrolling spectrogram of the channels' mix, with
+                         frequency going up on a logarithmic scale
+                       - `waterfall`: the latest XY pictures stacked going back into the
+                         screen
+                       - `composite`: the XY picture turned like on a goniometer, with level
+                         meters on either side and a correlation meter below, like on
+                         hardware goniometers
+                       - `polar`: every frame's amplitude against the direction it comes
+                         from, on a half disc with the mid straight up and the channels at
+                         45 degrees to either side
+                       - `surround`: how loud every channel of a --surround input is, as a
+                         shape reaching out towards the speakers, with a needle pointing to
+                         where the sound comes from
+                       - `dashboard`: the window split into panes showing the others, as set
+                         by --panes
+  --panes <modes>      what the panes of the dashboard show, separated by commas, eg.
+                       xy,dual. any of the display modes above but `composite` and
+                       `dashboard` can be used once. defaults to xy,yt,spectrogram
+  --fft-size <size>    how many samples the spectrogram analyzes at once, a power of two
+                       between 256 and 16384. bigger sizes tell frequencies apart better and
+                       times worse. defaults to 2048
+  --overlap <fraction> how much every window of the spectrogram overlaps the one before it,
+                       from 0 up to 0.95. more overlap scrolls faster. defaults to 0.75
+  --spectrogram-colormap <colormap>
+                       the colors of the spectrogram, from quiet to loud: `viridis` (default)
+                       or `inferno`
+  --waterfall-length <time>
+                       how far back the waterfall goes, eg. 5s. defaults to 3s
+  --waterfall-slices <count>
+                       how many pictures the waterfall stacks up, between 2 and 100. defaults
+                       to 24
+  --timebase <time>    how much time a sweep across the screen takes with --display yt or
+                       dual, eg. 5ms. defaults to 20ms. press Page Up and Page Down in the
+                       window to adjust it
+  --trigger <edge>     start sweeps where the X channel crosses the trigger level going up
+                       (`rising`, default) or down (`falling`), so that repeating waveforms
+                       stand still, or let them run freely (`off`). when there's no edge to
+                       trigger on, the latest frames are shown
+  --trigger-level <level>
+                       the level the X channel has to cross to trigger a sweep, between -1
+                       and 1. defaults to 0
+  --ms                 treat the input as mid/side (X = mid, Y = side) and decode it to
+                       left/right before plotting
+  --gain <gain>        scale the picture by <gain>, between 0.25 and 16, to make up for quiet
+                       or loud sources. defaults to 1. press + and - in the window to adjust it
+  --agc                scale the picture automatically so that the peaks of the input reach
+                       most of the way to the edge, on top of --gain. press N in the window to
+                       turn it on or off
+  --envelope           outline the farthest the picture has reached in every direction, so that
+                       short peaks stay visible. press E in the window to show or hide it
+  --envelope-hold <time>
+                       how long the outline holds a peak before shrinking back. defaults to 1s
+  --envelope-decay <time>
+                       how long it takes the outline to shrink halfway back after the hold,
+                       eg. 500ms. defaults to 1s
+  --correlation        show how alike the channels are, from -1 when they're out of phase to 1
+                       when they're the same, on a bar under the picture. press J in the window
+                       to show or hide it
+  --stereo-width       show how wide the stereo image is, as the energy of the side signal
+                       (X - Y) against that of the mid signal (X + Y), on a bar under the
+                       picture that goes from mono on the left to all side on the right. press
+                       W in the window to show or hide it
+  --levels             show the level of the X channel on the left of the picture and that of
+                       the Y channel on the right, as a bar for the RMS level and a line for
+                       the peak level. press B in the window to show or hide them
+  --balance            show how much louder one channel is than the other, going by their RMS
+                       levels, on a bar under the picture, eg. to catch a gain mismatch.
+                       press Z in the window to show or hide it
+  --peak-hold <time>   how long the level meters hold a peak before it falls, eg. 3s. defaults
+                       to 1.5s
+  --correlation-time <time>
+                       how long the correlation and the stereo width are averaged over, eg. 1s.
+                       defaults to 300ms
+  --gain-x <gain>      scale the picture horizontally by <gain>. defaults to 1
+  --gain-y <gain>      scale the picture vertically by <gain>. defaults to 1
+  --offset-x <offset>  move the picture right by <offset>, where 1 is from the center to the
+                       edge. defaults to 0
+  --offset-y <offset>  move the picture up by <offset>. defaults to 0. press the arrow keys in
+                       the window to move the picture, and hold Shift to change the gain
+  --invert-x           invert the polarity of the X channel. press 1 in the window to switch
+  --invert-y           invert the polarity of the Y channel. press 2 in the window to switch
+  --swap-axes          plot the X channel on the Y axis and the other way around. press X in
+                       the window to switch
+  --mirror-x           mirror the picture horizontally. press 3 in the window to switch
+  --mirror-y           mirror the picture vertically. press 4 in the window to switch
+  --goniometer         turn the picture 45° like a goniometer, so that mono signals draw a
+                       vertical line, left and right are the diagonals and mid and side are
+                       the axes. press M in the window to switch
+  --rotate <degrees>   turn the picture counterclockwise by <degrees>, eg. to match a
+                       projector or a scope that's mounted sideways. defaults to 0. hold Ctrl
+                       and press the left and right arrow keys in the window to adjust it
+  --amplitude <scale>  plot amplitudes on a `linear` scale (default), or in `db` so that quiet
+                       signals show up next to loud ones. press D in the window to switch
+  --db-range <db>      how far below full scale the center is with --amplitude db.
+                       defaults to 60
+  --oversample <factor>
+                       interpolate <factor> points per sample with a windowed sinc, so that
+                       the beam follows the smooth path between samples like on an analog
+                       scope. defaults to 1 (off)
+  --persistence <time> how long it takes the trace to fade to half its brightness, eg. 120ms
+                       or 0.5s. defaults to 70ms. press [ and ] in the window to adjust it
+  --path <mode>        how the beam gets from one point to the next: in straight `lines`
+                       (default), along a smooth `spline`, or not at all, drawing only the
+                       `dots` themselves. press P in the window to switch
+  --blank <distance>   leave out lines between points more than <distance> apart, where 1 is
+                       from the center to the edge, like a scope blanks the beam while it
+                       jumps back. lines across dropped frames are always left out
+  --intensity <factor> how bright the beam is, between 0.1 and 10 times as bright as usual.
+                       the brightness doesn't change with the sample rate, so 96 kHz input
+                       is as bright as 48 kHz input. defaults to 1. press ; and ' in the
+                       window to adjust it
+  --beam-width <px>    width of the beam's glow in pixels, measured where it's half as bright
+                       as in the middle. the glow has a Gaussian profile. defaults to 1
+  --resolution <size>  draw the picture at a fixed resolution, eg. `1024x1024`, and scale it to
+                       fit the window. defaults to the window's size
+  --render-scale <scale>
+                       draw the picture with <scale> pixels for every logical pixel of the
+                       window, eg. 1 to draw at a lower resolution on HiDPI displays for speed.
+                       defaults to the display's scale factor, which is every physical pixel
+  --supersample <factor>
+                       draw the picture <factor> times bigger and scale it down, for smoother
+                       lines at the cost of speed. defaults to 1 (off)
+  --phosphor <type>    the phosphor the screen is coated with: `p1` (green), `p7`
+                       (blue-white with a long yellow afterglow) or `p31` (green, default)
+  --heatmap <colormap> instead of the trace, show how often the beam has hit every pixel in
+                       the last few seconds, in the `viridis` or `inferno` colormap, to see
+                       how the signal is spread over the stereo field. defaults to `off`.
+                       press V in the window to switch
+  --heatmap-window <time>
+                       how long it takes the hits to count half as much, eg. 5s. defaults
+                       to 2s
+  --theme <theme>      the colors to draw in: `phosphor` (the phosphor's own colors, default),
+                       `green`, `amber`, `white`, `blue`, `dracula` or `light` (a dark trace
+                       on white, for printing). press T in the window to switch themes
+  --accessibility <mode>
+                       pick a theme that's easier to see: `deuteranopia` or `protanopia` for
+                       colors that stay apart with red-green color blindness, or
+                       `high-contrast` for a bright trace over a dim graticule
+  --palette <path>     draw the trace with a gradient read from a file, and pick the `custom`
+                       theme. every line of the file is a stop made of a position from 0
+                       (dark) to 1 (brightest) and a color, eg. `0.5 #20c040`. lines starting
+                       with # are comments
+  --hue <mode>         color the trace by the `frequency` of the signal, from red for bass to
+                       blue for treble, by the `velocity` of the beam, from red where it's
+                       slow to blue where it's fast, or by the `z` channel, from red at 0 to
+                       blue at 1, instead of with the theme's colors (`off`, default). press H
+                       in the window to switch
+  --auto-exposure      adjust the brightness to the signal, so that both quiet and loud signals
+                       are easy to see. press A in the window to turn it on or off
+  --transfer <curve>   how light turns into brightness: `exponential` (default), or `log` to
+                       bring out faint detail next to bright areas
+  --gamma <gamma>      lift faint parts of the trace (above 1) or darken them (below 1)
+                       without changing the brightest parts. defaults to 1. press , and . in
+                       the window to adjust it
+  --bloom <intensity>  make bright areas bleed light into their surroundings like on a
+                       phosphor screen, eg. 0.5. defaults to 0 (off)
+  --bloom-radius <px>  how far the bloom reaches, in pixels. defaults to 8
+  --graticule <style>  the markings behind the trace: a `grid` (default), a `polar` grid of
+                       circles, a `crosshair`, circles at 0, -3, -6 and -12 `db`, or `off`.
+                       press G in the window to switch
+  --divisions <count>  how many lines or circles the graticule is divided into. defaults to 5
+  --graticule-color <color>
+                       color of the graticule, as #rrggbb. defaults to the theme's color
+  --graticule-opacity <opacity>
+                       how much the graticule stands out from the background, from 0 to 1.
+                       defaults to 1
+  --overlay <path>     draw a PNG image over the graticule, eg. the figure a test signal should
+                       make or a mask of the safe area, fitted into the scope. press O in the
+                       window to show or hide it
+  --overlay-opacity <opacity>
+                       how much the overlay covers what's under it, from 0 to 1, on top of
+                       the image's own transparency. defaults to 1
+  --overlay-scale <scale>
+                       make the overlay <scale> times as big as fits in the scope, between
+                       0.01 and 16. defaults to 1
+  --overlay-offset <x>,<y>
+                       move the overlay right by <x> and up by <y>, where 1 is from the center
+                       of the scope to the edge. defaults to 0,0
+  --emphasize-center   draw the lines through the center brighter than the rest
+  --labels             label the graticule with the amplitudes its lines and circles are at
+  --hud                show the input's name, sample rate and levels in the corner. press I
+                       in the window to show or hide them
+  --mid-side-traces    trace the mid (X + Y) / 2 and side (X - Y) / 2 signals against time
+                       along the edges of the scope: the mid along the bottom and the side
+                       along the right edge, going down. press 5 in the window to show or hide
+                       them
+  --loudness           measure the loudness like EBU R128 does, and show the momentary,
+                       short-term and integrated loudness in LUFS in the heads-up display.
+                       press U in the window to start or stop measuring, which starts the
+                       integrated loudness over
+  --crosshair          draw a crosshair where the beam is, with the values of the X and Y
+                       channels there in dBFS, eg. to spot a DC offset or one channel being
+                       louder than the other. press K in the window to show or hide it
+  --square             keep the scope square in the middle of the window, with bars on either
+                       side, so that circles stay round. press Q in the window to switch
+  --crt                make the picture look like it's on the curved glass of a vintage
+                       scope, with scanlines and a darker rim. press C in the window to
+                       turn it on or off
+  --gpu                draw the trace on the GPU, which keeps the CPU free for big windows.
+                       the CPU takes over while anything the GPU can't draw is on: long or
+                       automatic exposure, bloom, the heatmap, a reference trace, the CRT
+                       look, or traces in colors of their own, like coloring by hue
+  --decimate <factor>  average every <factor> frames into one before plotting, to keep high
+                       sample rates from overloading the renderer. `auto` brings the rate
+                       down to around 48 kHz, `off` disables decimation (default)
+  --jack-name <name>   JACK client name, to tell multiple scopes apart in a patchbay. the
+                       input client is named <name>_in. defaults to vectorscope
+  --jack-ports <names> comma-separated names for the JACK input ports, eg. left,right
+  --jack-connect <ports>
+                       comma-separated JACK output ports to connect the scope's inputs to,
+                       in order, eg. system:capture_1,system:capture_2. if not given, the
+                       inputs are connected to the system's capture ports
+  --mute               don't play files through the default output device while displaying
+                       them
+  --compare-file <path>
+                       show a WAV file over the input in a color of its own, eg. a reference
+                       track to compare a mix against. the file plays silently
+  --compare-device <device>
+                       show another capture device over the input, by name or by index like
+                       --device, eg. to compare a master bus against what comes back from
+                       an external processor
+  --compare-gain <gain>
+                       scale the compared input by <gain>, between 0.25 and 16, on top of
+                       --gain. defaults to 1
+  --compare-color <color>
+                       color of the compared input, as #rrggbb. defaults to #ff6040
+  --follow-transport   when running under JACK, freeze the picture while the transport is
+                       stopped and resume when it starts rolling
+  --record             record the displayed channels to a WAV file from the start. press R
+                       in the window to start or stop recording at any time
+  --long-exposure      start with the trace never fading out, so that it builds up into a
+                       picture of everything that's played. the brightness adjusts itself
+                       to keep the picture from burning out. press L in the window to start
+                       or stop a long exposure, and S to save the picture as a PNG
+  --record-dir <path>  directory to save recordings and pictures in. files are named after
+                       the time they were started, in UTC. defaults to the current directory
+  -h, --help           print this help message
+
+every option can also be set in the config file, one per line, as `option = value`.
+switches such as --ms are written as `option = true` or `option = false`, or just `option`.
+options given on the command line take precedence over the config file.
+
+when started by a Non/New Session Manager (NSM_URL is set), the options are saved in and
+restored from the session, and the JACK client is named after the session's client ID.
+
+in the window, scroll to zoom in around the cursor, drag with the left mouse button to move
+the picture around, and press 0 to go back to the whole picture. click with the right mouse
+button to place the measurement markers A and B in turn, which show the amplitudes they're at
+and the difference between them, and with the middle button to remove them. press F to take a
+snapshot of the trace, which is drawn dimly under it to compare against, and again to remove it.
+";
+
+/// Selects a device from a host's list of input devices.
+#[derive(Clone)]
+pub enum DeviceSelector {
+    Index(usize),
+    Name(String),
+    Pattern(Regex),
+}
+
+impl DeviceSelector {
+    fn parse(s: &str) -> Self {
+        match s.parse() {
+            Ok(index) => DeviceSelector::Index(index),
+            Err(_) => DeviceSelector::Name(s.to_owned()),
+        }
+    }
+}
+
+/// An input shown over the one being displayed, for comparing the two.
+#[derive(Clone)]
+pub enum CompareSource {
+    File(PathBuf),
+    Device(DeviceSelector),
+}
+
+/// The pair of input channels plotted on the X and Y axes.
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub struct ChannelPair {
+    /// Index of the channel plotted on the X axis, counting from 0.
+    pub x: u16,
+    /// Index of the channel plotted on the Y axis, counting from 0.
+    pub y: u16,
+    /// Index of the channel that blanks the beam where it isn't positive, like the Z input of
+    /// an XY scope, counting from 0.
+    pub z: Option<u16>,
+}
+
+impl ChannelPair {
+    /// Parses a channel pair in the form `x,y`, optionally followed by a Z channel (`x,y,z`),
+    /// with channels counted from 1.
+    fn parse(s: &str) -> anyhow::Result<Self> {
+        let (x, y) = s.split_once(',')
+            .ok_or_else(|| anyhow!("channels must be given as a pair, eg. 1,2"))?;
+        let (y, z) = match y.split_once(',') {
+            Some((y, z)) => (y, Some(z)),
+            None => (y, None),
+        };
+        let channel = |s: &str| match s.trim().parse::<u16>() {
+            Ok(channel) if channel >= 1 => Ok(channel - 1),
+            _ => Err(anyhow!("invalid channel: {} (channels are counted from 1)", s)),
+        };
+        Ok(ChannelPair { x: channel(x)?, y: channel(y)?, z: z.map(channel).transpose()? })
+    }
+
+    /// Returns the pair the given number of pairs after this one, eg. 3,4 one pair after 1,2.
+    /// The Z channel is shared by all the pairs, so the new pair doesn't have one.
+    pub fn shift(self, pairs: u16) -> Self {
+        ChannelPair { x: self.x + pairs * 2, y: self.y + pairs * 2, z: None }
+    }
+
+    /// Returns the number of channels that need to be captured to get all the selected channels.
+    pub fn required_channels(self) -> u16 {
+        self.x.max(self.y).max(self.z.unwrap_or(0)) + 1
+    }
+}
+
+impl Default for ChannelPair {
+    fn default() -> Self {
+        ChannelPair { x: 0, y: 1, z: None }
+    }
+}
+
+/// The sample format of raw input.
+#[derive(Clone, Copy, PartialEq, Eq, Default)]
+pub enum RawFormat {
+    U8,
+    S8,
+    S16Le,
+    S16Be,
+    S24Le,
+    S32Le,
+    S32Be,
+    #[default]
+    F32Le,
+    F32Be,
+    F64Le,
+}
+
+impl RawFormat {
+    /// Returns the size of a sample, in bytes.
+    pub fn sample_size(self) -> usize {
+        match self {
+            RawFormat::U8 | RawFormat::S8 => 1,
+            RawFormat::S16Le | RawFormat::S16Be => 2,
+            RawFormat::S24Le => 3,
+            RawFormat::S32Le | RawFormat::S32Be | RawFormat::F32Le | RawFormat::F32Be => 4,
+            RawFormat::F64Le => 8,
+        }
+    }
+
+    /// Decodes a sample from the start of the given bytes.
+    pub fn decode(self, b: &[u8]) -> f32 {
+        match self {
+            RawFormat::U8 => (b[0] as f32 - 128.0) / 128.0,
+            RawFormat::S8 => b[0] as i8 as f32 / 128.0,
+            RawFormat::S16Le => i16::from_le_bytes([b[0], b[1]]) as f32 / 32768.0,
+            RawFormat::S16Be => i16::from_be_bytes([b[0], b[1]]) as f32 / 32768.0,
+            RawFormat::S24Le => i32::from_le_bytes([0, b[0], b[1], b[2]]) as f32 / 2147483648.0,
+            RawFormat::S32Le => i32::from_le_bytes([b[0], b[1], b[2], b[3]]) as f32 / 2147483648.0,
+            RawFormat::S32Be => i32::from_be_bytes([b[0], b[1], b[2], b[3]]) as f32 / 2147483648.0,
+            RawFormat::F32Le => f32::from_le_bytes([b[0], b[1], b[2], b[3]]),
+            RawFormat::F32Be => f32::from_be_bytes([b[0], b[1], b[2], b[3]]),
+            RawFormat::F64Le => f64::from_le_bytes([b[0], b[1], b[2], b[3], b[4], b[5], b[6], b[7]]) as f32,
+        }
+    }
+}
+
+/// How to plot a single input channel.
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum MonoMode {
+    /// The channel is plotted on both axes, which results in a diagonal line.
+    Diagonal,
+    /// The channel is plotted on the Y axis, against time on the X axis.
+    Sweep,
+}
+
+/// What the channels are plotted against.
+#[derive(Clone, Copy, PartialEq, Eq, Default)]
+pub enum DisplayMode {
+    /// The X channel against the Y channel.
+    #[default]
+    Xy,
+    /// The X channel against time, like an oscilloscope.
+    Yt,
+    /// Both channels against time, one above the other.
+    Dual,
+    /// The spectrum of the channels' mix over time.
+    Spectrogram,
+    /// The latest pictures of the X channel against the Y channel, stacked in perspective.
+    Waterfall,
+    /// The X channel against the Y channel like on a goniometer, laid out with level meters and
+    /// a correlation meter.
+    Composite,
+    /// Every frame's amplitude against the direction it comes from, on a half disc.
+    Polar,
+    /// The levels of a surround input's channels, as a shape reaching out towards the speakers.
+    Surround,
+    /// The window split into panes that each show the channels in one of the other ways.
+    Dashboard,
+}
+
+impl DisplayMode {
+    pub const ALL: [DisplayMode; 9] = [
+        DisplayMode::Xy,
+        DisplayMode::Yt,
+        DisplayMode::Dual,
+        DisplayMode::Spectrogram,
+        DisplayMode::Waterfall,
+        DisplayMode::Composite,
+        DisplayMode::Polar,
+        DisplayMode::Surround,
+        DisplayMode::Dashboard,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            DisplayMode::Xy => "xy",
+            DisplayMode::Yt => "yt",
+            DisplayMode::Dual => "dual",
+            DisplayMode::Spectrogram => "spectrogram",
+            DisplayMode::Waterfall => "waterfall",
+            DisplayMode::Composite => "composite",
+            DisplayMode::Polar => "polar",
+            DisplayMode::Surround => "surround",
+            DisplayMode::Dashboard => "dashboard",
+        }
+    }
+}
+
+/// The channel layout of a surround input.
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum SurroundLayout {
+    FivePointOne,
+    SevenPointOne,
+}
+
+impl SurroundLayout {
+    pub const ALL: [SurroundLayout; 2] = [SurroundLayout::FivePointOne, SurroundLayout::SevenPointOne];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            SurroundLayout::FivePointOne => "5.1",
+            SurroundLayout::SevenPointOne => "7.1",
+        }
+    }
+
+    /// Returns the layout's channels in order, with the direction of their speakers in degrees
+    /// clockwise from the front. The LFE channel has no direction.
+    pub fn speakers(self) -> &'static [(&'static str, Option<f32>)] {
+        match self {
+            SurroundLayout::FivePointOne => &[
+                ("L", Some(-30.0)),
+                ("R", Some(30.0)),
+                ("C", Some(0.0)),
+                ("LFE", None),
+                ("Ls", Some(-110.0)),
+                ("Rs", Some(110.0)),
+            ],
+            SurroundLayout::SevenPointOne => &[
+                ("L", Some(-30.0)),
+                ("R", Some(30.0)),
+                ("C", Some(0.0)),
+                ("LFE", None),
+                ("Lb", Some(-150.0)),
+                ("Rb", Some(150.0)),
+                ("Ls", Some(-90.0)),
+                ("Rs", Some(90.0)),
+            ],
+        }
+    }
+
+    /// Returns how much of every channel goes into the stereo mix by default, as recommended by
+    /// ITU-R BS.775, with the LFE left out.
+    pub fn downmix(self) -> &'static [f32] {
+        match self {
+            SurroundLayout::FivePointOne => &[1.0, 1.0, 0.707, 0.0, 0.707, 0.707],
+            SurroundLayout::SevenPointOne => &[1.0, 1.0, 0.707, 0.0, 0.707, 0.707, 0.707, 0.707],
+        }
+    }
+}
+
+/// Which edges of the X channel start a sweep when plotting against time.
+#[derive(Clone, Copy, PartialEq, Eq, Default)]
+pub enum Trigger {
+    #[default]
+    Rising,
+    Falling,
+    /// Sweeps run freely, always showing the latest frames.
+    Off,
+}
+
+impl Trigger {
+    pub const ALL: [Trigger; 3] = [Trigger::Rising, Trigger::Falling, Trigger::Off];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Trigger::Rising => "rising",
+            Trigger::Falling => "falling",
+            Trigger::Off => "off",
+        }
+    }
+}
+
+/// How the beam travels from one point to the next.
+#[derive(Clone, Copy, PartialEq, Eq, Default)]
+pub enum PathMode {
+    /// In a straight line.
+    #[default]
+    Lines,
+    /// Along a Catmull-Rom spline through the surrounding points.
+    Spline,
+    /// Not at all: only the points themselves are drawn, which shows how coarse a digital
+    /// signal really is.
+    Dots,
+}
+
+impl PathMode {
+    /// The modes, in the order they're cycled through.
+    pub const ALL: [PathMode; 3] = [PathMode::Lines, PathMode::Spline, PathMode::Dots];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            PathMode::Lines => "lines",
+            PathMode::Spline => "spline",
+            PathMode::Dots => "dots",
+        }
+    }
+}
+
+/// What decides the color of the trace, other than the palette.
+#[derive(Clone, Copy, PartialEq, Eq, Default)]
+pub enum HueMode {
+    /// The trace is drawn in the palette's colors.
+    #[default]
+    Off,
+    /// The trace is colored by the frequency of the signal, from red for bass to blue for
+    /// treble.
+    Frequency,
+    /// The trace is colored by how fast the beam moves, from red where it's slow to blue where
+    /// it's fast.
+    Velocity,
+    /// The trace is colored by the Z channel, from red at 0 to blue at 1.
+    Z,
+}
+
+impl HueMode {
+    /// The modes, in the order they're cycled through.
+    pub const ALL: [HueMode; 4] = [HueMode::Off, HueMode::Frequency, HueMode::Velocity, HueMode::Z];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            HueMode::Off => "off",
+            HueMode::Frequency => "frequency",
+            HueMode::Velocity => "velocity",
+            HueMode::Z => "z",
+        }
+    }
+}
+
+/// What the Z channel does to the beam.
+#[derive(Clone, Copy, PartialEq, Eq, Default)]
+pub enum ZMode {
+    /// The beam is off where the Z channel isn't positive, and fully on elsewhere.
+    #[default]
+    Blank,
+    /// The beam is as bright as the Z channel, from off at 0 to fully on at 1, for drawing
+    /// images in shades of gray.
+    Brightness,
+}
+
+impl ZMode {
+    pub const ALL: [ZMode; 2] = [ZMode::Blank, ZMode::Brightness];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            ZMode::Blank => "blank",
+            ZMode::Brightness => "brightness",
+        }
+    }
+
+    /// Returns how bright the beam is at a value of the Z channel, from 0 (off) to 1.
+    pub fn intensity(self, z: f32) -> f32 {
+        match self {
+            _ if z <= 0.0 => 0.0,
+            ZMode::Blank => 1.0,
+            ZMode::Brightness => z.min(1.0),
+        }
+    }
+}
+
+/// How the light a pixel has received turns into its brightness on screen.
+#[derive(Clone, Copy, PartialEq, Eq, Default)]
+pub enum Transfer {
+    /// The brightness approaches full brightness exponentially, like film does.
+    #[default]
+    Exponential,
+    /// The brightness goes up by the same amount every time the light doubles, which shows
+    /// faint detail next to much brighter areas.
+    Log,
+}
+
+impl Transfer {
+    pub const ALL: [Transfer; 2] = [Transfer::Exponential, Transfer::Log];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Transfer::Exponential => "exponential",
+            Transfer::Log => "log",
+        }
+    }
+}
+
+/// The markings drawn behind the trace.
+#[derive(Clone, Copy, PartialEq, Eq, Default)]
+pub enum Graticule {
+    /// A grid of evenly spaced lines.
+    #[default]
+    Grid,
+    /// Circles of evenly spaced amplitudes, with spokes coming out of the center.
+    Polar,
+    /// Just a line through the center on each axis.
+    Crosshair,
+    /// Circles at 0, -3, -6 and -12 dB, along with the crosshair.
+    Decibels,
+    Off,
+}
+
+impl Graticule {
+    /// The styles, in the order they're cycled through.
+    pub const ALL: [Graticule; 5] = [
+        Graticule::Grid,
+        Graticule::Polar,
+        Graticule::Crosshair,
+        Graticule::Decibels,
+        Graticule::Off,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Graticule::Grid => "grid",
+            Graticule::Polar => "polar",
+            Graticule::Crosshair => "crosshair",
+            Graticule::Decibels => "db",
+            Graticule::Off => "off",
+        }
+    }
+}
+
+/// How far from the center a sample is plotted, depending on its amplitude.
+#[derive(Clone, Copy, PartialEq, Eq, Default)]
+pub enum AmplitudeScale {
+    /// In proportion to the amplitude.
+    #[default]
+    Linear,
+    /// In proportion to the amplitude in decibels, from the bottom of the range at the center to
+    /// 0 dB at the edge, which shows quiet signals next to loud ones.
+    Decibels,
+}
+
+impl AmplitudeScale {
+    pub const ALL: [AmplitudeScale; 2] = [AmplitudeScale::Linear, AmplitudeScale::Decibels];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            AmplitudeScale::Linear => "linear",
+            AmplitudeScale::Decibels => "db",
+        }
+    }
+
+    /// Returns how far from the center an amplitude is plotted, where 1 is the edge. `db_range`
+    /// is how many decibels below full scale the center is on the decibel scale.
+    pub fn apply(self, amplitude: f32, db_range: f32) -> f32 {
+        match self {
+            AmplitudeScale::Linear => amplitude,
+            AmplitudeScale::Decibels => ((20.0 * amplitude.log10() + db_range) / db_range).max(0.0),
+        }
+    }
+
+    /// Returns the amplitude plotted at a distance from the center, the other way around from
+    /// `apply`. On the decibel scale, the center stands for silence.
+    pub fn invert(self, distance: f32, db_range: f32) -> f32 {
+        match self {
+            AmplitudeScale::Linear => distance,
+            AmplitudeScale::Decibels if distance <= 0.0 => 0.0,
+            AmplitudeScale::Decibels => 10f32.powf((distance - 1.0) * db_range / 20.0),
+        }
+    }
+}
+
+/// The kind of phosphor the screen is coated with, which decides the trace's colors and how it
+/// fades out.
+#[derive(Clone, Copy, PartialEq, Eq, Default)]
+pub enum Phosphor {
+    /// Green, with a medium persistence.
+    P1,
+    /// Blue-white while the beam is on it, leaving a long yellow-green afterglow.
+    P7,
+    /// Green with a short persistence, burning white where it's brightest.
+    #[default]
+    P31,
+}
+
+impl Phosphor {
+    pub const ALL: [Phosphor; 3] = [Phosphor::P1, Phosphor::P7, Phosphor::P31];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Phosphor::P1 => "p1",
+            Phosphor::P7 => "p7",
+            Phosphor::P31 => "p31",
+        }
+    }
+}
+
+/// The colors the picture is drawn in.
+#[derive(Clone, Copy, PartialEq, Eq, Default)]
+pub enum Theme {
+    /// The colors of the phosphor the screen is coated with.
+    #[default]
+    Phosphor,
+    Green,
+    Amber,
+    White,
+    Blue,
+    Dracula,
+    /// A dark blue trace on white, like ink on paper, for pictures that are going to be printed.
+    Light,
+    /// Blue and yellow, which stay apart for people who can't tell green from red.
+    Deuteranopia,
+    /// Yellow and blue, keeping away from reds, which look dark to people who can't see them.
+    Protanopia,
+    /// A white trace on black, with a graticule that's much dimmer than the trace.
+    HighContrast,
+    /// The gradient read from the palette file.
+    Custom,
+}
+
+impl Theme {
+    /// The themes, in the order they're cycled through.
+    pub const ALL: [Theme; 11] = [
+        Theme::Phosphor,
+        Theme::Green,
+        Theme::Amber,
+        Theme::White,
+        Theme::Blue,
+        Theme::Dracula,
+        Theme::Light,
+        Theme::Deuteranopia,
+        Theme::Protanopia,
+        Theme::HighContrast,
+        Theme::Custom,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Theme::Phosphor => "phosphor",
+            Theme::Green => "green",
+            Theme::Amber => "amber",
+            Theme::White => "white",
+            Theme::Blue => "blue",
+            Theme::Dracula => "dracula",
+            Theme::Light => "light",
+            Theme::Deuteranopia => "deuteranopia",
+            Theme::Protanopia => "protanopia",
+            Theme::HighContrast => "high-contrast",
+            Theme::Custom => "custom",
+        }
+    }
+}
+
+/// The colors the heatmap and the spectrogram go through from low to high values.
+#[derive(Clone, Copy, PartialEq, Eq, Default)]
+pub enum Colormap {
+    /// Dark blue through green to yellow.
+    #[default]
+    Viridis,
+    /// Black through red and orange to pale yellow.
+    Inferno,
+}
+
+impl Colormap {
+    pub const ALL: [Colormap; 2] = [Colormap::Viridis, Colormap::Inferno];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Colormap::Viridis => "viridis",
+            Colormap::Inferno => "inferno",
+        }
+    }
+}
+
+/// The capture buffer size to request from the device.
+#[derive(Clone, Copy, PartialEq, Eq, Default)]
+pub enum BufferSizeOption {
+    /// A small buffer size for low latency, if the device supports it.
+    #[default]
+    Auto,
+    /// The host's default buffer size.
+    Default,
+    /// A specific number of frames.
+    Fixed(u32),
+}
+
+impl FromStr for BufferSizeOption {
+    type Err = std::num::ParseIntError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "auto" => Ok(BufferSizeOption::Auto),
+            "default" => Ok(BufferSizeOption::Default),
+            _ => s.parse().map(BufferSizeOption::Fixed),
+        }
+    }
+}
+
+/// How much to decimate the captured audio by.
+#[derive(Clone, Copy, PartialEq, Eq, Default)]
+pub enum DecimationOption {
+    #[default]
+    Off,
+    /// Decimate high sample rates down to around 48 kHz.
+    Auto,
+    /// Average every n frames into one.
+    Factor(u32),
+}
+
+impl FromStr for DecimationOption {
+    type Err = std::num::ParseIntError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "off" => Ok(DecimationOption::Off),
+            "auto" => Ok(DecimationOption::Auto),
+            _ => s.parse().map(DecimationOption::Factor),
+        }
+    }
+}
+
+/// What the program should do.
+#[derive(Clone, PartialEq, Eq, Default)]
+pub enum Command {
+    #[default]
+    Scope,
+    ListDevices,
+    /// Display a file as it plays.
+    Play(PathBuf),
+}
+
+/// Options passed on the command line or read from the config file.
+#[derive(Clone, Default)]
+pub struct Options {
+    pub command: Command,
+    /// The audio host to capture with. If unset, the host is picked automatically.
+    pub host: Option<String>,
+    /// The device to capture from. If unset, the host's default input device is used.
+    pub device: Option<DeviceSelector>,
+    /// Whether to capture what the system is playing instead of an input device.
+    pub loopback: bool,
+    /// The sample rate to capture at. If unset, a sample rate is picked automatically.
+    pub sample_rate: Option<u32>,
+    pub buffer_size: BufferSizeOption,
+    pub channels: ChannelPair,
+    /// The number of channel pairs plotted, counting the selected one. If unset, only the
+    /// selected pair is.
+    pub pairs: Option<u16>,
+    /// Whether to read raw samples from standard input instead of capturing from a device.
+    pub stdin: bool,
+    /// The path of a Unix socket to read raw samples from instead of capturing from a device.
+    pub socket: Option<PathBuf>,
+    /// The URL of an HTTP audio stream to display instead of capturing from a device.
+    pub url: Option<String>,
+    /// The UDP address to listen for raw samples on instead of capturing from a device.
+    pub listen: Option<String>,
+    /// Whether the datagrams received over UDP are RTP packets.
+    pub rtp: bool,
+    pub raw_format: RawFormat,
+    /// Whether the raw input is made of complex I/Q samples, with I on X and Q on Y.
+    pub iq: bool,
+    /// The number of channels in raw input. If unset, the input is assumed to be stereo.
+    pub raw_channels: Option<u16>,
+    /// If set, the input is a surround input with this layout, mixed down to stereo.
+    pub surround: Option<SurroundLayout>,
+    /// How much of every surround channel goes into the stereo mix. If unset, the layout's
+    /// default coefficients are used.
+    pub downmix: Option<Vec<f32>>,
+    /// If set, only one input channel is captured and plotted in the given way.
+    pub mono: Option<MonoMode>,
+    pub display_mode: DisplayMode,
+    /// What every pane of the dashboard shows, in order. If unset, it's the XY picture, a sweep
+    /// and the spectrogram.
+    pub panes: Option<Vec<DisplayMode>>,
+    /// How many samples the spectrogram analyzes at once. If unset, it's 2048.
+    pub fft_size: Option<usize>,
+    /// How much every window of the spectrogram overlaps the one before it. If unset, it's 0.75.
+    pub overlap: Option<f32>,
+    pub spectrogram_colormap: Colormap,
+    /// How far back the waterfall goes. If unset, it's 3 seconds.
+    pub waterfall_length: Option<Duration>,
+    /// How many pictures the waterfall stacks up. If unset, it's 24.
+    pub waterfall_slices: Option<usize>,
+    /// How long a sweep across the screen takes when plotting against time. If unset, it's
+    /// 20 milliseconds.
+    pub timebase: Option<Duration>,
+    pub trigger: Trigger,
+    /// The level the X channel has to cross to trigger a sweep. If unset, it's 0.
+    pub trigger_level: Option<f32>,
+    /// Whether the input is mid/side encoded and should be decoded to left/right.
+    pub mid_side: bool,
+    pub decimation: DecimationOption,
+    /// The gain of both axes, on top of their own gains. If unset, the gain is 1.
+    pub gain: Option<f32>,
+    /// Whether the scope is kept square, with bars on either side in windows that aren't.
+    pub square: bool,
+    /// Whether the gain follows the input's level.
+    pub agc: bool,
+    /// Whether the farthest the picture has reached in every direction is outlined.
+    pub envelope: bool,
+    /// How long the outline holds a peak. If unset, it's a second.
+    pub envelope_hold: Option<Duration>,
+    /// How long the outline takes to shrink halfway back after the hold. If unset, it's a second.
+    pub envelope_decay: Option<Duration>,
+    /// Whether the correlation between the channels is shown.
+    pub correlation: bool,
+    /// Whether the width of the stereo image is shown.
+    pub stereo_width: bool,
+    /// Whether the levels of the channels are shown on either side of the picture.
+    pub levels: bool,
+    /// Whether the balance between the channels is shown.
+    pub balance: bool,
+    /// How long the level meters hold a peak. If unset, it's 1.5 seconds.
+    pub peak_hold: Option<Duration>,
+    /// How long the correlation and the stereo width are averaged over. If unset, it's 300
+    /// milliseconds.
+    pub correlation_time: Option<Duration>,
+    /// The gain of the X axis. If unset, the gain is 1.
+    pub gain_x: Option<f32>,
+    /// The gain of the Y axis. If unset, the gain is 1.
+    pub gain_y: Option<f32>,
+    /// How far the picture is moved to the right. If unset, it isn't moved.
+    pub offset_x: Option<f32>,
+    /// How far the picture is moved up. If unset, it isn't moved.
+    pub offset_y: Option<f32>,
+    /// Whether the polarity of the X channel is inverted.
+    pub invert_x: bool,
+    /// Whether the polarity of the Y channel is inverted.
+    pub invert_y: bool,
+    /// Whether the X channel is plotted on the Y axis and the other way around.
+    pub swap_axes: bool,
+    /// Whether the picture is turned 45° so that mid and side are the axes.
+    pub goniometer: bool,
+    /// How many degrees the picture is turned counterclockwise. If unset, it isn't turned.
+    pub rotation: Option<f32>,
+    /// Whether the picture is mirrored horizontally.
+    pub mirror_x: bool,
+    /// Whether the picture is mirrored vertically.
+    pub mirror_y: bool,
+    pub amplitude_scale: AmplitudeScale,
+    /// How many decibels below full scale the center is on the decibel scale. If unset, 60 dB.
+    pub db_range: Option<f32>,
+    /// The number of points to plot per sample. If unset, only the samples themselves are
+    /// plotted.
+    pub oversampling: Option<u32>,
+    /// How long it takes the trace to fade to half its brightness. If unset, a default that
+    /// looks like a real scope is used.
+    pub persistence: Option<Duration>,
+    pub path_mode: PathMode,
+    /// How far apart two points can be before the beam is blanked between them, where 1 is from
+    /// the center to the edge. If unset, the beam is never blanked for being too long.
+    pub blank: Option<f32>,
+    /// The width of the beam, in pixels. If unset, the beam is a pixel wide.
+    pub beam_width: Option<f32>,
+    /// How bright the beam is, relative to its usual brightness. If unset, it's 1.
+    pub intensity: Option<f32>,
+    /// The resolution the picture is drawn at, which is scaled to fit the window. If unset, the
+    /// picture is drawn at the window's size.
+    pub resolution: Option<(u32, u32)>,
+    /// How many pixels the picture is drawn with for every logical pixel of the window, when it's
+    /// drawn at the window's size. If unset, it's drawn with every physical pixel.
+    pub render_scale: Option<f32>,
+    /// How many times bigger than its resolution the picture is drawn before it's scaled down,
+    /// along each axis. If unset, it isn't supersampled.
+    pub supersampling: Option<u32>,
+    pub phosphor: Phosphor,
+    pub hue_mode: HueMode,
+    pub z_mode: ZMode,
+    /// Whether the brightness adjusts itself to the signal.
+    pub auto_exposure: bool,
+    pub transfer: Transfer,
+    /// The gamma the brightness is raised to the inverse of. If unset, the gamma is 1.
+    pub gamma: Option<f32>,
+    pub theme: Theme,
+    /// If set, the heatmap is shown instead of the trace, in the given colors.
+    pub heatmap: Option<Colormap>,
+    /// How long it takes the heatmap's hits to count half as much. If unset, it's 2 seconds.
+    pub heatmap_window: Option<Duration>,
+    pub graticule: Graticule,
+    /// How many lines or circles the graticule is divided into. If unset, there are 5.
+    pub divisions: Option<u32>,
+    /// The color of the graticule. If unset, the theme's color is used.
+    pub graticule_color: Option<(u8, u8, u8)>,
+    /// How much the graticule stands out from the background. If unset, it's fully opaque.
+    pub graticule_opacity: Option<f32>,
+    /// The image drawn over the graticule, if any.
+    pub overlay: Option<PathBuf>,
+    /// How much the overlay covers what's under it. If unset, it's fully opaque.
+    pub overlay_opacity: Option<f32>,
+    /// How big the overlay is, where 1 is as big as fits in the scope. If unset, it's 1.
+    pub overlay_scale: Option<f32>,
+    /// How far the overlay is moved along each axis, where 1 is from the center to the edge.
+    pub overlay_offset: (f32, f32),
+    /// Whether the graticule's center lines are drawn brighter than the rest.
+    pub emphasize_center: bool,
+    /// Whether the graticule is labeled with amplitudes.
+    pub graticule_labels: bool,
+    /// Whether information about the input is shown in the corner.
+    pub hud: bool,
+    /// Whether the mid and side signals are traced along the edges of the scope.
+    pub mid_side_traces: bool,
+    /// Whether the loudness is measured and shown in the heads-up display.
+    pub loudness: bool,
+    /// Whether a crosshair is drawn where the beam is, with the input's values there.
+    pub crosshair: bool,
+    /// The file the custom theme's gradient is read from.
+    pub palette: Option<PathBuf>,
+    /// How bright the light scattered around bright areas is. If unset, there's no bloom.
+    pub bloom: Option<f32>,
+    /// How far the bloom reaches, in pixels. If unset, a default radius is used.
+    pub bloom_radius: Option<u32>,
+    /// Whether the picture starts out looking like it's on a CRT.
+    pub crt: bool,
+    /// Whether the trace is drawn on the GPU when it can be.
+    pub gpu: bool,
+    /// The JACK client name. If unset, a default name is used.
+    pub jack_name: Option<String>,
+    /// Names for the JACK input ports, in order. Ports without a name here keep cpal's names.
+    pub jack_ports: Vec<String>,
+    /// JACK ports to connect the input ports to. If empty, the system's capture ports are used.
+    pub jack_connect: Vec<String>,
+    /// Whether files are displayed without playing them through the output device.
+    pub mute: bool,
+    /// The input shown over the one being displayed, if any.
+    pub compare: Option<CompareSource>,
+    /// How much the compared input is scaled, on top of the gain. If unset, it isn't.
+    pub compare_gain: Option<f32>,
+    /// The color the compared input is drawn in. If unset, a default color is used.
+    pub compare_color: Option<(u8, u8, u8)>,
+    /// Whether the picture is frozen while the JACK transport is stopped.
+    pub follow_transport: bool,
+    /// Whether to start recording the input as soon as it's open.
+    pub record: bool,
+    /// Whether the trace starts out never fading out.
+    pub long_exposure: bool,
+    /// The directory recordings and pictures are saved in. If unset, the current directory is used.
+    pub record_dir: Option<PathBuf>,
+    /// The options that were set, as they'd be written in the config file, so that they can be
+    /// saved again.
+    settings: Vec<(String, String)>,
+}
+
+impl Options {
+    /// The longest line that can be blanked, which is about from one corner to the other.
+    const MAX_BLANK_DISTANCE: f32 = 3.0;
+    /// The range the spectrogram's FFT size can be set in.
+    const FFT_SIZE_RANGE: (usize, usize) = (256, 16384);
+    /// The most the spectrogram's windows can overlap, past which it would analyze about the
+    /// same samples over and over.
+    const MAX_OVERLAP: f32 = 0.95;
+    /// The range the waterfall's length can be set in.
+    const WATERFALL_LENGTH_RANGE: (Duration, Duration) =
+        (Duration::from_millis(100), Duration::from_secs(60));
+    /// The range the number of the waterfall's slices can be set in.
+    const WATERFALL_SLICES_RANGE: (usize, usize) = (2, 100);
+    /// The most of a surround channel that can go into the stereo mix.
+    const MAX_DOWNMIX: f32 = 2.0;
+    /// The most channel pairs that can be plotted at once.
+    const MAX_PAIRS: u16 = 4;
+    /// The range the timebase can be set in.
+    pub const TIMEBASE_RANGE: (Duration, Duration) = (Duration::from_millis(1), Duration::from_secs(2));
+    /// The range the overlay's scale can be set in.
+    const OVERLAY_SCALE_RANGE: (f32, f32) = (0.01, 16.0);
+    /// The range the beam's intensity can be set in.
+    pub const INTENSITY_RANGE: (f32, f32) = (0.1, 10.0);
+    /// The widest beam that can be drawn without slowing rendering to a crawl.
+    const MAX_BEAM_WIDTH: f32 = 16.0;
+    /// The range the gain of both axes can be set in, which covers anything from mic level to
+    /// hot line level.
+    pub const GAIN_RANGE: (f32, f32) = (0.25, 16.0);
+    /// The range the gain of each axis can be set in.
+    pub const AXIS_GAIN_RANGE: (f32, f32) = (0.01, 100.0);
+    /// The range the offset of each axis can be set in. Past these, the picture is off screen.
+    pub const OFFSET_RANGE: (f32, f32) = (-2.0, 2.0);
+    /// The widest decibel range that makes sense with 32-bit float samples.
+    const MAX_DB_RANGE: f32 = 150.0;
+    /// The most lines or circles the graticule can be divided into.
+    const MAX_DIVISIONS: u32 = 32;
+    /// The range the gamma can be set in. Past these, the picture turns into a flat blob.
+    pub const GAMMA_RANGE: (f32, f32) = (0.25, 4.0);
+    /// The brightest the bloom can be made, past which it drowns out the trace.
+    const MAX_BLOOM: f32 = 4.0;
+    /// The farthest the bloom can reach.
+    const MAX_BLOOM_RADIUS: u32 = 64;
+    /// The most points per sample that can be plotted without slowing rendering to a crawl.
+    const MAX_OVERSAMPLING: u32 = 16;
+    /// The range of resolutions the picture can be drawn at. Bigger pictures take too long to
+    /// draw, and smaller ones don't leave room for the text.
+    const MIN_RESOLUTION: u32 = 64;
+    const MAX_RESOLUTION: u32 = 4096;
+    /// The range of render scales. 1 is the resolution of a display that isn't HiDPI.
+    const RENDER_SCALE_RANGE: (f32, f32) = (0.25, 4.0);
+    /// The most times bigger the picture can be drawn, past which it no longer gets smoother.
+    const MAX_SUPERSAMPLING: u32 = 4;
+
+    /// Reads options from the config file, and then from the process's command line arguments.
+    pub fn from_env() -> anyhow::Result<Self> {
+        // --flag=value is treated the same as --flag value
+        let args: Vec<String> = std::env::args()
+            .skip(1)
+            .flat_map(|arg| {
+                match arg.strip_prefix("--").and_then(|flag| flag.split_once('=')) {
+                    Some((flag, value)) => vec![format!("--{}", flag), value.to_owned()],
+                    None => vec![arg],
+                }
+            })
+            .collect();
+
+        let mut options = Options::default();
+        let explicit_config = args.iter()
+            .position(|arg| arg == "--config")
+            .map(|index| {
+                args.get(index + 1)
+                    .map(PathBuf::from)
+                    .ok_or_else(|| anyhow!("--config expects a value"))
+            })
+            .transpose()?;
+        match explicit_config {
+            Some(path) => options.read_config(&path)?,
+            None => if let Some(path) = default_config_path().filter(|path| path.is_file()) {
+                options.read_config(&path)?;
+            },
+        }
+        options.parse_args(args.into_iter())?;
+        Ok(options)
+    }
+
+    pub fn read_config(&mut self, path: &Path) -> anyhow::Result<()> {
+        let config = std::fs::read_to_string(path)
+            .with_context(|| format!("cannot read config file {}", path.display()))?;
+        for (line_number, line) in config.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue
+            }
+            let (name, value) = match line.split_once('=') {
+                Some((name, value)) => (name.trim(), Some(value.trim())),
+                None => (line, None),
+            };
+            let known = self.set(name, &mut ConfigValue { name, value })
+                .with_context(|| format!("{}:{}", path.display(), line_number + 1))?;
+            if !known {
+                bail!("{}:{}: unknown option: {}", path.display(), line_number + 1, name);
+            }
+        }
+        Ok(())
+    }
+
+    /// Sets an option as if it was read from a config file, so that it's saved along with the
+    /// options that were set at startup.
+    pub fn set_value(&mut self, name: &str, value: &str) -> anyhow::Result<()> {
+        match self.set(name, &mut ConfigValue { name, value: Some(value) })? {
+            true => Ok(()),
+            false => bail!("unknown option: {}", name),
+        }
+    }
+
+    /// Writes the options that were set to a config file.
+    pub fn save_config(&self, path: &Path) -> anyhow::Result<()> {
+        let mut config = String::new();
+        for (name, value) in &self.settings {
+            config.push_str(&format!("{} = {}\n", name, value));
+        }
+        std::fs::write(path, config)
+            .with_context(|| format!("cannot write config file {}", path.display()))
+    }
+
+    fn parse_args(&mut self, args: impl Iterator<Item = String>) -> anyhow::Result<()> {
+        let mut args = args.peekable();
+        if let Some(command) = args.next_if(|arg| !arg.starts_with('-')) {
+            self.command = match command.as_str() {
+                "scope" => Command::Scope,
+                "list-devices" => Command::ListDevices,
+                "play" => Command::Play(args.next()
+                    .map(PathBuf::from)
+                    .ok_or_else(|| anyhow!("play expects a file to play"))?),
+                _ => bail!("unknown command: {}\n\n{}", command, USAGE),
+            };
+        }
+        while let Some(arg) = args.next() {
+            match arg.as_str() {
+                // already handled before the config file is read
+                "--config" => {
+                    args.next();
+                },
+                "-h" | "--help" => {
+                    print!("{}", USAGE);
+                    std::process::exit(0);
+                },
+                _ => {
+                    let known = match arg.strip_prefix("--") {
+                        Some(name) => self.set(name, &mut ArgValue { flag: &arg, args: &mut args })?,
+                        None => false,
+                    };
+                    if !known {
+                        bail!("unknown argument: {}\n\n{}", arg, USAGE);
+                    }
+                },
+            }
+        }
+        Ok(())
+    }
+
+    /// Sets the option with the given name, asking for a value if the option needs one. Returns
+    /// `false` if there's no such option.
+    fn set(&mut self, name: &str, value: &mut dyn OptionValue) -> anyhow::Result<bool> {
+        let mut value = RecordedValue { inner: value, recorded: None };
+        let known = self.apply(name, &mut value)?;
+        if let (true, Some(recorded)) = (known, value.recorded) {
+            // a later setting overrides an earlier one, so only the last one is kept
+            self.settings.retain(|(setting, _)| setting != name);
+            self.settings.push((name.to_owned(), recorded));
+        }
+        Ok(known)
+    }
+
+    fn apply(&mut self, name: &str, value: &mut dyn OptionValue) -> anyhow::Result<bool> {
+        match name {
+            "host" => self.host = Some(value.value()?),
+            "device" => self.device = Some(DeviceSelector::parse(&value.value()?)),
+            "device-match" => self.device = Some(DeviceSelector::Pattern(Regex::new(&value.value()?)?)),
+            "loopback" => self.loopback = value.switch()?,
+            "stdin" => self.stdin = value.switch()?,
+            "socket" => self.socket = Some(PathBuf::from(value.value()?)),
+            "url" => self.url = Some(value.value()?),
+            "listen" => self.listen = Some(value.value()?),
+            "rtp" => self.rtp = value.switch()?,
+            "format" => {
+                let choices = [
+                    ("u8", RawFormat::U8),
+                    ("s8", RawFormat::S8),
+                    ("s16le", RawFormat::S16Le),
+                    ("s16be", RawFormat::S16Be),
+                    ("s24le", RawFormat::S24Le),
+                    ("s32le", RawFormat::S32Le),
+                    ("s32be", RawFormat::S32Be),
+                    ("f32le", RawFormat::F32Le),
+                    ("f32be", RawFormat::F32Be),
+                    ("f64le", RawFormat::F64Le),
+                ];
+                self.raw_format = choice(name, &value.value()?, &choices)?;
+            },
+            "iq" => {
+                let choices = [
+                    ("cu8", RawFormat::U8),
+                    ("cs8", RawFormat::S8),
+                    ("cs16", RawFormat::S16Le),
+                    ("cf32", RawFormat::F32Le),
+                ];
+                self.raw_format = choice(name, &value.value()?, &choices)?;
+                self.iq = true;
+                self.raw_channels = Some(2);
+                self.decimation = DecimationOption::Auto;
+                self.path_mode = PathMode::Dots;
+            },
+            "sample-rate" | "rate" => {
+                let sample_rate = parse(name, &value.value()?)?;
+                if sample_rate == 0 {
+                    bail!("the sample rate must be above 0 Hz");
+                }
+                self.sample_rate = Some(sample_rate);
+            },
+            "buffer-size" => self.buffer_size = parse(name, &value.value()?)?,
+            "channels" => {
+                let value = value.value()?;
+                match value.contains(',') {
+                    true => self.channels = ChannelPair::parse(&value)?,
+                    false => self.raw_channels = Some(parse(name, &value)?),
+                }
+            },
+            "pairs" => {
+                let pairs: u16 = parse(name, &value.value()?)?;
+                if !(1..=Self::MAX_PAIRS).contains(&pairs) {
+                    bail!("between 1 and {} channel pairs can be plotted", Self::MAX_PAIRS);
+                }
+                self.pairs = Some(pairs);
+            },
+            "surround" => {
+                let choices: Vec<_> = std::iter::once(("off", None))
+                    .chain(SurroundLayout::ALL.iter().map(|&layout| (layout.name(), Some(layout))))
+                    .collect();
+                self.surround = choice(name, &value.value()?, &choices)?;
+            },
+            "downmix" => {
+                let coefficients = value.value()?.split(',')
+                    .map(|coefficient| parse(name, coefficient.trim()))
+                    .collect::<anyhow::Result<Vec<f32>>>()?;
+                let range = 0.0..=Self::MAX_DOWNMIX;
+                if coefficients.iter().any(|coefficient| !range.contains(coefficient)) {
+                    bail!("the downmix coefficients must be between 0 and {}", Self::MAX_DOWNMIX);
+                }
+                self.downmix = Some(coefficients);
+            },
+            "mono" => {
+                let choices = [
+                    ("off", None),
+                    ("diagonal", Some(MonoMode::Diagonal)),
+                    ("sweep", Some(MonoMode::Sweep)),
+                ];
+                self.mono = choice(name, &value.value()?, &choices)?;
+            },
+            "display" => {
+                let choices = DisplayMode::ALL.map(|mode| (mode.name(), mode));
+                self.display_mode = choice(name, &value.value()?, &choices)?;
+            },
+            "panes" => {
+                // the composite layout and the dashboard take up the whole window, and the
+                // state that a picture is drawn from can only be used once per frame
+                let choices: Vec<_> = DisplayMode::ALL.iter()
+                    .filter(|&&mode| !matches!(mode, DisplayMode::Composite | DisplayMode::Dashboard))
+                    .map(|&mode| (mode.name(), mode))
+                    .collect();
+                let mut panes = Vec::new();
+                for pane in value.value()?.split(',') {
+                    let mode = choice(name, pane.trim(), &choices)?;
+                    if panes.contains(&mode) {
+                        bail!("the dashboard can only have one {} pane", mode.name());
+                    }
+                    panes.push(mode);
+                }
+                self.panes = Some(panes);
+            },
+            "fft-size" => {
+                let size: usize = parse(name, &value.value()?)?;
+                let (min, max) = Self::FFT_SIZE_RANGE;
+                if !size.is_power_of_two() || !(min..=max).contains(&size) {
+                    bail!("the FFT size must be a power of two between {} and {}", min, max);
+                }
+                self.fft_size = Some(size);
+            },
+            "overlap" => {
+                let overlap: f32 = parse(name, &value.value()?)?;
+                if !(0.0..=Self::MAX_OVERLAP).contains(&overlap) {
+                    bail!("the overlap must be between 0 and {}", Self::MAX_OVERLAP);
+                }
+                self.overlap = Some(overlap);
+            },
+            "spectrogram-colormap" => {
+                let choices = Colormap::ALL.map(|colormap| (colormap.name(), colormap));
+                self.spectrogram_colormap = choice(name, &value.value()?, &choices)?;
+            },
+            "waterfall-length" => {
+                let length = duration(name, &value.value()?)?;
+                let (min, max) = Self::WATERFALL_LENGTH_RANGE;
+                if !(min..=max).contains(&length) {
+                    let (min, max) = (min.as_millis(), max.as_secs());
+                    bail!("the waterfall's length must be between {} ms and {} s", min, max);
+                }
+                self.waterfall_length = Some(length);
+            },
+            "waterfall-slices" => {
+                let slices: usize = parse(name, &value.value()?)?;
+                let (min, max) = Self::WATERFALL_SLICES_RANGE;
+                if !(min..=max).contains(&slices) {
+                    bail!("the waterfall must have between {} and {} slices", min, max);
+                }
+                self.waterfall_slices = Some(slices);
+            },
+            "timebase" => {
+                let timebase = duration(name, &value.value()?)?;
+                let (min, max) = Self::TIMEBASE_RANGE;
+                if !(min..=max).contains(&timebase) {
+                    bail!("the timebase must be between {} ms and {} s", min.as_millis(), max.as_secs());
+                }
+                self.timebase = Some(timebase);
+            },
+            "trigger" => {
+                let choices = Trigger::ALL.map(|trigger| (trigger.name(), trigger));
+                self.trigger = choice(name, &value.value()?, &choices)?;
+            },
+            "trigger-level" => {
+                let level: f32 = parse(name, &value.value()?)?;
+                if !(-1.0..=1.0).contains(&level) {
+                    bail!("the trigger level must be between -1 and 1");
+                }
+                self.trigger_level = Some(level);
+            },
+            "ms" => self.mid_side = value.switch()?,
+            "decimate" => self.decimation = parse(name, &value.value()?)?,
+            "gain" => {
+                let gain: f32 = parse(name, &value.value()?)?;
+                let (min, max) = Self::GAIN_RANGE;
+                if !(min..=max).contains(&gain) {
+                    bail!("the gain must be between {} and {}", min, max);
+                }
+                self.gain = Some(gain);
+            },
+            "agc" => self.agc = value.switch()?,
+            "envelope" => self.envelope = value.switch()?,
+            "envelope-hold" => self.envelope_hold = Some(duration(name, &value.value()?)?),
+            "envelope-decay" => self.envelope_decay = Some(duration(name, &value.value()?)?),
+            "correlation" => self.correlation = value.switch()?,
+            "stereo-width" => self.stereo_width = value.switch()?,
+            "levels" => self.levels = value.switch()?,
+            "balance" => self.balance = value.switch()?,
+            "peak-hold" => self.peak_hold = Some(duration(name, &value.value()?)?),
+            "correlation-time" => {
+                let time = duration(name, &value.value()?)?;
+                if time.is_zero() {
+                    bail!("the correlation can't be averaged over no time");
+                }
+                self.correlation_time = Some(time);
+            },
+            "square" => self.square = value.switch()?,
+            "gain-x" | "gain-y" => {
+                let gain: f32 = parse(name, &value.value()?)?;
+                let (min, max) = Self::AXIS_GAIN_RANGE;
+                if !(min..=max).contains(&gain) {
+                    bail!("the gain must be between {} and {}", min, max);
+                }
+                match name {
+                    "gain-x" => self.gain_x = Some(gain),
+                    _ => self.gain_y = Some(gain),
+                }
+            },
+            "offset-x" | "offset-y" => {
+                let offset: f32 = parse(name, &value.value()?)?;
+                let (min, max) = Self::OFFSET_RANGE;
+                if !(min..=max).contains(&offset) {
+                    bail!("the offset must be between {} and {}", min, max);
+                }
+                match name {
+                    "offset-x" => self.offset_x = Some(offset),
+                    _ => self.offset_y = Some(offset),
+                }
+            },
+            "invert-x" => self.invert_x = value.switch()?,
+            "invert-y" => self.invert_y = value.switch()?,
+            "swap-axes" => self.swap_axes = value.switch()?,
+            "goniometer" => self.goniometer = value.switch()?,
+            "rotate" => {
+                let degrees: f32 = parse(name, &value.value()?)?;
+                if !degrees.is_finite() {
+                    bail!("the rotation must be a number of degrees");
+                }
+                self.rotation = Some(degrees.rem_euclid(360.0));
+            },
+            "mirror-x" => self.mirror_x = value.switch()?,
+            "mirror-y" => self.mirror_y = value.switch()?,
+            "amplitude" => {
+                let choices = AmplitudeScale::ALL.map(|scale| (scale.name(), scale));
+                self.amplitude_scale = choice(name, &value.value()?, &choices)?;
+            },
+            "db-range" => {
+                let range: f32 = parse(name, &value.value()?)?;
+                if !(range > 0.0 && range <= Self::MAX_DB_RANGE) {
+                    bail!("the decibel range must be more than 0 and at most {}", Self::MAX_DB_RANGE);
+                }
+                self.db_range = Some(range);
+            },
+            "oversample" => {
+                let factor: u32 = parse(name, &value.value()?)?;
+                if !(1..=Self::MAX_OVERSAMPLING).contains(&factor) {
+                    bail!("the oversampling factor must be between 1 and {}", Self::MAX_OVERSAMPLING);
+                }
+                self.oversampling = Some(factor);
+            },
+            "persistence" => self.persistence = Some(duration(name, &value.value()?)?),
+            "path" => {
+                let choices = PathMode::ALL.map(|mode| (mode.name(), mode));
+                self.path_mode = choice(name, &value.value()?, &choices)?;
+            },
+            "blank" => {
+                let distance: f32 = parse(name, &value.value()?)?;
+                if !(distance > 0.0 && distance <= Self::MAX_BLANK_DISTANCE) {
+                    bail!("the distance must be more than 0 and at most {}", Self::MAX_BLANK_DISTANCE);
+                }
+                self.blank = Some(distance);
+            },
+            "beam-width" => {
+                let width: f32 = parse(name, &value.value()?)?;
+                if !(width > 0.0 && width <= Self::MAX_BEAM_WIDTH) {
+                    bail!("the beam width must be more than 0 and at most {}", Self::MAX_BEAM_WIDTH);
+                }
+                self.beam_width = Some(width);
+            },
+            "intensity" => {
+                let intensity: f32 = parse(name, &value.value()?)?;
+                let (min, max) = Self::INTENSITY_RANGE;
+                if !(min..=max).contains(&intensity) {
+                    bail!("the intensity must be between {} and {}", min, max);
+                }
+                self.intensity = Some(intensity);
+            },
+            "resolution" => {
+                let value = value.value()?;
+                let (width, height) = value.split_once('x')
+                    .ok_or_else(|| anyhow!("invalid resolution: {} (expected eg. 1024x1024)", value))?;
+                let (width, height): (u32, u32) = (parse(name, width)?, parse(name, height)?);
+                let range = Self::MIN_RESOLUTION..=Self::MAX_RESOLUTION;
+                if !range.contains(&width) || !range.contains(&height) {
+                    bail!(
+                        "the resolution must be between {} and {} pixels along each side",
+                        Self::MIN_RESOLUTION,
+                        Self::MAX_RESOLUTION,
+                    );
+                }
+                self.resolution = Some((width, height));
+            },
+            "render-scale" => {
+                let scale: f32 = parse(name, &value.value()?)?;
+                let (min, max) = Self::RENDER_SCALE_RANGE;
+                if !(min..=max).contains(&scale) {
+                    bail!("the render scale must be between {} and {}", min, max);
+                }
+                self.render_scale = Some(scale);
+            },
+            "supersample" => {
+                let factor: u32 = parse(name, &value.value()?)?;
+                if !(1..=Self::MAX_SUPERSAMPLING).contains(&factor) {
+                    bail!("the supersampling factor must be between 1 and {}", Self::MAX_SUPERSAMPLING);
+                }
+                self.supersampling = Some(factor);
+            },
+            "phosphor" => {
+                let choices = Phosphor::ALL.map(|phosphor| (phosphor.name(), phosphor));
+                self.phosphor = choice(name, &value.value()?, &choices)?;
+            },
+            "graticule" => {
+                let choices = Graticule::ALL.map(|graticule| (graticule.name(), graticule));
+                self.graticule = choice(name, &value.value()?, &choices)?;
+            },
+            "divisions" => {
+                let divisions: u32 = parse(name, &value.value()?)?;
+                if !(1..=Self::MAX_DIVISIONS).contains(&divisions) {
+                    bail!("the number of divisions must be between 1 and {}", Self::MAX_DIVISIONS);
+                }
+                self.divisions = Some(divisions);
+            },
+            "graticule-color" => self.graticule_color = Some(palette::parse_color(&value.value()?)?),
+            "graticule-opacity" => {
+                let opacity: f32 = parse(name, &value.value()?)?;
+                if !(0.0..=1.0).contains(&opacity) {
+                    bail!("the graticule opacity must be between 0 and 1");
+                }
+                self.graticule_opacity = Some(opacity);
+            },
+            "overlay" => self.overlay = Some(PathBuf::from(value.value()?)),
+            "overlay-opacity" => {
+                let opacity: f32 = parse(name, &value.value()?)?;
+                if !(0.0..=1.0).contains(&opacity) {
+                    bail!("the overlay opacity must be between 0 and 1");
+                }
+                self.overlay_opacity = Some(opacity);
+            },
+            "overlay-scale" => {
+                let scale: f32 = parse(name, &value.value()?)?;
+                let (min, max) = Self::OVERLAY_SCALE_RANGE;
+                if !(min..=max).contains(&scale) {
+                    bail!("the overlay scale must be between {} and {}", min, max);
+                }
+                self.overlay_scale = Some(scale);
+            },
+            "overlay-offset" => {
+                let value = value.value()?;
+                let (x, y) = value.split_once(',')
+                    .ok_or_else(|| anyhow!("invalid offset: {} (expected eg. 0.5,-0.25)", value))?;
+                let (x, y): (f32, f32) = (parse(name, x.trim())?, parse(name, y.trim())?);
+                let (min, max) = Self::OFFSET_RANGE;
+                if !(min..=max).contains(&x) || !(min..=max).contains(&y) {
+                    bail!("the offset must be between {} and {}", min, max);
+                }
+                self.overlay_offset = (x, y);
+            },
+            "emphasize-center" => self.emphasize_center = value.switch()?,
+            "labels" => self.graticule_labels = value.switch()?,
+            "hud" => self.hud = value.switch()?,
+            "mid-side-traces" => self.mid_side_traces = value.switch()?,
+            "loudness" => self.loudness = value.switch()?,
+            "crosshair" => self.crosshair = value.switch()?,
+            "heatmap" => {
+                let choices = [
+                    ("off", None),
+                    ("viridis", Some(Colormap::Viridis)),
+                    ("inferno", Some(Colormap::Inferno)),
+                ];
+                self.heatmap = choice(name, &value.value()?, &choices)?;
+            },
+            "heatmap-window" => self.heatmap_window = Some(duration(name, &value.value()?)?),
+            "theme" => {
+                let choices = Theme::ALL.map(|theme| (theme.name(), theme));
+                self.theme = choice(name, &value.value()?, &choices)?;
+            },
+            "accessibility" => {
+                let choices = [Theme::Deuteranopia, Theme::Protanopia, Theme::HighContrast]
+                    .map(|theme| (theme.name(), theme));
+                self.theme = choice(name, &value.value()?, &choices)?;
+            },
+            "palette" => {
+                self.palette = Some(PathBuf::from(value.value()?));
+                self.theme = Theme::Custom;
+            },
+            "z-mode" => {
+                let choices = ZMode::ALL.map(|mode| (mode.name(), mode));
+                self.z_mode = choice(name, &value.value()?, &choices)?;
+            },
+            "hue" => {
+                let choices = HueMode::ALL.map(|mode| (mode.name(), mode));
+                self.hue_mode = choice(name, &value.value()?, &choices)?;
+            },
+            "auto-exposure" => self.auto_exposure = value.switch()?,
+            "transfer" => {
+                let choices = Transfer::ALL.map(|transfer| (transfer.name(), transfer));
+                self.transfer = choice(name, &value.value()?, &choices)?;
+            },
+            "gamma" => {
+                let gamma: f32 = parse(name, &value.value()?)?;
+                let (min, max) = Self::GAMMA_RANGE;
+                if !(min..=max).contains(&gamma) {
+                    bail!("the gamma must be between {} and {}", min, max);
+                }
+                self.gamma = Some(gamma);
+            },
+            "bloom" => {
+                let intensity: f32 = parse(name, &value.value()?)?;
+                if !(0.0..=Self::MAX_BLOOM).contains(&intensity) {
+                    bail!("the bloom intensity must be between 0 and {}", Self::MAX_BLOOM);
+                }
+                self.bloom = Some(intensity).filter(|&intensity| intensity > 0.0);
+            },
+            "bloom-radius" => {
+                let radius: u32 = parse(name, &value.value()?)?;
+                if !(1..=Self::MAX_BLOOM_RADIUS).contains(&radius) {
+                    bail!("the bloom radius must be between 1 and {}", Self::MAX_BLOOM_RADIUS);
+                }
+                self.bloom_radius = Some(radius);
+            },
+            "crt" => self.crt = value.switch()?,
+            "gpu" => self.gpu = value.switch()?,
+            "jack-name" => self.jack_name = Some(value.value()?),
+            "jack-ports" => self.jack_ports = list(&value.value()?),
+            "jack-connect" => self.jack_connect = list(&value.value()?),
+            "mute" => self.mute = value.switch()?,
+            "compare-file" => self.compare = Some(CompareSource::File(PathBuf::from(value.value()?))),
+            "compare-device" =>
+                self.compare = Some(CompareSource::Device(DeviceSelector::parse(&value.value()?))),
+            "compare-gain" => {
+                let gain: f32 = parse(name, &value.value()?)?;
+                let (min, max) = Self::GAIN_RANGE;
+                if !(min..=max).contains(&gain) {
+                    bail!("the compared input's gain must be between {} and {}", min, max);
+                }
+                self.compare_gain = Some(gain);
+            },
+            "compare-color" => self.compare_color = Some(palette::parse_color(&value.value()?)?),
+            "follow-transport" => self.follow_transport = value.switch()?,
+            "record" => self.record = value.switch()?,
+            "long-exposure" => self.long_exposure = value.switch()?,
+            "record-dir" => self.record_dir = Some(PathBuf::from(value.value()?)),
+            _ => return Ok(false),
+        }
+        Ok(true)
+    }
+}
+
+/// Where an option's value comes from.
+trait OptionValue {
+    /// Returns the option's value, or an error if it wasn't given one.
+    fn value(&mut self) -> anyhow::Result<String>;
+    /// Returns whether a switch is turned on.
+    fn switch(&mut self) -> anyhow::Result<bool>;
+}
+
+/// The argument following an option on the command line. Switches don't take a value there,
+/// they're turned on just by being present.
+struct ArgValue<'a, I> {
+    flag: &'a str,
+    args: &'a mut I,
+}
+
+impl<I: Iterator<Item = String>> OptionValue for ArgValue<'_, I> {
+    fn value(&mut self) -> anyhow::Result<String> {
+        self.args.next().ok_or_else(|| anyhow!("{} expects a value", self.flag))
+    }
+
+    fn switch(&mut self) -> anyhow::Result<bool> {
+        Ok(true)
+    }
+}
+
+/// The value after the `=` on a config file line. Switches may be written without a value, which
+/// turns them on.
+struct ConfigValue<'a> {
+    name: &'a str,
+    value: Option<&'a str>,
+}
+
+impl OptionValue for ConfigValue<'_> {
+    fn value(&mut self) -> anyhow::Result<String> {
+        self.value.map(str::to_owned).ok_or_else(|| anyhow!("{} expects a value", self.name))
+    }
+
+    fn switch(&mut self) -> anyhow::Result<bool> {
+        match self.value {
+            None => Ok(true),
+            Some(value) => choice(self.name, value, &[("true", true), ("false", false)]),
+        }
+    }
+}
+
+/// Remembers the value an option was set to.
+struct RecordedValue<'a> {
+    inner: &'a mut dyn OptionValue,
+    recorded: Option<String>,
+}
+
+impl OptionValue for RecordedValue<'_> {
+    fn value(&mut self) -> anyhow::Result<String> {
+        let value = self.inner.value()?;
+        self.recorded = Some(value.clone());
+        Ok(value)
+    }
+
+    fn switch(&mut self) -> anyhow::Result<bool> {
+        let on = self.inner.switch()?;
+        self.recorded = Some(on.to_string());
+        Ok(on)
+    }
+}
+
+/// Returns where the config file is looked for when it's not given explicitly.
+fn default_config_path() -> Option<PathBuf> {
+    let config_dir = if cfg!(target_os = "windows") {
+        std::env::var_os("APPDATA").map(PathBuf::from)
+    } else {
+        std::env::var_os("XDG_CONFIG_HOME")
+            .map(PathBuf::from)
+            .or_else(|| std::env::var_os("HOME").map(|home| Path::new(&home).join(".config")))
+    };
+    Some(config_dir?.join("vectorscope").join("config"))
+}
+
+fn parse<T>(name: &str, value: &str) -> anyhow::Result<T>
+where
+    T: FromStr,
+    T::Err: std::error::Error + Send + Sync + 'static,
+{
+    value.parse().with_context(|| format!("invalid value for {}: {}", name, value))
+}
+
+/// Parses a positive duration in seconds (`0.5s`) or milliseconds (`120ms`, or just `120`).
+fn duration(name: &str, value: &str) -> anyhow::Result<Duration> {
+    let (number, unit) = match (value.strip_suffix("ms"), value.strip_suffix('s')) {
+        (Some(number), _) => (number, 0.001),
+        (None, Some(number)) => (number, 1.0),
+        (None, None) => (value, 0.001),
+    };
+    let seconds: f64 = parse(name, number.trim())?;
+    if !(seconds > 0.0 && seconds.is_finite()) {
+        bail!("invalid value for {}: {} (expected a positive duration)", name, value);
+    }
+    // durations too long to be represented are as invalid as negative ones
+    Duration::try_from_secs_f64(seconds * unit)
+        .map_err(|_| anyhow!("invalid value for {}: {} (expected a positive duration)", name, value))
+}
+
+/// Parses a comma-separated list of names.
+fn list(value: &str) -> Vec<String> {
+    value.split(',')
+        .map(str::trim)
+        .filter(|name| !name.is_empty())
+        .map(str::to_owned)
+        .collect()
+}
+
+/// Parses a value that must be one of the given choices.
+fn choice<T: Copy>(name: &str, value: &str, choices: &[(&str, T)]) -> anyhow::Result<T> {
+    choices.iter()
+        .find(|(choice, _)| *choice == value)
+        .map(|&(_, parsed)| parsed)
+        .ok_or_else(|| {
+            let names: Vec<_> = choices.iter().map(|(choice, _)| *choice).collect();
+            anyhow!("invalid value for {}: {} (expected one of: {})", name, value, names.join(", "))
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Writes a config file with the given contents, reads it into default options, and removes it.
+    fn config(contents: &str) -> anyhow::Result<Options> {
+        let path = std::env::temp_dir()
+            .join(format!("vectorscope-config-test-{:?}", std::thread::current().id()));
+        std::fs::write(&path, contents).unwrap();
+        let mut options = Options::default();
+        let result = options.read_config(&path);
+        std::fs::remove_file(&path).unwrap();
+        result.map(|_| options)
+    }
+
+    fn args(args: &[&str]) -> anyhow::Result<Options> {
+        let mut options = Options::default();
+        options.parse_args(args.iter().map(|arg| arg.to_string()))?;
+        Ok(options)
+    }
+
+    #[test]
+    fn duration_units() {
+        assert_eq!(duration("t", "120").unwrap(), Duration::from_millis(120));
+        assert_eq!(duration("t", "120ms").unwrap(), Duration::from_millis(120));
+        assert_eq!(duration("t", "0.5s").unwrap(), Duration::from_millis(500));
+        assert_eq!(duration("t", " 2 s").unwrap(), Duration::from_secs(2));
+        for invalid in ["0", "-5ms", "0s", "nan", "infs", "s", "5m", "1e300s", "1e300"] {
+            assert!(duration("t", invalid).is_err(), "{} should not parse", invalid);
+        }
+    }
+
+    #[test]
+    fn channel_pairs() {
+        let pair = ChannelPair::parse("3, 4").unwrap();
+        assert!(pair == ChannelPair { x: 2, y: 3, z: None });
+        let pair = ChannelPair::parse("1,2,5").unwrap();
+        assert!(pair == ChannelPair { x: 0, y: 1, z: Some(4) });
+        assert_eq!(pair.required_channels(), 5);
+        assert!(pair.shift(1) == ChannelPair { x: 2, y: 3, z: None });
+        for invalid in ["1", "0,1", "1,x", "1,2,0", "1,2,3,4", ",2", "1,-2"] {
+            assert!(ChannelPair::parse(invalid).is_err(), "{} should not parse", invalid);
+        }
+    }
+
+    #[test]
+    fn config_switches() {
+        let options = config("# a comment\n\nloopback\nms = true\nlevels = false\n").unwrap();
+        assert!(options.loopback && options.mid_side && !options.levels);
+        assert!(config("loopback = yes\n").is_err());
+        let error = config("gain = 2\nno-such-option\n").err().unwrap().to_string();
+        assert!(error.ends_with(":2: unknown option: no-such-option"), "{}", error);
+        // an option that needs a value can't be given as a bare switch
+        assert!(config("gain\n").is_err());
+        assert!(config("rate = 0\n").is_err());
+        assert_eq!(config("rate = 44100\n").unwrap().sample_rate, Some(44100));
+    }
+
+    #[test]
+    fn later_settings_override_earlier_ones() {
+        let mut options = args(&["--gain", "2", "--ms", "--gain", "4"]).unwrap();
+        assert_eq!(options.gain, Some(4.0));
+        options.set_value("persistence", "0.5s").unwrap();
+        options.set_value("gain", "8").unwrap();
+        let settings: Vec<_> = options.settings.iter()
+            .map(|(name, value)| (name.as_str(), value.as_str()))
+            .collect();
+        assert_eq!(settings, [("ms", "true"), ("persistence", "0.5s"), ("gain", "8")]);
+        assert!(options.set_value("no-such-option", "1").is_err());
+    }
+
+    #[test]
+    fn config_round_trip() {
+        let options = args(&["--channels", "3,4", "--timebase", "20ms", "--crt", "--gain", "2.5"]).unwrap();
+        let path = std::env::temp_dir().join(format!("vectorscope-save-test-{}", std::process::id()));
+        options.save_config(&path).unwrap();
+        let mut read = Options::default();
+        let result = read.read_config(&path);
+        std::fs::remove_file(&path).unwrap();
+        result.unwrap();
+        assert!(read.channels == options.channels);
+        assert_eq!(read.timebase, Some(Duration::from_millis(20)));
+        assert!(read.crt);
+        assert_eq!(read.gain, Some(2.5));
+        assert_eq!(read.settings, options.settings);
+    }
+}
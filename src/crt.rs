@@ -0,0 +1,110 @@
+/// Makes the picture look like it's on the face of a vintage scope's tube: curved, with
+/// scanlines, darker towards the edges and with a reflection on the glass.
+///
+/// Everything the effect does to a pixel only depends on where the pixel is, so it's worked out
+/// once for every pixel, and applying it to a frame is just a lookup.
+pub struct Crt {
+    width: u32,
+    /// What every pixel of the output is made of, or `None` for pixels outside the tube.
+    texels: Vec<Option<Texel>>,
+    /// A copy of the frame, which the output is sampled from.
+    source: Vec<u8>,
+}
+
+/// Where a pixel of the output comes from, and how it's lit.
+#[derive(Clone, Copy)]
+struct Texel {
+    /// The index of the top left pixel of the 2×2 square the pixel is interpolated from.
+    index: usize,
+    /// How much each pixel of the square contributes: top left, top right, bottom left and
+    /// bottom right.
+    weights: [f32; 4],
+    /// How much of the pixel's light makes it through the scanlines and vignette.
+    gain: f32,
+    /// How much light the reflection adds to the pixel.
+    glare: f32,
+}
+
+impl Crt {
+    /// How much the tube bulges out. The corners are pushed in by about this fraction.
+    const CURVATURE: f32 = 0.08;
+    /// How many pixels apart the scanlines are.
+    const SCANLINE_PERIOD: f32 = 3.0;
+    /// How much darker the scanlines are than the lines between them.
+    const SCANLINE_DEPTH: f32 = 0.2;
+    /// How much darker the corners are than the middle.
+    const VIGNETTE: f32 = 0.4;
+    /// Where the reflection is, and how big, relative to the half-width of the screen.
+    const GLARE_CENTER: (f32, f32) = (-0.45, -0.55);
+    const GLARE_SIZE: f32 = 0.35;
+    /// How bright the middle of the reflection is.
+    const GLARE_BRIGHTNESS: f32 = 20.0;
+
+    pub fn new(width: u32, height: u32) -> Self {
+        let texels = (0..height)
+            .flat_map(|y| (0..width).map(move |x| (x, y)))
+            .map(|(x, y)| Self::texel(x, y, width, height))
+            .collect();
+        Crt { width, texels, source: vec![0; (width * height * 4) as usize] }
+    }
+
+    fn texel(x: u32, y: u32, width: u32, height: u32) -> Option<Texel> {
+        // -1 to 1 across the screen, taking the middle of the pixel
+        let u = (x as f32 + 0.5) / width as f32 * 2.0 - 1.0;
+        let v = (y as f32 + 0.5) / height as f32 * 2.0 - 1.0;
+        let distance = u * u + v * v;
+
+        // barrel distortion: the farther from the middle, the more of the picture gets squeezed
+        // into the same space
+        let scale = 1.0 + Self::CURVATURE * distance;
+        let (source_u, source_v) = (u * scale, v * scale);
+        if source_u.abs() > 1.0 || source_v.abs() > 1.0 {
+            return None
+        }
+        let source_x = ((source_u + 1.0) / 2.0 * width as f32 - 0.5).clamp(0.0, (width - 1) as f32);
+        let source_y = ((source_v + 1.0) / 2.0 * height as f32 - 0.5).clamp(0.0, (height - 1) as f32);
+        // the square is kept inside the frame, with the weights making up for it at the edges
+        let left = (source_x as u32).min(width.saturating_sub(2));
+        let top = (source_y as u32).min(height.saturating_sub(2));
+        let (fx, fy) = (source_x - left as f32, source_y - top as f32);
+        let weights = [(1.0 - fx) * (1.0 - fy), fx * (1.0 - fy), (1.0 - fx) * fy, fx * fy];
+
+        let phase = y as f32 / Self::SCANLINE_PERIOD * std::f32::consts::TAU;
+        let scanline = 1.0 - Self::SCANLINE_DEPTH * (0.5 + 0.5 * phase.cos());
+        let vignette = 1.0 - Self::VIGNETTE * distance / 2.0;
+        let glare_distance = (u - Self::GLARE_CENTER.0).powi(2) + (v - Self::GLARE_CENTER.1).powi(2);
+        let glare = Self::GLARE_BRIGHTNESS * (-glare_distance / Self::GLARE_SIZE.powi(2)).exp();
+
+        Some(Texel {
+            index: (left + top * width) as usize,
+            weights,
+            gain: scanline * vignette,
+            glare,
+        })
+    }
+
+    /// Applies the effect to an RGBA frame the size the effect was created for.
+    pub fn apply(&mut self, frame: &mut [u8]) {
+        self.source.copy_from_slice(frame);
+        let row = self.width as usize;
+        for (texel, pixel) in self.texels.iter().zip(frame.chunks_exact_mut(4)) {
+            let texel = match texel {
+                Some(texel) => texel,
+                None => {
+                    pixel[..3].fill(0);
+                    continue
+                },
+            };
+            let corners = [texel.index, texel.index + 1, texel.index + row, texel.index + row + 1];
+            for (channel, value) in pixel[..3].iter_mut().enumerate() {
+                let sampled: f32 = corners.iter()
+                    .zip(&texel.weights)
+                    .map(|(&index, &weight)| {
+                        self.source.get(index * 4 + channel).map_or(0.0, |&value| value as f32 * weight)
+                    })
+                    .sum();
+                *value = (sampled * texel.gain + texel.glare).min(255.0) as u8;
+            }
+        }
+    }
+}
@@ -0,0 +1,852 @@
+use std::{
+    future::Future,
+    ops::Range,
+    pin::pin,
+    task::{Context, Poll, Waker},
+};
+
+use anyhow::bail;
+use pixels::{wgpu::{self, util::DeviceExt}, PixelsContext};
+
+use crate::{cli::Transfer, palette::Palette, Plot};
+
+/// A line the beam traces, as it's drawn on the GPU.
+#[derive(Clone, Copy)]
+struct Segment {
+    /// Where the line starts, in pixels of the canvas.
+    origin: (f32, f32),
+    /// Which way the line goes, as a unit vector.
+    axis: (f32, f32),
+    length: f32,
+    /// How much light the line puts down along every pixel of it.
+    intensity: f32,
+}
+
+impl Segment {
+    /// How many bytes a segment takes up in the vertex buffer: three pairs of floats.
+    const SIZE: u64 = 24;
+}
+
+/// The trace of a frame, as the plot hands it over to the GPU: the lines the beam has traced,
+/// and how they're shown.
+#[derive(Default)]
+pub struct Frame {
+    segments: Vec<Segment>,
+    /// How much of its light each of the phosphor's layers keeps for the next frame.
+    pub decay: [f32; 2],
+    /// The columns and rows of the canvas the trace is shown in. It's cut off everywhere else,
+    /// eg. behind the letterbox bars.
+    pub clip: (Range<u32>, Range<u32>),
+    pub exposure: f32,
+    pub transfer: Transfer,
+    /// The gamma the brightness is raised to the inverse of.
+    pub gamma: f32,
+}
+
+impl Frame {
+    /// Adds a line the beam traces between two points of the canvas, putting down `intensity`
+    /// along every pixel of it. Lines shorter than a pixel are drawn a pixel long, with as much
+    /// light as a pixel of a longer line, like the CPU draws them as a spot.
+    pub fn line(&mut self, start: (f32, f32), end: (f32, f32), intensity: f32) {
+        let length = (end.0 - start.0).hypot(end.1 - start.1);
+        let axis = match length > 0.0 {
+            true => ((end.0 - start.0) / length, (end.1 - start.1) / length),
+            false => (1.0, 0.0),
+        };
+        // short lines are stretched out evenly on either side
+        let stretch = (1.0 - length).max(0.0) / 2.0;
+        let origin = (start.0 - axis.0 * stretch, start.1 - axis.1 * stretch);
+        self.segments.push(Segment { origin, axis, length: length.max(1.0), intensity });
+    }
+}
+
+/// Draws the trace on the GPU, over the frame the CPU has drawn everything else in.
+///
+/// Every line the beam traces is drawn as a quad, one instance a line, with additive blending
+/// into a texture of the frame's fresh light. A compute shader then adds the fresh light to the
+/// light kept in a texture from frame to frame, colors it with the palette's ramps into a texture
+/// of the trace, and fades it out for the next frame, in the same order as the CPU. A last pass
+/// adds the trace to the frame on screen byte for byte in sRGB, cut off at full brightness, the
+/// way the CPU adds it to the pixels. Only the lines traced in a frame are uploaded; the light
+/// stays on the GPU.
+///
+/// The shaders are written in GLSL and checked in compiled to SPIR-V, so that building doesn't
+/// need a shader compiler. After changing one, compile it again with glslc from the Vulkan SDK
+/// or shaderc: `glslc shaders/<name> -o shaders/<name>.spv`, eg.
+/// `glslc shaders/beam.frag -o shaders/beam.frag.spv`.
+///
+/// The tests that run the shaders need a GPU, so they're only built with the `gpu-tests` feature:
+/// `cargo test --features gpu-tests`.
+pub struct Renderer {
+    width: u32,
+    height: u32,
+    /// The light every pixel has, in a layer of the phosphor a channel.
+    light: wgpu::Texture,
+    /// The light the beam puts down in the frame being drawn, before it's shared out between the
+    /// layers.
+    fresh: wgpu::TextureView,
+    /// The palette's ramps, a row a layer.
+    ramps: wgpu::Texture,
+    /// The colored light, before it's added to the frame.
+    trace: wgpu::TextureView,
+    /// The segments of the frame being drawn, and how many of them fit.
+    segments: wgpu::Buffer,
+    capacity: u64,
+    /// How the light fades out and how it's colored, after the size of the canvas, and where it's
+    /// shown in the window. The shape of the beam, whose uniforms stay the same, is in the beam's
+    /// bind group.
+    light_uniform: wgpu::Buffer,
+    placement_uniform: wgpu::Buffer,
+    beam_pipeline: wgpu::RenderPipeline,
+    decay_pipeline: wgpu::ComputePipeline,
+    trace_pipeline: wgpu::RenderPipeline,
+    beam_bind_group: wgpu::BindGroup,
+    decay_bind_group: wgpu::BindGroup,
+    /// The trace's bind group is made every frame, since the frame it's added to is in a texture
+    /// of the window's.
+    trace_layout: wgpu::BindGroupLayout,
+    /// The share of the beam's light that goes into each layer.
+    shares: [f32; 2],
+    dark_trace: bool,
+}
+
+impl Renderer {
+    /// The format the light is kept in, with the layers in the first two channels. It needs full
+    /// precision, so that slow fades still fade out from one frame to the next.
+    const LIGHT_FORMAT: wgpu::TextureFormat = wgpu::TextureFormat::Rgba32Float;
+    /// The format the fresh light is drawn in, which the beam's quads are added up in.
+    const FRESH_FORMAT: wgpu::TextureFormat = wgpu::TextureFormat::R16Float;
+    /// The format the colored trace is kept in until it's added to the frame.
+    const TRACE_FORMAT: wgpu::TextureFormat = wgpu::TextureFormat::Rgba16Float;
+    /// The format of the frame on screen, which is the one the pixel buffer is shown in.
+    const TARGET_FORMAT: wgpu::TextureFormat = wgpu::TextureFormat::Bgra8UnormSrgb;
+    /// How many pixels across and down the compute shader works on at a time, as set in it.
+    const WORK_GROUP_SIZE: u32 = 8;
+
+    /// Creates a renderer for a canvas of the given size, for a beam of the given width in pixels
+    /// and a phosphor whose layers get the given shares of its light.
+    pub fn new(
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        (width, height): (u32, u32),
+        beam_width: f32,
+        shares: [f32; 2],
+        palette: &Palette,
+    ) -> Self {
+        let texture = |label, size: (u32, u32), format, usage| {
+            device.create_texture(&wgpu::TextureDescriptor {
+                label: Some(label),
+                size: wgpu::Extent3d { width: size.0, height: size.1, depth: 1 },
+                mip_level_count: 1,
+                sample_count: 1,
+                dimension: wgpu::TextureDimension::D2,
+                format,
+                usage,
+            })
+        };
+        let light_usage = wgpu::TextureUsage::STORAGE
+            | wgpu::TextureUsage::COPY_SRC
+            | wgpu::TextureUsage::COPY_DST;
+        let light = texture("light", (width, height), Self::LIGHT_FORMAT, light_usage);
+        let fresh_usage = wgpu::TextureUsage::OUTPUT_ATTACHMENT | wgpu::TextureUsage::SAMPLED;
+        let fresh = texture("fresh light", (width, height), Self::FRESH_FORMAT, fresh_usage);
+        let trace_usage = wgpu::TextureUsage::STORAGE | wgpu::TextureUsage::SAMPLED;
+        let trace = texture("trace", (width, height), Self::TRACE_FORMAT, trace_usage);
+        // the ramps are added up as they are, in sRGB, like on the CPU
+        let ramps_usage = wgpu::TextureUsage::SAMPLED | wgpu::TextureUsage::COPY_DST;
+        let ramps = texture("ramps", (256, 2), wgpu::TextureFormat::Rgba8Unorm, ramps_usage);
+        let view = |texture: &wgpu::Texture| texture.create_view(&wgpu::TextureViewDescriptor::default());
+        let (light_view, fresh, trace, ramps_view) = (view(&light), view(&fresh), view(&trace), view(&ramps));
+
+        let capacity = 1024;
+        let segments = Self::segment_buffer(device, capacity);
+        let uniform = |label, contents: &[u8]| {
+            device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+                label: Some(label),
+                contents,
+                usage: wgpu::BufferUsage::UNIFORM | wgpu::BufferUsage::COPY_DST,
+            })
+        };
+        let beam_uniform = uniform("beam", &bytes(&Self::beam_shape((width, height), beam_width)));
+        // the size of the canvas never changes, and the rest is written every frame
+        let mut light_uniform = [width, height].map(u32::to_ne_bytes).concat();
+        light_uniform.resize(48, 0);
+        let light_uniform = uniform("light", &light_uniform);
+        let placement_uniform = uniform("placement", &[0; 32]);
+
+        let texture_entry = |binding, visibility| wgpu::BindGroupLayoutEntry {
+            binding,
+            visibility,
+            ty: wgpu::BindingType::SampledTexture {
+                dimension: wgpu::TextureViewDimension::D2,
+                component_type: wgpu::TextureComponentType::Float,
+                multisampled: false,
+            },
+            count: None,
+        };
+        let storage_entry = |binding, format, readonly| wgpu::BindGroupLayoutEntry {
+            binding,
+            visibility: wgpu::ShaderStage::COMPUTE,
+            ty: wgpu::BindingType::StorageTexture {
+                dimension: wgpu::TextureViewDimension::D2,
+                format,
+                readonly,
+            },
+            count: None,
+        };
+        let uniform_entry = |binding, visibility| wgpu::BindGroupLayoutEntry {
+            binding,
+            visibility,
+            ty: wgpu::BindingType::UniformBuffer { dynamic: false, min_binding_size: None },
+            count: None,
+        };
+        let layout = |label, entries: &[wgpu::BindGroupLayoutEntry]| {
+            device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor { label: Some(label), entries })
+        };
+        let beam_layout = layout("beam", &[
+            uniform_entry(0, wgpu::ShaderStage::VERTEX | wgpu::ShaderStage::FRAGMENT),
+        ]);
+        let decay_layout = layout("decay", &[
+            storage_entry(0, Self::LIGHT_FORMAT, false),
+            texture_entry(1, wgpu::ShaderStage::COMPUTE),
+            texture_entry(2, wgpu::ShaderStage::COMPUTE),
+            storage_entry(3, Self::TRACE_FORMAT, false),
+            uniform_entry(4, wgpu::ShaderStage::COMPUTE),
+        ]);
+        let trace_layout = layout("trace", &[
+            texture_entry(0, wgpu::ShaderStage::FRAGMENT),
+            uniform_entry(1, wgpu::ShaderStage::FRAGMENT),
+            texture_entry(2, wgpu::ShaderStage::FRAGMENT),
+        ]);
+
+        let bind_group = |label, layout, resources: &[wgpu::BindingResource]| {
+            let entries: Vec<wgpu::BindGroupEntry> = resources.iter()
+                .enumerate()
+                .map(|(binding, resource)| {
+                    wgpu::BindGroupEntry { binding: binding as u32, resource: resource.clone() }
+                })
+                .collect();
+            let descriptor = wgpu::BindGroupDescriptor { label: Some(label), layout, entries: &entries };
+            device.create_bind_group(&descriptor)
+        };
+        let beam_bind_group = bind_group("beam", &beam_layout, &[
+            wgpu::BindingResource::Buffer(beam_uniform.slice(..)),
+        ]);
+        let decay_bind_group = bind_group("decay", &decay_layout, &[
+            wgpu::BindingResource::TextureView(&light_view),
+            wgpu::BindingResource::TextureView(&fresh),
+            wgpu::BindingResource::TextureView(&ramps_view),
+            wgpu::BindingResource::TextureView(&trace),
+            wgpu::BindingResource::Buffer(light_uniform.slice(..)),
+        ]);
+
+        let shader = |source| device.create_shader_module(source);
+        let beam_vertex = shader(wgpu::include_spirv!("../shaders/beam.vert.spv"));
+        let beam_fragment = shader(wgpu::include_spirv!("../shaders/beam.frag.spv"));
+        let decay = shader(wgpu::include_spirv!("../shaders/decay.comp.spv"));
+        let fullscreen = shader(wgpu::include_spirv!("../shaders/fullscreen.vert.spv"));
+        let trace_fragment = shader(wgpu::include_spirv!("../shaders/trace.frag.spv"));
+        let stage = |module| wgpu::ProgrammableStageDescriptor { module, entry_point: "main" };
+        let pipeline_layout = |label, layout| {
+            device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+                label: Some(label),
+                bind_group_layouts: &[layout],
+                push_constant_ranges: &[],
+            })
+        };
+        let pipeline = |label, layout, (vertex, fragment), format, blend, topology, buffers: &[_]| {
+            device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+                label: Some(label),
+                layout: Some(&pipeline_layout(label, layout)),
+                vertex_stage: stage(vertex),
+                fragment_stage: Some(stage(fragment)),
+                rasterization_state: Some(wgpu::RasterizationStateDescriptor::default()),
+                primitive_topology: topology,
+                color_states: &[wgpu::ColorStateDescriptor {
+                    format,
+                    color_blend: blend,
+                    // the frame's alpha is left as it is
+                    alpha_blend: wgpu::BlendDescriptor {
+                        src_factor: wgpu::BlendFactor::Zero,
+                        dst_factor: wgpu::BlendFactor::One,
+                        operation: wgpu::BlendOperation::Add,
+                    },
+                    write_mask: wgpu::ColorWrite::ALL,
+                }],
+                depth_stencil_state: None,
+                vertex_state: wgpu::VertexStateDescriptor {
+                    index_format: wgpu::IndexFormat::Uint16,
+                    vertex_buffers: buffers,
+                },
+                sample_count: 1,
+                sample_mask: !0,
+                alpha_to_coverage_enabled: false,
+            })
+        };
+        let blend = |dst_factor| wgpu::BlendDescriptor {
+            src_factor: wgpu::BlendFactor::One,
+            dst_factor,
+            operation: wgpu::BlendOperation::Add,
+        };
+        // every line is an instance, whose quad is worked out from its segment
+        let segment_layout = wgpu::VertexBufferDescriptor {
+            stride: Segment::SIZE,
+            step_mode: wgpu::InputStepMode::Instance,
+            attributes: &wgpu::vertex_attr_array![0 => Float2, 1 => Float2, 2 => Float2],
+        };
+        let beam_pipeline = pipeline(
+            "beam",
+            &beam_layout,
+            (&beam_vertex, &beam_fragment),
+            Self::FRESH_FORMAT,
+            blend(wgpu::BlendFactor::One),
+            wgpu::PrimitiveTopology::TriangleStrip,
+            &[segment_layout],
+        );
+        let decay_pipeline = device.create_compute_pipeline(&wgpu::ComputePipelineDescriptor {
+            label: Some("decay"),
+            layout: Some(&pipeline_layout("decay", &decay_layout)),
+            compute_stage: stage(&decay),
+        });
+        // the trace pass draws the frame itself, with the trace added to it
+        let trace_pipeline = pipeline(
+            "trace",
+            &trace_layout,
+            (&fullscreen, &trace_fragment),
+            Self::TARGET_FORMAT,
+            blend(wgpu::BlendFactor::Zero),
+            wgpu::PrimitiveTopology::TriangleList,
+            &[],
+        );
+
+        let mut renderer = Renderer {
+            width,
+            height,
+            light,
+            fresh,
+            ramps,
+            trace,
+            segments,
+            capacity,
+            light_uniform,
+            placement_uniform,
+            beam_pipeline,
+            decay_pipeline,
+            trace_pipeline,
+            beam_bind_group,
+            decay_bind_group,
+            trace_layout,
+            shares,
+            dark_trace: false,
+        };
+        renderer.set_palette(queue, palette);
+        renderer
+    }
+
+    /// Creates a vertex buffer for the given number of segments.
+    fn segment_buffer(device: &wgpu::Device, capacity: u64) -> wgpu::Buffer {
+        device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("segments"),
+            size: capacity * Segment::SIZE,
+            usage: wgpu::BufferUsage::VERTEX | wgpu::BufferUsage::COPY_DST,
+            mapped_at_creation: false,
+        })
+    }
+
+    /// Works out the uniforms of the beam: the size of the canvas, how far the beam reaches off
+    /// the line, and the factors of its profile. It's the same Gaussian as on the CPU, without
+    /// being cut up into pixels. A beam no wider than a pixel is spread out over about a pixel,
+    /// with as much light in all as the pixel it lights up on the CPU.
+    fn beam_shape((width, height): (u32, u32), beam_width: f32) -> [f32; 8] {
+        let (sigma, peak) = match beam_width > 1.0 {
+            true => (beam_width / (8.0 * 2f32.ln()).sqrt(), 1.0),
+            // a narrower profile than this would light up pixels unevenly along the line
+            false => (0.5, 1.0 / (std::f32::consts::TAU * 0.5 * 0.5)),
+        };
+        // the shader sums the profile up along the line with the error function, which takes
+        // distances scaled by this spread
+        let spread = 1.0 / (sigma * 2f32.sqrt());
+        let scale = peak * sigma * std::f32::consts::FRAC_PI_2.sqrt();
+        [width as f32, height as f32, sigma * 3.0 + 1.0, spread, scale, 0.0, 0.0, 0.0]
+    }
+
+    /// Uploads the palette's ramps, for the trace to be colored with.
+    pub fn set_palette(&mut self, queue: &wgpu::Queue, palette: &Palette) {
+        let texels: Vec<u8> = palette.ramps.iter()
+            .take(2)
+            .flat_map(|ramp| ramp.iter())
+            .flat_map(|&(r, g, b)| [r, g, b, 255])
+            .collect();
+        self.write_texture(queue, &self.ramps, &texels, (256, 2), 4);
+        self.dark_trace = palette.dark_trace;
+    }
+
+    /// Uploads the light of a plot, for the GPU to go on from it.
+    pub fn upload(&self, queue: &wgpu::Queue, plot: &[f32]) {
+        // the layers are interleaved, a channel of the texture each
+        let (first, second) = plot.split_at(plot.len() / 2);
+        let texels: Vec<f32> = first.iter()
+            .zip(second)
+            .flat_map(|(&first, &second)| [first, second, 0.0, 0.0])
+            .collect();
+        let size = (self.width, self.height);
+        self.write_texture(queue, &self.light, &bytes(&texels), size, 16);
+    }
+
+    /// Uploads a whole texture of the given size, whose texels are `texel` bytes each.
+    fn write_texture(
+        &self,
+        queue: &wgpu::Queue,
+        texture: &wgpu::Texture,
+        data: &[u8],
+        size: (u32, u32),
+        texel: u32,
+    ) {
+        queue.write_texture(
+            wgpu::TextureCopyView { texture, mip_level: 0, origin: wgpu::Origin3d::ZERO },
+            data,
+            wgpu::TextureDataLayout { offset: 0, bytes_per_row: size.0 * texel, rows_per_image: size.1 },
+            wgpu::Extent3d { width: size.0, height: size.1, depth: 1 },
+        );
+    }
+
+    /// Reads the light back into a plot, for the CPU to go on from it. This waits for the GPU to
+    /// finish drawing.
+    pub fn download(
+        &self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        plot: &mut [f32],
+    ) -> anyhow::Result<()> {
+        let size = (self.width, self.height);
+        let data = Self::read_texture(device, queue, &self.light, size, 16)?;
+        let frame_size = (self.width * self.height) as usize;
+        let (first, second) = plot.split_at_mut(frame_size);
+        for (i, texel) in data.chunks_exact(16).enumerate() {
+            first[i] = f32::from_ne_bytes([texel[0], texel[1], texel[2], texel[3]]);
+            second[i] = f32::from_ne_bytes([texel[4], texel[5], texel[6], texel[7]]);
+        }
+        Ok(())
+    }
+
+    /// Reads a whole texture of the given size back, whose texels are `texel` bytes each, a row
+    /// after the other. This waits for the GPU to finish drawing.
+    fn read_texture(
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        texture: &wgpu::Texture,
+        (width, height): (u32, u32),
+        texel: u32,
+    ) -> anyhow::Result<Vec<u8>> {
+        // rows copied into a buffer are padded out to a multiple of 256 bytes
+        let row_size = (width * texel).next_multiple_of(wgpu::COPY_BYTES_PER_ROW_ALIGNMENT);
+        let buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("readback"),
+            size: (row_size * height) as u64,
+            usage: wgpu::BufferUsage::MAP_READ | wgpu::BufferUsage::COPY_DST,
+            mapped_at_creation: false,
+        });
+        let descriptor = wgpu::CommandEncoderDescriptor { label: Some("readback") };
+        let mut encoder = device.create_command_encoder(&descriptor);
+        let layout = wgpu::TextureDataLayout { offset: 0, bytes_per_row: row_size, rows_per_image: height };
+        encoder.copy_texture_to_buffer(
+            wgpu::TextureCopyView { texture, mip_level: 0, origin: wgpu::Origin3d::ZERO },
+            wgpu::BufferCopyView { buffer: &buffer, layout },
+            wgpu::Extent3d { width, height, depth: 1 },
+        );
+        queue.submit(Some(encoder.finish()));
+        let slice = buffer.slice(..);
+        let mapping = slice.map_async(wgpu::MapMode::Read);
+        // the mapping is done as soon as the GPU is, which polling the device waits for
+        device.poll(wgpu::Maintain::Wait);
+        match pin!(mapping).poll(&mut Context::from_waker(Waker::noop())) {
+            Poll::Ready(Ok(())) => (),
+            Poll::Ready(Err(_)) | Poll::Pending => bail!("cannot read a texture back from the GPU"),
+        }
+        let data: Vec<u8> = slice.get_mapped_range()
+            .chunks_exact(row_size as usize)
+            .flat_map(|row| &row[..(width * texel) as usize])
+            .copied()
+            .collect();
+        buffer.unmap();
+        Ok(data)
+    }
+
+    /// Draws the trace of a frame over the frame the CPU has drawn, which is in the context's
+    /// texture. `placement` is where the canvas is shown in the window: its left and top edges,
+    /// and its width and height.
+    pub fn render(
+        &mut self,
+        encoder: &mut wgpu::CommandEncoder,
+        target: &wgpu::TextureView,
+        context: &PixelsContext,
+        frame: &mut Frame,
+        (left, top, width, height): (u32, u32, u32, u32),
+    ) {
+        self.draw(&context.device, &context.queue, encoder, frame);
+        // the frame the CPU has drawn, and the trace over it
+        context.scaling_renderer.render(encoder, target);
+        let frame_view = context.texture.create_view(&wgpu::TextureViewDescriptor::default());
+        let gpu = (&context.device, &context.queue);
+        self.composite(gpu, encoder, target, &frame_view, &frame.clip, (left, top, width, height));
+    }
+
+    /// Adds the trace to the frame the CPU has drawn, whose pixels are the window's, cut off at
+    /// the clip mapped into the window.
+    fn composite(
+        &self,
+        (device, queue): (&wgpu::Device, &wgpu::Queue),
+        encoder: &mut wgpu::CommandEncoder,
+        target: &wgpu::TextureView,
+        frame: &wgpu::TextureView,
+        (xs, ys): &(Range<u32>, Range<u32>),
+        (left, top, width, height): (u32, u32, u32, u32),
+    ) {
+        let placement = [
+            left as f32,
+            top as f32,
+            self.width as f32 / width as f32,
+            self.height as f32 / height as f32,
+            self.dark_trace as u8 as f32,
+        ];
+        queue.write_buffer(&self.placement_uniform, 0, &bytes(&placement));
+        let entries = [
+            wgpu::BindGroupEntry { binding: 0, resource: wgpu::BindingResource::TextureView(&self.trace) },
+            wgpu::BindGroupEntry {
+                binding: 1,
+                resource: wgpu::BindingResource::Buffer(self.placement_uniform.slice(..)),
+            },
+            wgpu::BindGroupEntry { binding: 2, resource: wgpu::BindingResource::TextureView(frame) },
+        ];
+        let layout = &self.trace_layout;
+        let descriptor = wgpu::BindGroupDescriptor { label: Some("trace"), layout, entries: &entries };
+        let bind_group = device.create_bind_group(&descriptor);
+
+        let x = |column: u32| left + (column as u64 * width as u64 / self.width as u64) as u32;
+        let y = |row: u32| top + (row as u64 * height as u64 / self.height as u64) as u32;
+        let (clip_left, clip_top) = (x(xs.start), y(ys.start));
+        let (clip_width, clip_height) = (x(xs.end) - clip_left, y(ys.end) - clip_top);
+        if clip_width == 0 || clip_height == 0 {
+            return
+        }
+        let mut pass = begin_pass(encoder, target, wgpu::LoadOp::Load);
+        pass.set_scissor_rect(clip_left, clip_top, clip_width, clip_height);
+        pass.set_pipeline(&self.trace_pipeline);
+        pass.set_bind_group(0, &bind_group, &[]);
+        pass.draw(0..3, 0..1);
+    }
+
+    /// Adds the lines of a frame to the light, colors the light into the trace, and fades it out
+    /// for the next frame. This is all of drawing a frame that doesn't need a window.
+    fn draw(
+        &mut self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        encoder: &mut wgpu::CommandEncoder,
+        frame: &mut Frame,
+    ) {
+        let count = frame.segments.len() as u64;
+        if count > self.capacity {
+            self.capacity = count.next_power_of_two();
+            self.segments = Self::segment_buffer(device, self.capacity);
+        }
+        let values: Vec<f32> = frame.segments.drain(..)
+            .flat_map(|segment| {
+                let Segment { origin, axis, length, intensity } = segment;
+                [origin.0, origin.1, axis.0, axis.1, length, intensity]
+            })
+            .collect();
+        if count > 0 {
+            queue.write_buffer(&self.segments, 0, &bytes(&values));
+        }
+        let (full, range) = Plot::LOG_RANGE;
+        let light = [
+            frame.decay[0],
+            frame.decay[1],
+            self.shares[0],
+            self.shares[1],
+            1.0 / frame.exposure,
+            range / (frame.exposure * full),
+            1.0 / range.ln_1p(),
+            if frame.transfer == Transfer::Log { 1.0 } else { 0.0 },
+            1.0 / frame.gamma,
+        ];
+        // right after the size of the canvas
+        queue.write_buffer(&self.light_uniform, 8, &bytes(&light));
+
+        // the fresh light, which starts out dark every frame
+        {
+            let mut pass = begin_pass(encoder, &self.fresh, wgpu::LoadOp::Clear(wgpu::Color::TRANSPARENT));
+            if count > 0 {
+                pass.set_pipeline(&self.beam_pipeline);
+                pass.set_bind_group(0, &self.beam_bind_group, &[]);
+                pass.set_vertex_buffer(0, self.segments.slice(..count * Segment::SIZE));
+                pass.draw(0..4, 0..count as u32);
+            }
+        }
+
+        // the fresh light is added to the light, which is colored for the trace and faded out
+        {
+            let mut pass = encoder.begin_compute_pass();
+            pass.set_pipeline(&self.decay_pipeline);
+            pass.set_bind_group(0, &self.decay_bind_group, &[]);
+            let groups = |size: u32| size.div_ceil(Self::WORK_GROUP_SIZE);
+            pass.dispatch(groups(self.width), groups(self.height), 1);
+        }
+    }
+}
+
+/// Begins a render pass drawing into a single texture, which is kept afterwards.
+fn begin_pass<'a>(
+    encoder: &'a mut wgpu::CommandEncoder,
+    attachment: &'a wgpu::TextureView,
+    load: wgpu::LoadOp<wgpu::Color>,
+) -> wgpu::RenderPass<'a> {
+    encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+        color_attachments: &[wgpu::RenderPassColorAttachmentDescriptor {
+            attachment,
+            resolve_target: None,
+            ops: wgpu::Operations { load, store: true },
+        }],
+        depth_stencil_attachment: None,
+    })
+}
+
+/// Lays out floats the way the GPU reads them.
+fn bytes(values: &[f32]) -> Vec<u8> {
+    values.iter().flat_map(|value| value.to_ne_bytes()).collect()
+}
+
+#[cfg(all(test, feature = "gpu-tests"))]
+mod tests {
+    use super::*;
+    use crate::{
+        cli::{AmplitudeScale, Graticule, Theme},
+        phosphor::Layer,
+        Beam,
+        Exposure,
+        GraticuleSettings,
+        View,
+    };
+
+    /// Waits for one of wgpu's futures, which are done as soon as the device has been polled.
+    fn block_on<T>(future: impl Future<Output = T>) -> T {
+        let mut future = pin!(future);
+        loop {
+            match future.as_mut().poll(&mut Context::from_waker(Waker::noop())) {
+                Poll::Ready(value) => return value,
+                Poll::Pending => std::thread::yield_now(),
+            }
+        }
+    }
+
+    /// Opens the first GPU that's available, without a window to draw in.
+    fn device() -> (wgpu::Device, wgpu::Queue) {
+        let instance = wgpu::Instance::new(wgpu::BackendBit::PRIMARY);
+        let adapter = block_on(instance.request_adapter(&wgpu::RequestAdapterOptions::default()))
+            .expect("there is no GPU to run the tests on");
+        let descriptor = wgpu::DeviceDescriptor {
+            features: wgpu::Features::empty(),
+            limits: wgpu::Limits::default(),
+            shader_validation: true,
+        };
+        block_on(adapter.request_device(&descriptor, None)).unwrap()
+    }
+
+    /// Creates a renderer for a canvas of the given size, with a thin beam and a phosphor that
+    /// puts 70% of the beam's light into its first layer.
+    fn renderer(device: &wgpu::Device, queue: &wgpu::Queue, size: (u32, u32)) -> Renderer {
+        let layers = [0.7, 0.3].map(|share| Layer { share, persistence: 1.0, color: (0.0, 1.0, 0.0) });
+        let palette = Palette::new(Theme::Green, &layers, None);
+        Renderer::new(device, queue, size, 1.0, [0.7, 0.3], &palette)
+    }
+
+    #[test]
+    fn light_round_trip() {
+        let (device, queue) = device();
+        // a canvas that isn't a multiple of the work group's size
+        let (width, height) = (37, 21);
+        let renderer = renderer(&device, &queue, (width, height));
+        let plot: Vec<f32> = (0..width * height * 2).map(|i| i as f32 * 0.25).collect();
+        renderer.upload(&queue, &plot);
+        let mut read = vec![0.0; plot.len()];
+        renderer.download(&device, &queue, &mut read).unwrap();
+        assert!(read == plot);
+    }
+
+    #[test]
+    fn beam() {
+        let (device, queue) = device();
+        let (width, height) = (64, 48);
+        let mut renderer = renderer(&device, &queue, (width, height));
+        let mut frame = Frame {
+            decay: [1.0; 2],
+            clip: (0..width, 0..height),
+            exposure: 1.0,
+            gamma: 1.0,
+            ..Frame::default()
+        };
+        frame.line((10.0, 20.0), (50.0, 30.0), 0.5);
+        let mut encoder = device.create_command_encoder(&wgpu::CommandEncoderDescriptor { label: None });
+        renderer.draw(&device, &queue, &mut encoder, &mut frame);
+        queue.submit(Some(encoder.finish()));
+        let mut plot = vec![0.0; (width * height * 2) as usize];
+        renderer.download(&device, &queue, &mut plot).unwrap();
+
+        // all the light the beam puts down along the line is there, shared out between the layers
+        let (first, second) = plot.split_at(plot.len() / 2);
+        let light = 0.5 * 40f32.hypot(10.0);
+        for (layer, share) in [(first, 0.7), (second, 0.3)] {
+            let total: f32 = layer.iter().sum();
+            assert!((total / (light * share) - 1.0).abs() < 0.02, "{} instead of {}", total, light * share);
+        }
+        // and it's on the line, not off it
+        let at = |x: u32, y: u32| first[(y * width + x) as usize];
+        assert!(at(30, 25) > 0.1);
+        assert_eq!(at(30, 35), 0.0);
+        assert_eq!(at(5, 20), 0.0);
+    }
+
+    /// Finishes a frame of a plot whose canvas has the given size, with nothing but the graticule
+    /// and the light it already has, on the CPU or, with `gpu` set, leaving the trace to the GPU.
+    fn done(
+        (width, height): (u32, u32),
+        pixels: &mut [u8],
+        plot: &mut [f32],
+        gpu: Option<&mut Frame>,
+        (transfer, gamma): (Transfer, f32),
+        layers: &[Layer],
+        palette: &Palette,
+    ) {
+        let frame_size = (width * height) as usize;
+        Plot {
+            width,
+            height,
+            pixels,
+            plot,
+            tint: &mut vec![0.0; frame_size * 4],
+            gpu,
+            reference: None,
+            density: &mut vec![0.0; frame_size],
+            density_decay: 1.0,
+            colormap: None,
+            color: None,
+            beam: &Beam::new(1.0),
+            layers,
+            palette,
+            image: None,
+            polar: false,
+            speakers: Vec::new(),
+            pane: None,
+            panes: Vec::new(),
+            overlay: None,
+            no_input: false,
+            data_lost: false,
+            frozen: false,
+            decay: 0.5,
+            exposure: Exposure::Fixed,
+            amplitude_scale: AmplitudeScale::Linear,
+            db_range: 60.0,
+            graticule: GraticuleSettings {
+                style: Graticule::Grid,
+                divisions: 4,
+                color: None,
+                opacity: 1.0,
+                emphasize_center: true,
+                labels: false,
+            },
+            view: View::UNIT,
+            square: false,
+            composite: false,
+            supersampling: 1,
+            transfer,
+            gamma,
+            indicator: None,
+            bloom: None,
+            hud: None,
+            crosshair: false,
+            cursor: None,
+            envelope: Vec::new(),
+            correlation: None,
+            stereo_width: None,
+            levels: None,
+            balance: None,
+            mid_side: None,
+            markers: [None; 2],
+            measurement: None,
+        }.done();
+    }
+
+    #[test]
+    fn same_as_cpu() {
+        let (device, queue) = device();
+        let (width, height) = (16, 12);
+        let frame_size = (width * height) as usize;
+        // the layers fade out at different paces
+        let layers = [(0.7, 1.0), (0.3, 0.5)].map(|(share, persistence)| {
+            Layer { share, persistence, color: (0.0, 1.0, 0.0) }
+        });
+        // light all the way from too little to be seen to burning out, in both layers, with the
+        // trace lighting up the background or darkening it
+        let light: Vec<f32> = (0..frame_size * 2)
+            .map(|i| Plot::MIN_INTENSITY * 2f32.powf((i * 7 % (frame_size * 2)) as f32 * 0.06 - 2.0))
+            .collect();
+        let settings = [
+            (Theme::Dracula, Transfer::Exponential, 1.0),
+            (Theme::Light, Transfer::Log, 2.2),
+        ];
+        for (theme, transfer, gamma) in settings {
+            let palette = Palette::new(theme, &layers, None);
+            let mut cpu_pixels = vec![0; frame_size * 4];
+            let mut cpu_plot = light.clone();
+            done((width, height), &mut cpu_pixels, &mut cpu_plot, None, (transfer, gamma), &layers, &palette);
+
+            // the same frame, with everything but the trace drawn on the CPU
+            let mut renderer = Renderer::new(&device, &queue, (width, height), 1.0, [0.7, 0.3], &palette);
+            renderer.upload(&queue, &light);
+            let mut background = vec![0; frame_size * 4];
+            let mut frame = Frame::default();
+            let mut plot = light.clone();
+            let gpu = Some(&mut frame);
+            done((width, height), &mut background, &mut plot, gpu, (transfer, gamma), &layers, &palette);
+            assert!(plot == light);
+
+            // which is handed over in a texture like the one the frame is shown from
+            let texture = |format, usage| device.create_texture(&wgpu::TextureDescriptor {
+                label: None,
+                size: wgpu::Extent3d { width, height, depth: 1 },
+                mip_level_count: 1,
+                sample_count: 1,
+                dimension: wgpu::TextureDimension::D2,
+                format,
+                usage,
+            });
+            let usage = wgpu::TextureUsage::SAMPLED | wgpu::TextureUsage::COPY_DST;
+            let frame_texture = texture(wgpu::TextureFormat::Rgba8UnormSrgb, usage);
+            renderer.write_texture(&queue, &frame_texture, &background, (width, height), 4);
+            let usage = wgpu::TextureUsage::OUTPUT_ATTACHMENT | wgpu::TextureUsage::COPY_SRC;
+            let target = texture(Renderer::TARGET_FORMAT, usage);
+            let view = |texture: &wgpu::Texture| texture.create_view(&wgpu::TextureViewDescriptor::default());
+            let mut encoder = device.create_command_encoder(&wgpu::CommandEncoderDescriptor { label: None });
+            renderer.draw(&device, &queue, &mut encoder, &mut frame);
+            let (clip, placement) = (frame.clip.clone(), (0, 0, width, height));
+            let gpu = (&device, &queue);
+            renderer.composite(gpu, &mut encoder, &view(&target), &view(&frame_texture), &clip, placement);
+            queue.submit(Some(encoder.finish()));
+
+            // the GPU's exp, log and pow are less exact than the CPU's, which can move a pixel a
+            // step along its ramp
+            let pixels = Renderer::read_texture(&device, &queue, &target, (width, height), 4).unwrap();
+            for (gpu, cpu) in pixels.chunks_exact(4).zip(cpu_pixels.chunks_exact(4)) {
+                let gpu = [gpu[2], gpu[1], gpu[0], gpu[3]];
+                let close = gpu.iter().zip(cpu).all(|(&gpu, &cpu)| gpu.abs_diff(cpu) <= 4);
+                assert!(close, "{:?} instead of {:?}", gpu, cpu);
+            }
+            let mut gpu_plot = vec![0.0; frame_size * 2];
+            renderer.download(&device, &queue, &mut gpu_plot).unwrap();
+            for (gpu, cpu) in gpu_plot.iter().zip(&cpu_plot) {
+                assert!((gpu - cpu).abs() <= cpu * 1e-6, "{} instead of {}", gpu, cpu);
+            }
+        }
+    }
+}
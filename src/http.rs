@@ -0,0 +1,69 @@
+use std::{
+    io::{BufRead, BufReader, Write},
+    net::TcpStream,
+};
+
+use anyhow::{anyhow, bail, Context};
+
+/// A response to an HTTP request, whose body is read as a stream.
+pub struct Response {
+    /// The media type of the body, with its parameters (eg. `audio/L16;rate=44100`).
+    pub content_type: String,
+    pub body: BufReader<TcpStream>,
+}
+
+/// How many redirects are followed before giving up.
+const MAX_REDIRECTS: usize = 5;
+
+/// Sends a GET request to a plain HTTP URL, following redirects. Icecast and SHOUTcast servers
+/// answering with `ICY 200 OK` are understood, too.
+pub fn get(url: &str) -> anyhow::Result<Response> {
+    let mut url = url.to_owned();
+    for _ in 0..=MAX_REDIRECTS {
+        let (host, path) = url.strip_prefix("http://")
+            .map(|rest| rest.split_at(rest.find('/').unwrap_or(rest.len())))
+            .ok_or_else(|| anyhow!("only http:// URLs are supported: {}", url))?;
+        let path = if path.is_empty() { "/" } else { path };
+        let address = if host.contains(':') { host.to_owned() } else { format!("{}:80", host) };
+        let mut stream = TcpStream::connect(&address)
+            .with_context(|| format!("cannot connect to {}", address))?;
+        // HTTP/1.0 keeps the server from sending the body in chunks
+        write!(
+            stream,
+            "GET {} HTTP/1.0\r\nHost: {}\r\nUser-Agent: vectorscope\r\nIcy-MetaData: 0\r\n\r\n",
+            path,
+            host,
+        )?;
+
+        let mut body = BufReader::new(stream);
+        let mut status_line = String::new();
+        body.read_line(&mut status_line)?;
+        let status: u16 = status_line.split_whitespace()
+            .nth(1)
+            .and_then(|status| status.parse().ok())
+            .ok_or_else(|| anyhow!("invalid response from {}: {}", host, status_line.trim()))?;
+        let mut content_type = String::new();
+        let mut location = None;
+        loop {
+            let mut line = String::new();
+            if body.read_line(&mut line)? == 0 || line.trim().is_empty() {
+                break
+            }
+            if let Some((name, value)) = line.split_once(':') {
+                match name.trim().to_ascii_lowercase().as_str() {
+                    "content-type" => content_type = value.trim().to_owned(),
+                    "location" => location = Some(value.trim().to_owned()),
+                    _ => (),
+                }
+            }
+        }
+        match status {
+            200..=299 => return Ok(Response { content_type, body }),
+            300..=399 => {
+                url = location.ok_or_else(|| anyhow!("{} redirected without a location", url))?;
+            },
+            _ => bail!("{} responded with {}", url, status_line.trim()),
+        }
+    }
+    bail!("too many redirects")
+}
@@ -0,0 +1,256 @@
+use std::collections::VecDeque;
+
+/// Loudness as measured by EBU R128: momentary over the last 400 ms, short-term over the last 3
+/// seconds, and integrated over everything since the measurement started, leaving out silence
+/// and quiet parts.
+pub struct Loudness {
+    sample_rate: u32,
+    /// The K-weighting filters of both channels, which weigh frequencies roughly like the ear
+    /// does: a shelf that boosts the treble, followed by a high-pass filter.
+    filters: [[Biquad; 2]; 2],
+    /// How many frames make up a step, after which the blocks move on.
+    step_length: usize,
+    /// The sum of the filtered channels' squares over the step being filled, and how many frames
+    /// went into it.
+    step: (f64, usize),
+    /// The mean squares of the latest steps, newest last, enough for the short-term loudness.
+    steps: VecDeque<f64>,
+    /// The mean squares of the 400 ms blocks that have passed the absolute gate, for the
+    /// integrated loudness.
+    blocks: Vec<f64>,
+    /// The number of frames the input produced before the last one that's been measured.
+    end: u64,
+}
+
+impl Loudness {
+    /// How long a step is, which is how far apart the overlapping blocks start.
+    const STEP: f64 = 0.1;
+    /// How many steps a momentary and a short-term block are made of.
+    const MOMENTARY_STEPS: usize = 4;
+    const SHORT_TERM_STEPS: usize = 30;
+    /// Blocks quieter than this are silence, which doesn't count towards the integrated loudness.
+    const ABSOLUTE_GATE: f64 = -70.0;
+    /// How far below the loudness of the blocks that pass the absolute gate a block has to be to
+    /// not count towards the integrated loudness either.
+    const RELATIVE_GATE: f64 = -10.0;
+
+    pub fn new(sample_rate: u32) -> Self {
+        let filter = [Biquad::shelf(sample_rate), Biquad::high_pass(sample_rate)];
+        Loudness {
+            sample_rate,
+            filters: [filter; 2],
+            step_length: ((sample_rate as f64 * Self::STEP) as usize).max(1),
+            step: (0.0, 0),
+            steps: VecDeque::new(),
+            blocks: Vec::new(),
+            end: 0,
+        }
+    }
+
+    pub fn sample_rate(&self) -> u32 {
+        self.sample_rate
+    }
+
+    /// Measures the frames being displayed, leaving out the ones it's already had. `start` is
+    /// the number of frames the input produced before the first of them.
+    pub fn push(&mut self, frames: &[(f32, f32, f32)], start: u64) {
+        let end = start + frames.len() as u64;
+        let new = match start > self.end || end < self.end {
+            true => frames,
+            false => &frames[(self.end - start) as usize..],
+        };
+        self.end = end;
+        for &(x, y, _) in new {
+            for (filters, sample) in self.filters.iter_mut().zip([x, y]) {
+                let filtered = filters.iter_mut()
+                    .fold(sample as f64, |sample, filter| filter.process(sample));
+                self.step.0 += filtered * filtered;
+            }
+            self.step.1 += 1;
+            if self.step.1 == self.step_length {
+                self.finish_step();
+            }
+        }
+    }
+
+    /// Moves the blocks on by a step, and gates the momentary block that just ended for the
+    /// integrated loudness.
+    fn finish_step(&mut self) {
+        self.steps.push_back(self.step.0 / self.step.1 as f64);
+        self.step = (0.0, 0);
+        if self.steps.len() > Self::SHORT_TERM_STEPS {
+            self.steps.pop_front();
+        }
+        if let Some(block) = self.mean(Self::MOMENTARY_STEPS) {
+            if loudness(block) > Self::ABSOLUTE_GATE {
+                self.blocks.push(block);
+            }
+        }
+    }
+
+    /// Returns the mean square over the given number of the latest steps, or `None` if there
+    /// haven't been that many yet.
+    fn mean(&self, steps: usize) -> Option<f64> {
+        let start = self.steps.len().checked_sub(steps)?;
+        Some(self.steps.range(start..).sum::<f64>() / steps as f64)
+    }
+
+    /// Returns the momentary loudness in LUFS, or `None` if there hasn't been enough input yet.
+    pub fn momentary(&self) -> Option<f32> {
+        self.mean(Self::MOMENTARY_STEPS).map(|mean| loudness(mean) as f32)
+    }
+
+    /// Returns the short-term loudness in LUFS, or `None` if there hasn't been enough input yet.
+    pub fn short_term(&self) -> Option<f32> {
+        self.mean(Self::SHORT_TERM_STEPS).map(|mean| loudness(mean) as f32)
+    }
+
+    /// Returns the integrated loudness in LUFS, or `None` if nothing louder than silence has
+    /// been measured yet.
+    pub fn integrated(&self) -> Option<f32> {
+        if self.blocks.is_empty() {
+            return None
+        }
+        let mean = self.blocks.iter().sum::<f64>() / self.blocks.len() as f64;
+        let gate = loudness(mean) + Self::RELATIVE_GATE;
+        let (sum, count) = self.blocks.iter()
+            .filter(|&&block| loudness(block) > gate)
+            .fold((0.0, 0), |(sum, count), &block| (sum + block, count + 1));
+        (count > 0).then(|| loudness(sum / count as f64) as f32)
+    }
+}
+
+/// Converts the sum of the channels' mean squares into loudness in LUFS.
+fn loudness(mean_square: f64) -> f64 {
+    -0.691 + 10.0 * mean_square.max(1e-20).log10()
+}
+
+/// A second order IIR filter.
+#[derive(Clone, Copy)]
+struct Biquad {
+    b: [f64; 3],
+    a: [f64; 2],
+    /// The last two inputs and outputs.
+    inputs: [f64; 2],
+    outputs: [f64; 2],
+}
+
+impl Biquad {
+    /// The first stage of K-weighting, which models the head's effect on sound: a shelf that
+    /// boosts frequencies above about 1.5 kHz by 4 dB. The coefficients are worked out for any
+    /// sample rate from the analog prototype behind the ones ITU-R BS.1770 gives for 48 kHz.
+    fn shelf(sample_rate: u32) -> Self {
+        const FREQUENCY: f64 = 1681.974450955533;
+        const GAIN: f64 = 3.999843853973347;
+        const Q: f64 = 0.7071752369554196;
+        let k = (std::f64::consts::PI * FREQUENCY / sample_rate as f64).tan();
+        let vh = 10f64.powf(GAIN / 20.0);
+        let vb = vh.powf(0.4996667741545416);
+        let a0 = 1.0 + k / Q + k * k;
+        Biquad::new(
+            [(vh + vb * k / Q + k * k) / a0, 2.0 * (k * k - vh) / a0, (vh - vb * k / Q + k * k) / a0],
+            [2.0 * (k * k - 1.0) / a0, (1.0 - k / Q + k * k) / a0],
+        )
+    }
+
+    /// The second stage of K-weighting, a high-pass filter that leaves out the lowest bass.
+    fn high_pass(sample_rate: u32) -> Self {
+        const FREQUENCY: f64 = 38.13547087602444;
+        const Q: f64 = 0.5003270373238773;
+        let k = (std::f64::consts::PI * FREQUENCY / sample_rate as f64).tan();
+        let a0 = 1.0 + k / Q + k * k;
+        Biquad::new([1.0, -2.0, 1.0], [2.0 * (k * k - 1.0) / a0, (1.0 - k / Q + k * k) / a0])
+    }
+
+    fn new(b: [f64; 3], a: [f64; 2]) -> Self {
+        Biquad { b, a, inputs: [0.0; 2], outputs: [0.0; 2] }
+    }
+
+    fn process(&mut self, input: f64) -> f64 {
+        let output = self.b[0] * input + self.b[1] * self.inputs[0] + self.b[2] * self.inputs[1]
+            - self.a[0] * self.outputs[0] - self.a[1] * self.outputs[1];
+        self.inputs = [input, self.inputs[0]];
+        self.outputs = [output, self.outputs[0]];
+        output
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Loudness;
+
+    /// Measures five seconds of a full-scale 997 Hz sine on the given channels, pushed a frame's
+    /// worth of samples at a time.
+    fn sine(sample_rate: u32, channels: (f32, f32)) -> Loudness {
+        let mut loudness = Loudness::new(sample_rate);
+        let frames: Vec<_> = (0..sample_rate as u64 * 5)
+            .map(|i| {
+                let sample = (std::f64::consts::TAU * 997.0 * i as f64 / sample_rate as f64).sin() as f32;
+                (sample * channels.0, sample * channels.1, 0.0)
+            })
+            .collect();
+        for (i, chunk) in frames.chunks(800).enumerate() {
+            loudness.push(chunk, i as u64 * 800);
+        }
+        loudness
+    }
+
+    fn assert_reads(loudness: &Loudness, expected: f32) {
+        for (name, measured) in [
+            ("momentary", loudness.momentary()),
+            ("short-term", loudness.short_term()),
+            ("integrated", loudness.integrated()),
+        ] {
+            let measured = measured.unwrap_or_else(|| panic!("no {} loudness", name));
+            assert!((measured - expected).abs() < 0.05, "{} loudness is {} LUFS", name, measured);
+        }
+    }
+
+    #[test]
+    fn full_scale_sine_on_one_channel() {
+        // BS.1770 is calibrated so that this reads -3.01 LUFS
+        assert_reads(&sine(48000, (1.0, 0.0)), -3.01);
+        assert_reads(&sine(48000, (0.0, 1.0)), -3.01);
+    }
+
+    #[test]
+    fn full_scale_sine_on_both_channels() {
+        // twice the power of one channel
+        assert_reads(&sine(48000, (1.0, 1.0)), 0.0);
+    }
+
+    #[test]
+    fn other_sample_rates() {
+        assert_reads(&sine(44100, (1.0, 0.0)), -3.01);
+        assert_reads(&sine(96000, (1.0, 0.0)), -3.01);
+    }
+
+    #[test]
+    fn silence_is_gated() {
+        let mut loudness = Loudness::new(48000);
+        loudness.push(&vec![(0.0, 0.0, 0.0); 48000 * 5], 0);
+        assert!(loudness.momentary().unwrap() < -70.0);
+        assert_eq!(loudness.integrated(), None);
+    }
+
+    #[test]
+    fn nothing_is_measured_before_a_block_is_full() {
+        let mut loudness = Loudness::new(48000);
+        loudness.push(&vec![(0.5, 0.5, 0.0); 48000 * 3 / 10], 0);
+        assert_eq!(loudness.momentary(), None);
+        assert_eq!(loudness.short_term(), None);
+        assert_eq!(loudness.integrated(), None);
+    }
+
+    #[test]
+    fn frames_already_measured_are_left_out() {
+        let mut once = Loudness::new(48000);
+        let mut twice = Loudness::new(48000);
+        let frames: Vec<_> = (0..48000).map(|i| ((i % 100) as f32 / 100.0, 0.0, 0.0)).collect();
+        once.push(&frames, 0);
+        // the displayed frames overlap from one redraw to the next
+        twice.push(&frames[..30000], 0);
+        twice.push(&frames[10000..], 10000);
+        assert_eq!(once.momentary(), twice.momentary());
+    }
+}
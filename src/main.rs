@@ -1,120 +1,383 @@
-use std::sync::{Arc, Mutex};
+mod audio;
+mod canvas;
+mod cli;
+mod crt;
+mod gpu;
+mod http;
+mod loudness;
+mod nsm;
+mod overlay;
+mod palette;
+mod phosphor;
+mod png;
+mod regex;
+mod ring;
+mod spectrogram;
+mod text;
+mod wav;
 
-use cpal::{
-    Stream, StreamConfig, SampleRate, BufferSize,
-    traits::{DeviceTrait, HostTrait, StreamTrait},
+use std::{
+    ops::Range,
+    path::{Path, PathBuf},
+    time::{Duration, Instant, SystemTime},
 };
+
+use anyhow::bail;
 use pixels::{Pixels, SurfaceTexture};
 use winit::{
-    dpi::LogicalSize,
-    event::{Event, WindowEvent},
+    dpi::{LogicalSize, PhysicalSize},
+    event::{
+        ElementState, Event, KeyboardInput, ModifiersState, MouseButton, MouseScrollDelta, VirtualKeyCode,
+        WindowEvent,
+    },
     event_loop::{ControlFlow, EventLoop},
-    window::{WindowBuilder},
+    window::{Window, WindowBuilder},
 };
 
-struct AudioState {
-    stream: Stream,
-    samples_back: Arc<Mutex<Vec<f32>>>,
-    samples_front: Vec<f32>,
-    last_sample: (f32, f32),
-}
-
-impl AudioState {
-    fn new() -> anyhow::Result<Self> {
-        // find host
-        let host_id = cpal::available_hosts()
-            .into_iter()
-            .find(|id| *id == cpal::HostId::Jack)
-            .expect("could not find JACK host. make sure JACK is installed");
-        let host = cpal::host_from_id(host_id)?;
-        let input = host.default_input_device().unwrap();
-
-        let arc_samples = Arc::new(Mutex::new(Vec::new()));
-
-        let samples = arc_samples.clone();
-        let stream = input.build_input_stream(
-            &StreamConfig {
-                channels: 2,
-                sample_rate: SampleRate(48000),
-                buffer_size: BufferSize::Fixed(128),
-            },
-            move |in_samples: &[f32], _info| {
-                let mut samples = samples.lock().unwrap();
-                samples.extend(in_samples.iter());
-            },
-            |error| {
-                eprintln!("audio error: {}", error);
-            },
-        )?;
-        stream.play()?;
-
-        Ok(AudioState {
-            stream,
-            samples_back: arc_samples.clone(),
-            samples_front: Vec::new(),
-            last_sample: (0.0, 0.0),
-        })
+use crate::{
+    audio::{AudioState, Flip},
+    cli::{AmplitudeScale, Colormap, Command, DisplayMode, Graticule, Options, Theme, Transfer},
+    canvas::{Canvas, rescale},
+    crt::Crt,
+    nsm::Session,
+    overlay::Overlay,
+    palette::{Gradient, Palette},
+    phosphor::Layer,
+};
+
+struct Plot<'a, 'b, 'c> {
+    width: u32,
+    height: u32,
+    pixels: &'a mut [u8],
+    /// How much light each pixel has received, as it fades out, one whole frame for each of the
+    /// phosphor's layers. This is kept in full precision, so that dense areas don't clip and
+    /// faint ones fade out smoothly.
+    plot: &'b mut [f32],
+    /// The colors the beam has drawn every pixel in, each weighted by how much light it put in,
+    /// followed by the total weight: four values a pixel. Only used when the trace is colored
+    /// by hue.
+    tint: &'b mut [f32],
+    /// If set, the trace is drawn on the GPU: the beam's lines go into this frame instead of the
+    /// plot, which is left as it is.
+    gpu: Option<&'b mut gpu::Frame>,
+    /// A snapshot of how much light every pixel had, summed over the layers, which is drawn dimly
+    /// under the trace to compare it with.
+    reference: Option<&'b [f32]>,
+    /// How many times the beam has hit every pixel, fading out over the heatmap's window. Only
+    /// used when the heatmap is on.
+    density: &'b mut [f32],
+    /// How much of its hits every pixel keeps for the next frame in the heatmap.
+    density_decay: f32,
+    /// If set, the heatmap is drawn with these colors instead of the trace.
+    colormap: Option<&'c [(u8, u8, u8); 256]>,
+    /// The color the beam is drawing in, if the trace is colored by hue rather than with the
+    /// palette.
+    color: Option<(f32, f32, f32)>,
+    beam: &'c Beam,
+    layers: &'c [Layer],
+    palette: &'c Palette,
+    /// If set, a picture that fills the scope instead of the graticule and the trace, such as
+    /// the spectrogram.
+    image: Option<png::Image>,
+    /// Whether the scope shows the polar display, which has a graticule of its own.
+    polar: bool,
+    /// The names of a surround input's speakers and their directions in degrees clockwise from
+    /// the front, if the scope shows the surround display, which has a graticule of its own.
+    speakers: Vec<(&'static str, f32)>,
+    /// If set, the pane of the dashboard being drawn in, which the scope fills.
+    pane: Option<(Range<u32>, Range<u32>)>,
+    /// The panes of the dashboard that have been drawn in, if the window is split into panes.
+    panes: Vec<Pane>,
+    /// If set, an image drawn over the graticule.
+    overlay: Option<&'c Overlay>,
+    /// Whether the input device is gone. The graticule is drawn in red when this is set.
+    no_input: bool,
+    /// Whether input data was lost recently. A warning badge is drawn in the corner when this is
+    /// set, since the picture may be missing parts of the signal.
+    data_lost: bool,
+    /// Whether the picture is held still, eg. while the JACK transport is stopped. Nothing fades
+    /// out while this is set.
+    frozen: bool,
+    /// How much of its light every pixel keeps for the next frame, in a layer that fades out
+    /// as fast as the set persistence.
+    decay: f32,
+    exposure: Exposure,
+    /// How amplitudes map to distances from the center, both for the trace and the graticule.
+    amplitude_scale: AmplitudeScale,
+    /// How many decibels below full scale the center is on the decibel scale.
+    db_range: f32,
+    graticule: GraticuleSettings,
+    /// The part of the picture that's shown.
+    view: View,
+    /// Whether the scope is kept square in the middle of the window, with bars on either side,
+    /// so that circles stay round.
+    square: bool,
+    /// Whether the scope is laid out like on a hardware goniometer, made smaller to make room
+    /// for the level meters on either side of it and the correlation meter below it.
+    composite: bool,
+    /// How many pixels of the frame go into a pixel on screen along each axis. Lines and margins
+    /// are made this many times bigger, so that they look the same as without supersampling.
+    supersampling: u32,
+    transfer: Transfer,
+    /// The gamma the brightness is raised to the inverse of.
+    gamma: f32,
+    /// A value between 0 and 1 shown as a bar along the bottom edge, with a label above it, eg.
+    /// right after a setting has been changed.
+    indicator: Option<(f32, String)>,
+    /// If set, bright areas bleed light into their surroundings.
+    bloom: Option<Bloom>,
+    /// If set, lines of text shown in the top left corner, such as the input's name and levels.
+    hud: Option<Vec<String>>,
+    /// Whether a crosshair is drawn where the beam is.
+    crosshair: bool,
+    /// Where the beam is, and the values of the X and Y channels there, for the crosshair.
+    cursor: Option<Cursor>,
+    /// The outline of the farthest the picture has reached in every direction, in the frame, if
+    /// it's shown.
+    envelope: Vec<(f32, f32)>,
+    /// If set, the correlation between the channels, shown on a bar under the picture.
+    correlation: Option<f32>,
+    /// If set, the energy of the mid and side signals, shown on a bar under the picture, or
+    /// `None` in there if the input is silent.
+    stereo_width: Option<Option<(f32, f32)>>,
+    /// If set, the RMS and peak levels of the X and Y channels, shown on meters on either side
+    /// of the picture.
+    levels: Option<[(f32, f32); 2]>,
+    /// If set, how much louder the Y channel is than the X channel in decibels, shown on a bar
+    /// under the picture, or `None` in there if either is silent.
+    balance: Option<Option<f32>>,
+    /// If set, the traces of the mid and side signals along the edges of the scope, in the
+    /// frame.
+    mid_side: Option<[Vec<(f32, f32)>; 2]>,
+    /// The measurement markers A and B, where 1 is the edge of the unzoomed picture.
+    markers: [Option<(f32, f32)>; 2],
+    /// Where the markers are in the frame and what they measure, filled in while rendering.
+    measurement: Option<Measurement>,
+}
+
+/// A part of the window showing the channels in its own way, when it's split into panes.
+struct Pane {
+    area: (Range<u32>, Range<u32>),
+    /// If set, a picture that fills the pane instead of the graticule.
+    image: Option<png::Image>,
+    /// Whether the pane shows the polar display.
+    polar: bool,
+    /// The speakers of the surround display, if the pane shows it.
+    speakers: Vec<(&'static str, f32)>,
+}
+
+/// The measurement markers as they're drawn.
+struct Measurement {
+    /// Where markers A and B are in the frame.
+    positions: [Option<(f32, f32)>; 2],
+    /// The lines of text listing the amplitudes at the markers and the difference between them.
+    readout: Vec<String>,
+}
+
+/// Where the beam is at the end of a frame.
+struct Cursor {
+    /// The position of the beam in the frame.
+    position: (f32, f32),
+    /// The values of the X and Y channels, as they came in.
+    values: (f32, f32),
+}
+
+/// How the exposure is picked.
+#[derive(Clone, Copy)]
+enum Exposure {
+    /// The exposure is always the same.
+    Fixed,
+    /// The exposure follows the brightest pixel, so that a long exposure never burns out.
+    Peak,
+    /// The exposure follows the distribution of light over the picture, so that quiet signals
+    /// are brought up and loud ones are brought down.
+    Histogram,
+}
+
+/// The part of the picture that's shown, for looking at small details up close.
+#[derive(Clone, Copy)]
+struct View {
+    /// How many times bigger than normal the picture is shown.
+    zoom: f32,
+    /// The point shown in the center, where 1 is the edge of the unzoomed picture.
+    center: (f32, f32),
+}
+
+impl View {
+    /// The whole picture, not zoomed in.
+    const UNIT: View = View { zoom: 1.0, center: (0.0, 0.0) };
+    /// The range the zoom can be set in.
+    const ZOOM_RANGE: (f32, f32) = (0.25, 256.0);
+    /// How much one step of the mouse wheel zooms in or out.
+    const ZOOM_STEP: f32 = 1.2;
+
+    /// Maps a point of the picture to where it's shown, where 1 is the edge of the window.
+    fn apply(self, (x, y): (f32, f32)) -> (f32, f32) {
+        ((x - self.center.0) * self.zoom, (y - self.center.1) * self.zoom)
     }
 
-    fn get_xy(half_width: f32, half_height: f32, left: f32, right: f32) -> (i32, i32) {
-        let x = left * half_width + half_width;
-        let y = -right * half_height + half_height;
-        (x as i32, y as i32)
+    /// Maps a point of the window, where 1 is the edge, back to the point of the picture that's
+    /// shown there.
+    fn unapply(self, (x, y): (f32, f32)) -> (f32, f32) {
+        (x / self.zoom + self.center.0, y / self.zoom + self.center.1)
     }
 
-    fn render(&mut self, mut plot: Plot) {
-        let (half_width, half_height) = (plot.width as f32 / 2.0, plot.height as f32 / 2.0);
-        // flip buffers
-        {
-            const BUFFER_SIZE: usize = 2048;
-            let mut samples = self.samples_back.lock().unwrap();
-            if samples.len() > BUFFER_SIZE {
-                self.samples_front.clear();
-                self.samples_front.extend(samples.drain(..));
-            }
-        }
-        // plot the samples
-        for channels in self.samples_front.chunks_exact(2) {
-            if let [left, right, ..] = channels {
-                let (previous_left, previous_right) = self.last_sample;
-                let (previous_x, previous_y) = Self::get_xy(half_width, half_height, previous_left, previous_right);
-                let (x, y) = Self::get_xy(half_width, half_height, *left, *right);
-                plot.dot(previous_x, previous_y, x, y);
-                self.last_sample = (*left, *right);
-            }
+    /// Zooms in (or out, for negative steps) keeping the point of the window under the cursor
+    /// in place.
+    fn zoom_at(&mut self, cursor: (f32, f32), steps: f32) {
+        let anchor = self.unapply(cursor);
+        let (min, max) = Self::ZOOM_RANGE;
+        self.zoom = (self.zoom * Self::ZOOM_STEP.powf(steps)).clamp(min, max);
+        self.center = (anchor.0 - cursor.0 / self.zoom, anchor.1 - cursor.1 / self.zoom);
+    }
+}
+
+/// How the graticule is drawn.
+#[derive(Clone, Copy)]
+struct GraticuleSettings {
+    style: Graticule,
+    /// How many lines or circles the grids are divided into.
+    divisions: u32,
+    /// The color of the lines. If unset, the palette's color is used.
+    color: Option<(u8, u8, u8)>,
+    /// How much the lines stand out from the background, from 0 (not at all) to 1.
+    opacity: f32,
+    /// Whether the lines through the center are drawn brighter than the rest.
+    emphasize_center: bool,
+    /// Whether the lines and circles are labeled with the amplitudes they're at.
+    labels: bool,
+}
+
+/// The spot the beam lights up around every point it passes through.
+struct Beam {
+    radius: i32,
+    /// How brightly each pixel of the square around the point is lit, relative to the point
+    /// itself, row by row.
+    weights: Vec<f32>,
+}
+
+impl Beam {
+    /// Creates a beam with a Gaussian profile, whose width is the full width at half maximum, in
+    /// pixels.
+    fn new(width: f32) -> Self {
+        // a beam no wider than a pixel lights up just the pixel it passes through
+        if width <= 1.0 {
+            return Beam { radius: 0, weights: vec![1.0] }
         }
-        plot.done();
+        let sigma = width / (8.0 * 2f32.ln()).sqrt();
+        // the profile is cut off where it can no longer light up any pixels
+        let radius = (sigma * 3.0).ceil() as i32;
+        let weights = (-radius..=radius)
+            .flat_map(|y| (-radius..=radius).map(move |x| (x, y)))
+            .map(|(x, y)| (-((x * x + y * y) as f32) / (2.0 * sigma * sigma)).exp())
+            .collect();
+        Beam { radius, weights }
     }
 }
 
-struct Plot<'a, 'b> {
-    width: u32,
-    height: u32,
-    pixels: &'a mut [u8],
-    plot: &'b mut [u8],
-    previous_pos1: (i32, i32)
+/// Light that bright areas of the plot scatter around them, like in the glass of a CRT.
+#[derive(Clone, Copy)]
+struct Bloom {
+    /// How bright the scattered light is, relative to the light it comes from.
+    intensity: f32,
+    /// How far the light is scattered, in pixels.
+    radius: u32,
 }
 
-const fn to_u8(x: i32) -> u8 {
-    if x < 0 { 0 }
-    else if x > 255 { 255 }
-    else { x as u8 }
+impl Bloom {
+    /// How far the light is scattered if no radius is given.
+    const DEFAULT_RADIUS: u32 = 8;
+    /// How bright a pixel must be to scatter light, relative to the exposure.
+    const THRESHOLD: f32 = 0.5;
+
+    /// Returns the light scattered by every pixel of the plot.
+    fn glow(&self, plot: &[f32], width: u32, height: u32, exposure: f32) -> Vec<f32> {
+        let (width, height) = (width as usize, height as usize);
+        let threshold = exposure * Self::THRESHOLD;
+        let mut glow: Vec<f32> = plot.iter()
+            .map(|&intensity| (intensity - threshold).max(0.0) * self.intensity)
+            .collect();
+        // two box blurs in a row make a tent, which is close enough to a Gaussian and takes the
+        // same time no matter how wide it is. each direction is blurred separately
+        let radius = self.radius.div_ceil(2) as usize;
+        let mut scratch = vec![0.0; glow.len()];
+        for _ in 0..2 {
+            Self::box_blur(&glow, &mut scratch, height, width, 1, width, radius);
+            Self::box_blur(&scratch, &mut glow, width, height, width, 1, radius);
+        }
+        glow
+    }
+
+    /// Blurs every line of `input` into `output`, by averaging the pixels within `radius` of
+    /// each other. Lines are `length` pixels long, `step` apart within the buffer and their
+    /// pixels `stride` apart.
+    fn box_blur(
+        input: &[f32],
+        output: &mut [f32],
+        lines: usize,
+        length: usize,
+        stride: usize,
+        step: usize,
+        radius: usize,
+    ) {
+        let scale = 1.0 / (radius * 2 + 1) as f32;
+        for line in 0..lines {
+            let index = |i: usize| line * step + i * stride;
+            // a running sum of the window around the pixel, with nothing outside the edges
+            let mut sum: f32 = (0..radius.min(length)).map(|i| input[index(i)]).sum();
+            for i in 0..length {
+                if i + radius < length {
+                    sum += input[index(i + radius)];
+                }
+                if i > radius {
+                    sum -= input[index(i - radius - 1)];
+                }
+                // rounding errors can leave the sum slightly below zero after bright pixels
+                output[index(i)] = sum.max(0.0) * scale;
+            }
+        }
+    }
 }
 
-impl Plot<'_, '_> {
-    const INTENSITY_COLORS: [(u8, u8, u8); 256] = {
-        let mut colors = [(0u8, 0u8, 0u8); 256];
-        let mut i = 0;
-        while i < 256 {
-            let n = i as i32;
-            colors[i].1 = to_u8(n * 2);
-            let rb = to_u8(((n - 128) as f32 * 1.5) as i32);
-            colors[i].0 += rb;
-            colors[i].2 += rb;
-            i += 1;
+impl Plot<'_, '_, '_> {
+    /// The energy the beam puts into every line, spread out over the line's length. Like on a
+    /// CRT, the beam glows brighter where it moves slowly.
+    const BEAM_ENERGY: f32 = 128.0;
+    /// How much light it takes to get a pixel about two thirds of the way to full brightness.
+    /// Anything brighter is compressed towards full brightness instead of clipping.
+    const EXPOSURE: f32 = 256.0;
+    /// How much light a pixel must have left to not be considered dark. Fading out further would
+    /// make no visible difference, and float arithmetic gets very slow close to zero.
+    const MIN_INTENSITY: f32 = 1.0 / 256.0;
+    /// How far the bottom meter is from the bottom of the scope, which leaves room for the
+    /// indicator bar.
+    const METER_BOTTOM: u32 = 40;
+    /// How wide the level meters are, and how far they're kept from the edges of the scope.
+    const LEVEL_WIDTH: u32 = 8;
+    const LEVEL_MARGIN: u32 = 8;
+    /// How many times the exposure it takes to get to full brightness with the log transfer
+    /// curve, and how much fainter than that the faintest visible light is.
+    const LOG_RANGE: (f32, f32) = (4.0, 1000.0);
+
+    /// Returns the columns and rows of the frame the scope takes up.
+    fn scope(&self) -> (Range<u32>, Range<u32>) {
+        match &self.pane {
+            Some(pane) => pane_scope(pane, self.square),
+            None => scope_area(self.width, self.height, self.square, self.composite, self.supersampling),
         }
-        colors
-    };
+    }
+
+    /// Returns the center of the scope.
+    fn center(&self) -> (f32, f32) {
+        let (xs, ys) = self.scope();
+        ((xs.start + xs.end) as f32 / 2.0, (ys.start + ys.end) as f32 / 2.0)
+    }
+
+    /// Returns whether a pixel is inside the scope.
+    fn in_scope(&self, x: i32, y: i32) -> bool {
+        let (xs, ys) = self.scope();
+        (xs.start as i32..xs.end as i32).contains(&x) && (ys.start as i32..ys.end as i32).contains(&y)
+    }
 
     fn plot_index(&self, x: u32, y: u32) -> usize {
         (x + y * self.width) as usize
@@ -125,126 +388,1661 @@ impl Plot<'_, '_> {
     }
 
     fn done(&mut self) {
-        const DIVISIONS: u32 = 5;
-        const DIV_BRIGHTNESS: u8 = 24;
-        let division_width = self.width / DIVISIONS;
-        let division_height = self.height / DIVISIONS;
-        let half_division_width = division_width / 2;
-        let half_division_height = division_height / 2;
-
         // background
         for pixel in self.pixels.iter_mut() {
             *pixel = 0;
         }
-        for div in 0..DIVISIONS {
-            let x = half_division_width + division_width * div;
+        self.fill(0..self.width, 0..self.height, self.palette.background);
+        let panes = std::mem::take(&mut self.panes);
+        let tiled = !panes.is_empty();
+        for pane in panes {
+            self.pane = Some(pane.area);
+            self.polar = pane.polar;
+            self.speakers = pane.speakers;
+            match pane.image {
+                Some(image) => self.draw_image(image),
+                None => self.draw_graticule(),
+            }
+        }
+        // the panes leave their graticules behind, which aren't the scope's
+        if tiled {
+            self.pane = None;
+            self.polar = false;
+            self.speakers.clear();
+        }
+        if let Some(image) = self.image.take() {
+            self.draw_image(image);
+        } else if !tiled {
+            self.draw_graticule();
+        }
+        if let Some(overlay) = self.overlay.filter(|_| !tiled) {
+            let scope = self.scope();
+            overlay.draw(self.pixels, self.width, scope);
+        }
+
+        // dots
+        let exposure = match self.exposure {
+            Exposure::Fixed => Self::EXPOSURE,
+            // the brightest pixel is kept just short of full brightness
+            Exposure::Peak => self.plot.iter().fold(Self::EXPOSURE, |exposure, &intensity| {
+                exposure.max(intensity / 4.0)
+            }),
+            Exposure::Histogram => self.histogram_exposure(),
+        };
+        // the gamma is applied through a table, since raising every pixel to a power is slow
+        const CURVE_SIZE: usize = 1024;
+        let curve: Vec<f32> = (0..CURVE_SIZE)
+            .map(|i| (i as f32 / (CURVE_SIZE - 1) as f32).powf(1.0 / self.gamma))
+            .collect();
+        let frame_size = (self.width * self.height) as usize;
+        if let Some(reference) = self.reference {
+            self.draw_reference(reference, exposure);
+        }
+        if let Some(colormap) = self.colormap {
+            self.draw_heatmap(colormap);
+        }
+        let blend = match self.palette.dark_trace {
+            true => u8::saturating_sub,
+            false => u8::saturating_add,
+        };
+        let ramps = match (self.colormap, &self.gpu) {
+            (None, None) => &self.palette.ramps[..],
+            _ => &[],
+        };
+        for (ramp, plot) in ramps.iter().zip(self.plot.chunks_exact(frame_size)) {
+            let glow = self.bloom.map(|bloom| bloom.glow(plot, self.width, self.height, exposure));
             for y in 0..self.height {
-                let i = self.pixel_index(x, y);
-                self.pixels[i] = DIV_BRIGHTNESS;
-                self.pixels[i + 1] = DIV_BRIGHTNESS;
-                self.pixels[i + 2] = DIV_BRIGHTNESS;
+                for x in 0..self.width {
+                    let i = self.pixel_index(x, y);
+                    let j = self.plot_index(x, y);
+                    let intensity = plot[j] + glow.as_ref().map_or(0.0, |glow| glow[j]);
+                    let brightness = match self.transfer {
+                        Transfer::Exponential => 1.0 - (-intensity / exposure).exp(),
+                        Transfer::Log => {
+                            let (full, range) = Self::LOG_RANGE;
+                            let light = intensity / exposure / full * range;
+                            (light.ln_1p() / range.ln_1p()).min(1.0)
+                        },
+                    };
+                    let brightness = curve[(brightness * (CURVE_SIZE - 1) as f32) as usize];
+                    let tint = &self.tint[j * 4..j * 4 + 4];
+                    let color = match self.color.is_some() && tint[3] > 0.0 {
+                        true => {
+                            let hue = (tint[0] / tint[3], tint[1] / tint[3], tint[2] / tint[3]);
+                            palette::shade(hue, brightness)
+                        },
+                        false => ramp[(brightness * 255.0) as usize],
+                    };
+                    self.pixels[i] = blend(self.pixels[i], color.0);
+                    self.pixels[i + 1] = blend(self.pixels[i + 1], color.1);
+                    self.pixels[i + 2] = blend(self.pixels[i + 2], color.2);
+                }
             }
-            let y = half_division_height + division_height * div;
+        }
+
+        // envelope, drawn faintly between the brightness of the graticule and that of text
+        if !self.envelope.is_empty() {
+            let (graticule, text) = (self.palette.graticule, self.palette.text());
+            let mix = |a: u8, b: u8| ((a as u16 + b as u16) / 2) as u8;
+            let color = (mix(graticule.0, text.0), mix(graticule.1, text.1), mix(graticule.2, text.2));
+            let envelope = std::mem::take(&mut self.envelope);
+            for (i, &start) in envelope.iter().enumerate() {
+                let end = envelope[(i + 1) % envelope.len()];
+                self.line(start, end, color);
+            }
+        }
+
+        // mid and side traces, each in a color of its own so that they can be told apart
+        if let Some(traces) = self.mid_side.take() {
+            const MID_COLOR: (u8, u8, u8) = (96, 176, 255);
+            const SIDE_COLOR: (u8, u8, u8) = (255, 160, 64);
+            for (trace, color) in traces.iter().zip([MID_COLOR, SIDE_COLOR]) {
+                for line in trace.windows(2) {
+                    self.line(line[0], line[1], color);
+                }
+            }
+        }
+
+        // letterbox bars, which also cut off the trace where it goes past the scope. panes are
+        // kept square on their own
+        if self.square && !tiled {
+            let (xs, ys) = self.scope();
+            let background = self.palette.background;
+            let color = (background.0 / 2, background.1 / 2, background.2 / 2);
+            let (width, height) = (self.width, self.height);
+            self.fill(0..xs.start, 0..height, color);
+            self.fill(xs.end..width, 0..height, color);
+            self.fill(xs.clone(), 0..ys.start, color);
+            self.fill(xs, ys.end..height, color);
+        }
+
+        let ss = self.supersampling;
+        if let Some(cursor) = self.cursor.take() {
+            self.draw_crosshair(cursor);
+        }
+
+        // meters, stacked up from the bottom of the scope, clear of the indicator bar. the
+        // composite layout leaves room for them under the scope
+        let scope_bottom = if self.composite { self.height } else { self.scope().1.end };
+        let mut bottom = scope_bottom.saturating_sub(Self::METER_BOTTOM * ss);
+        if let Some(correlation) = self.correlation.take() {
+            bottom = self.draw_correlation(correlation, bottom);
+        }
+        if let Some(mid_side) = self.stereo_width.take() {
+            bottom = self.draw_stereo_width(mid_side, bottom);
+        }
+        if let Some(balance) = self.balance.take() {
+            self.draw_balance(balance, bottom);
+        }
+        // the heads-up display moves over to make room for the left level meter
+        let hud_left = match self.levels.take() {
+            Some(levels) => self.draw_levels(levels),
+            None => 0,
+        };
+
+        // data loss badge
+        const BADGE_SIZE: u32 = 12;
+        const BADGE_MARGIN: u32 = 8;
+        if self.data_lost {
+            const BADGE_COLOR: (u8, u8, u8) = (224, 128, 0);
+            let (size, margin) = (BADGE_SIZE * ss, BADGE_MARGIN * ss);
+            let left = self.width.saturating_sub(size + margin);
+            let right = self.width.saturating_sub(margin);
+            self.fill(left..right, margin..margin + size, BADGE_COLOR);
+        }
+
+        // measurement markers, with their readout in the top right corner, below the badge
+        if let Some(measurement) = self.measurement.take() {
+            const MARKER_SIZE: i32 = 6;
+            let color = self.palette.text();
+            let size = MARKER_SIZE * ss as i32;
+            for (position, name) in measurement.positions.iter().zip(["A", "B"]) {
+                let (x, y) = match position {
+                    Some((x, y)) => (x.round() as i32, y.round() as i32),
+                    None => continue,
+                };
+                if !self.in_scope(x, y) {
+                    continue
+                }
+                for offset in -size..=size {
+                    self.stroke(x + offset, y, color);
+                    self.stroke(x, y + offset, color);
+                }
+                self.text(x + size, y + size, name, color);
+            }
+            let margin = BADGE_MARGIN * ss;
+            let top = margin * 2 + BADGE_SIZE * ss;
+            let line_height = text::LINE_HEIGHT * self.text_scale();
+            for (i, line) in measurement.readout.iter().enumerate() {
+                let width = text::width(line) * self.text_scale();
+                let left = self.width.saturating_sub(margin + width);
+                self.text(left as i32, (top + i as u32 * line_height) as i32, line, color);
+            }
+        }
+
+        // indicator bar
+        if let Some((value, label)) = self.indicator.take() {
+            const BAR_HEIGHT: u32 = 4;
+            const BAR_MARGIN: u32 = 8;
+            const TRACK_COLOR: (u8, u8, u8) = (40, 40, 40);
+            const BAR_COLOR: (u8, u8, u8) = (192, 192, 192);
+            let (height, margin) = (BAR_HEIGHT * ss, BAR_MARGIN * ss);
+            let (left, right) = (margin, self.width.saturating_sub(margin));
+            let top = self.height.saturating_sub(margin + height);
+            let length = (right.saturating_sub(left) as f32 * value.clamp(0.0, 1.0)) as u32;
+            self.fill(left..right, top..top + height, TRACK_COLOR);
+            self.fill(left..left + length, top..top + height, BAR_COLOR);
+            let label_top = top as i32 - margin as i32 - (text::GLYPH_HEIGHT * self.text_scale()) as i32;
+            self.text(left as i32, label_top, &label, BAR_COLOR);
+        }
+
+        // heads-up display
+        if let Some(hud) = self.hud.take() {
+            const HUD_MARGIN: u32 = 8;
+            let margin = (HUD_MARGIN * ss) as i32;
+            let line_height = (text::LINE_HEIGHT * self.text_scale()) as i32;
+            let color = self.palette.text();
+            for (i, line) in hud.iter().enumerate() {
+                self.text(hud_left as i32 + margin, margin + i as i32 * line_height, line, color);
+            }
+        }
+
+        // the GPU picks the trace up from here, cut off where the letterbox bars are
+        let decay = [0, 1].map(|i| match self.frozen {
+            true => 1.0,
+            false => self.decay.powf(1.0 / self.layers[i].persistence),
+        });
+        let clip = match self.square && !tiled {
+            true => self.scope(),
+            false => (0..self.width, 0..self.height),
+        };
+        if let Some(frame) = &mut self.gpu {
+            frame.decay = decay;
+            frame.clip = clip;
+            frame.exposure = exposure;
+            frame.transfer = self.transfer;
+            frame.gamma = self.gamma;
+        }
+
+        // fading out
+        if self.frozen {
+            return
+        }
+        let plot = match self.gpu {
+            Some(_) => &mut [],
+            None => &mut self.plot[..],
+        };
+        for (layer, plot) in self.layers.iter().zip(plot.chunks_exact_mut(frame_size)) {
+            let decay = self.decay.powf(1.0 / layer.persistence);
+            for plot in plot.iter_mut() {
+                *plot = if *plot < Self::MIN_INTENSITY { 0.0 } else { *plot * decay };
+            }
+        }
+        if self.colormap.is_some() {
+            let decay = self.density_decay;
+            for density in self.density.iter_mut() {
+                *density = if *density < Self::MIN_INTENSITY { 0.0 } else { *density * decay };
+            }
+        }
+        // older colors give way to newer ones at the same pace as the trace fades out
+        if self.color.is_some() {
+            let decay = self.decay;
+            for tint in self.tint.chunks_exact_mut(4) {
+                match tint[3] < Self::MIN_INTENSITY {
+                    true => tint.fill(0.0),
+                    false => tint.iter_mut().for_each(|value| *value *= decay),
+                }
+            }
+        }
+    }
+
+    /// Draws a picture that fills the scope.
+    fn draw_image(&mut self, image: png::Image) {
+        let (xs, ys) = self.scope();
+        for (y, row) in ys.zip(image.rgba.chunks_exact(image.width as usize * 4)) {
+            let i = self.pixel_index(xs.start, y);
+            self.pixels[i..i + row.len()].copy_from_slice(row);
+        }
+    }
+
+    fn draw_graticule(&mut self) {
+        const NO_INPUT_COLOR: (u8, u8, u8) = (64, 8, 8);
+        let settings = self.graticule;
+        let background = self.palette.background;
+        let base_color = match self.no_input {
+            true => NO_INPUT_COLOR,
+            false => settings.color.unwrap_or(self.palette.graticule),
+        };
+        let mix = |opacity: f32| {
+            let channel = |from: u8, to: u8| {
+                (from as f32 + (to as f32 - from as f32) * opacity).clamp(0.0, 255.0) as u8
+            };
+            (
+                channel(background.0, base_color.0),
+                channel(background.1, base_color.1),
+                channel(background.2, base_color.2),
+            )
+        };
+        let color = mix(settings.opacity);
+        // the center lines stand out twice as much as the others
+        let center_color = mix(settings.opacity * 2.0);
+        let divisions = settings.divisions;
+        // how far labels are kept from the lines they belong to
+        const LABEL_MARGIN: i32 = 4;
+        let label_margin = LABEL_MARGIN * self.supersampling as i32;
+        let thickness = self.supersampling;
+        if self.polar {
+            if settings.style != Graticule::Off {
+                self.draw_polar_graticule(color, center_color, settings.labels);
+            }
+            return
+        }
+        if !self.speakers.is_empty() {
+            if settings.style != Graticule::Off {
+                self.draw_surround_graticule(color, center_color, settings.labels);
+            }
+            return
+        }
+        // the amplitudes of the circles drawn for the decibel marks
+        const DECIBELS: [f32; 4] = [0.0, -3.0, -6.0, -12.0];
+        // how many degrees apart the spokes of the polar grid are
+        const SPOKE_ANGLE: u32 = 30;
+        let (xs, ys) = self.scope();
+        let (width, height) = (xs.end - xs.start, ys.end - ys.start);
+        let (center_x, center_y) = ((xs.start + xs.end) / 2, (ys.start + ys.end) / 2);
+        let crosshair = |plot: &mut Self, color: (u8, u8, u8)| {
+            plot.fill(center_x..center_x + thickness, ys.clone(), color);
+            plot.fill(xs.clone(), center_y..center_y + thickness, color);
+        };
+        let scale = self.text_scale();
+        let text_height = (text::GLYPH_HEIGHT * scale) as i32;
+        let (center_x, center_y) = (center_x as i32, center_y as i32);
+        // labels go just inside the circles, above the horizontal axis
+        let circle_label = |plot: &mut Self, amplitude: f32, label: &str| {
+            let radius = (width as f32 / 2.0 * amplitude) as i32;
+            let x = center_x + radius - (text::width(label) * scale) as i32 - label_margin;
+            plot.text(x, center_y - text_height - label_margin, label, center_color);
+        };
+        // labels at the ends of the axes
+        let axis_labels = |plot: &mut Self| {
+            let label_width = (text::width("+1") * scale) as i32;
+            let (left, top) = (xs.start as i32, ys.start as i32);
+            let (right, bottom) = (xs.end as i32, ys.end as i32);
+            plot.text(right - label_width - label_margin, center_y + label_margin, "+1", center_color);
+            plot.text(left + label_margin, center_y + label_margin, "-1", center_color);
+            plot.text(center_x + label_margin, top + label_margin, "+1", center_color);
+            plot.text(center_x + label_margin, bottom - text_height - label_margin, "-1", center_color);
+        };
+        match settings.style {
+            Graticule::Grid => {
+                let division_width = width / divisions;
+                let division_height = height / divisions;
+                for div in 0..divisions {
+                    let x = xs.start + division_width / 2 + division_width * div;
+                    self.fill(x..x + thickness, ys.clone(), color);
+                    let y = ys.start + division_height / 2 + division_height * div;
+                    self.fill(xs.clone(), y..y + thickness, color);
+                }
+            },
+            Graticule::Polar => {
+                for ring in 1..=divisions {
+                    let position = ring as f32 / divisions as f32;
+                    self.ellipse(position, color);
+                    if settings.labels {
+                        let label = match self.amplitude_scale {
+                            AmplitudeScale::Linear => format!("{:.2}", position),
+                            AmplitudeScale::Decibels => format!("{:.0} dB", (position - 1.0) * self.db_range),
+                        };
+                        circle_label(self, position, &label);
+                    }
+                }
+                for angle in (0..360).step_by(SPOKE_ANGLE as usize) {
+                    self.spoke((angle as f32).to_radians(), color);
+                }
+            },
+            Graticule::Crosshair => crosshair(self, color),
+            Graticule::Decibels => {
+                crosshair(self, color);
+                for decibels in DECIBELS {
+                    let position = self.amplitude_scale.apply(10f32.powf(decibels / 20.0), self.db_range);
+                    self.ellipse(position, color);
+                    if settings.labels {
+                        circle_label(self, position, &format!("{} dB", decibels));
+                    }
+                }
+            },
+            Graticule::Off => return,
+        }
+        if settings.labels && matches!(settings.style, Graticule::Grid | Graticule::Crosshair) {
+            axis_labels(self);
+        }
+        if settings.emphasize_center {
+            crosshair(self, center_color);
+        }
+    }
+
+    /// Draws the graticule of the polar display: half rings for the amplitudes, and spokes for
+    /// the directions sound comes from, labeled with the channels, the mid and the side.
+    fn draw_polar_graticule(&mut self, color: (u8, u8, u8), center_color: (u8, u8, u8), labels: bool) {
+        const RINGS: u32 = 4;
+        const SPOKES: [(f32, &str); 5] = [(-90.0, "S"), (-45.0, "L"), (0.0, "M"), (45.0, "R"), (90.0, "S")];
+        const LABEL_MARGIN: f32 = 4.0;
+        let (xs, ys) = self.scope();
+        let (center_x, bottom) = ((xs.start + xs.end) as f32 / 2.0, ys.end as f32 - 1.0);
+        let (radius_x, radius_y) = (xs.len() as f32 / 2.0, ys.len() as f32 - 1.0);
+        let point = |amplitude: f32, angle: f32| {
+            let (sin, cos) = angle.to_radians().sin_cos();
+            (center_x + radius_x * amplitude * sin, bottom - radius_y * amplitude * cos)
+        };
+        for ring in 1..=RINGS {
+            let amplitude = ring as f32 / RINGS as f32;
+            // enough steps to not leave gaps between the pixels
+            let steps = (std::f32::consts::PI * radius_x.max(radius_y) * amplitude * 2.0).ceil() as u32;
+            for step in 0..=steps {
+                let (x, y) = point(amplitude, step as f32 / steps.max(1) as f32 * 180.0 - 90.0);
+                self.stroke(x as i32, y as i32, color);
+            }
+        }
+        let scale = self.text_scale();
+        let margin = LABEL_MARGIN * self.supersampling as f32;
+        for (angle, label) in SPOKES {
+            let color = if angle == 0.0 { center_color } else { color };
+            let steps = radius_x.max(radius_y).ceil() as u32;
+            for step in 0..=steps {
+                let (x, y) = point(step as f32 / steps as f32, angle);
+                self.stroke(x as i32, y as i32, color);
+            }
+            if labels {
+                let (x, y) = point(1.0, angle);
+                let width = (text::width(label) * scale) as f32;
+                let height = (text::GLYPH_HEIGHT * scale) as f32;
+                // the labels go just inside the outer ring
+                let (sin, cos) = angle.to_radians().sin_cos();
+                let x = x - sin * (width + margin) - width / 2.0;
+                let y = y + cos * (height + margin) - height / 2.0;
+                let x = x.clamp(xs.start as f32, xs.end as f32 - width);
+                let y = y.clamp(ys.start as f32, bottom - height);
+                self.text(x as i32, y as i32, label, center_color);
+            }
+        }
+    }
+
+    /// Draws the graticule of the surround display: rings for the levels, and spokes towards the
+    /// speakers, labeled with their names.
+    fn draw_surround_graticule(&mut self, color: (u8, u8, u8), center_color: (u8, u8, u8), labels: bool) {
+        const RINGS: u32 = 4;
+        const LABEL_MARGIN: f32 = 4.0;
+        for ring in 1..=RINGS {
+            self.ellipse(ring as f32 / RINGS as f32, color);
+        }
+        let (xs, ys) = self.scope();
+        let (center_x, center_y) = self.center();
+        let (radius_x, radius_y) = (xs.len() as f32 / 2.0, ys.len() as f32 / 2.0);
+        let scale = self.text_scale();
+        let margin = LABEL_MARGIN * self.supersampling as f32;
+        for (name, angle) in std::mem::take(&mut self.speakers) {
+            // the front is straight up, and the angles go clockwise
+            self.spoke((90.0 - angle).to_radians(), color);
+            if labels {
+                let (sin, cos) = angle.to_radians().sin_cos();
+                let width = (text::width(name) * scale) as f32;
+                let height = (text::GLYPH_HEIGHT * scale) as f32;
+                // the labels go just inside the outer ring
+                let x = center_x + radius_x * sin - sin * (width + margin) - width / 2.0;
+                let y = center_y - radius_y * cos + cos * (height + margin) - height / 2.0;
+                let x = x.clamp(xs.start as f32, xs.end as f32 - width);
+                let y = y.clamp(ys.start as f32, ys.end as f32 - height);
+                self.text(x as i32, y as i32, name, center_color);
+            }
+        }
+    }
+
+    /// Draws an outline around the center of the scope at the given amplitude, which is a circle
+    /// if the scope is square.
+    fn ellipse(&mut self, amplitude: f32, color: (u8, u8, u8)) {
+        let (xs, ys) = self.scope();
+        let (center_x, center_y) = self.center();
+        let radius_x = xs.len() as f32 / 2.0 * amplitude;
+        let radius_y = ys.len() as f32 / 2.0 * amplitude;
+        // enough steps to not leave gaps between the pixels
+        let steps = (std::f32::consts::TAU * radius_x.max(radius_y) * 2.0).ceil() as u32;
+        for step in 0..steps {
+            let angle = step as f32 / steps as f32 * std::f32::consts::TAU;
+            let x = center_x + radius_x * angle.cos();
+            let y = center_y + radius_y * angle.sin();
+            self.stroke(x as i32, y as i32, color);
+        }
+    }
+
+    /// Draws a line from the center of the scope out to full amplitude, at an angle in radians.
+    fn spoke(&mut self, angle: f32, color: (u8, u8, u8)) {
+        let (xs, ys) = self.scope();
+        let (radius_x, radius_y) = (xs.len() as f32 / 2.0, ys.len() as f32 / 2.0);
+        let (center_x, center_y) = self.center();
+        let steps = radius_x.max(radius_y).ceil() as u32;
+        for step in 0..=steps {
+            let distance = step as f32 / steps as f32;
+            let x = center_x + radius_x * distance * angle.cos();
+            let y = center_y - radius_y * distance * angle.sin();
+            self.stroke(x as i32, y as i32, color);
+        }
+    }
+
+    /// Draws the track of a meter under the scope with its bottom edge at `bottom`, with a label
+    /// above it and the values at its ends on either side. Returns where the track is, and where
+    /// the next meter's bottom goes.
+    fn meter(&mut self, bottom: u32, label: &str, ends: [&str; 2]) -> (Range<u32>, Range<u32>, u32) {
+        const METER_WIDTH: u32 = 240;
+        const METER_HEIGHT: u32 = 6;
+        // how far apart the label and the track are, and the meter and the next one
+        const METER_SPACING: u32 = 4;
+        const TRACK_COLOR: (u8, u8, u8) = (40, 40, 40);
+        let ss = self.supersampling;
+        let (xs, _) = self.scope();
+        let width = (METER_WIDTH * ss).min(xs.len() as u32 / 2);
+        let left = xs.start + (xs.len() as u32 - width) / 2;
+        let right = left + width;
+        let top = bottom.saturating_sub(METER_HEIGHT * ss);
+        self.fill(left..right, top..bottom, TRACK_COLOR);
+        let (spacing, scale, color) = (METER_SPACING * ss, self.text_scale(), self.palette.text());
+        let label_top = top.saturating_sub(spacing + text::GLYPH_HEIGHT * scale);
+        self.text(left as i32, label_top as i32, label, color);
+        let label_middle = (top + bottom) as i32 / 2 - (text::GLYPH_HEIGHT * scale) as i32 / 2;
+        let start_width = text::width(ends[0]) * scale;
+        self.text(left as i32 - (start_width + spacing) as i32, label_middle, ends[0], color);
+        self.text((right + spacing) as i32, label_middle, ends[1], color);
+        (left..right, top..bottom, label_top.saturating_sub(spacing * 3))
+    }
+
+    /// Draws the correlation meter: a bar from the middle towards -1 on the left or 1 on the
+    /// right. Returns where the next meter's bottom goes.
+    fn draw_correlation(&mut self, correlation: f32, bottom: u32) -> u32 {
+        // out of phase channels cancel out when mixed to mono, so they're warned about
+        const NEGATIVE_COLOR: (u8, u8, u8) = (224, 128, 0);
+        let label = format!("correlation {:+.2}", correlation);
+        let (xs, ys, next) = self.meter(bottom, &label, ["-1", "+1"]);
+        let middle = (xs.start + xs.end) / 2;
+        let end = (middle as f32 + correlation * (xs.len() / 2) as f32).round() as u32;
+        match correlation < 0.0 {
+            true => self.fill(end..middle, ys.clone(), NEGATIVE_COLOR),
+            false => self.fill(middle..end, ys.clone(), self.palette.text()),
+        }
+        // marks at -1, 0 and 1
+        let ss = self.supersampling;
+        for x in [xs.start, middle, xs.end.saturating_sub(ss)] {
+            self.fill(x..x + ss, ys.start.saturating_sub(ss * 2)..ys.end + ss * 2, self.palette.graticule);
+        }
+        next
+    }
+
+    /// Draws the stereo width meter: a bar filled by the side signal's share of the energy,
+    /// which is empty for mono, halfway for channels that have nothing to do with each other,
+    /// and full when one is the other upside down. Returns where the next meter's bottom goes.
+    fn draw_stereo_width(&mut self, mid_side: Option<(f32, f32)>, bottom: u32) -> u32 {
+        // past halfway, there's more side than mid, which falls apart when mixed to mono
+        const WIDE_COLOR: (u8, u8, u8) = (224, 128, 0);
+        let label = match mid_side {
+            Some((_, 0.0)) => "width S/M -inf dB".to_owned(),
+            Some((0.0, _)) => "width S/M +inf dB".to_owned(),
+            Some((mid, side)) => format!("width S/M {:+.1} dB", 10.0 * (side / mid).log10()),
+            None => "width S/M - dB".to_owned(),
+        };
+        let (xs, ys, next) = self.meter(bottom, &label, ["M", "S"]);
+        let share = mid_side.map_or(0.0, |(mid, side)| side / (mid + side));
+        let end = xs.start + (xs.len() as f32 * share).round() as u32;
+        let middle = (xs.start + xs.end) / 2;
+        self.fill(xs.start..end.min(middle), ys.clone(), self.palette.text());
+        self.fill(middle..end.max(middle), ys.clone(), WIDE_COLOR);
+        let ss = self.supersampling;
+        let mark = ys.start.saturating_sub(ss * 2)..ys.end + ss * 2;
+        self.fill(middle..middle + ss, mark, self.palette.graticule);
+        next
+    }
+
+    /// Draws the balance meter: a bar from the middle towards the louder channel, X on the left
+    /// and Y on the right, which reaches the end at `RANGE` decibels. Returns where the next
+    /// meter's bottom goes.
+    fn draw_balance(&mut self, balance: Option<f32>, bottom: u32) -> u32 {
+        const RANGE: f32 = 12.0;
+        // a mismatch this big is more than a mix leaning to one side
+        const MISMATCH: f32 = 3.0;
+        const MISMATCH_COLOR: (u8, u8, u8) = (224, 128, 0);
+        let label = match balance {
+            Some(balance) if balance.abs() < 0.05 => "balance centered".to_owned(),
+            Some(balance) => {
+                let side = if balance < 0.0 { "X" } else { "Y" };
+                format!("balance {:.1} dB towards {}", balance.abs(), side)
+            },
+            None => "balance -".to_owned(),
+        };
+        let (xs, ys, next) = self.meter(bottom, &label, ["X", "Y"]);
+        let middle = (xs.start + xs.end) / 2;
+        let balance = balance.unwrap_or(0.0);
+        let end = (middle as f32 + (balance / RANGE).clamp(-1.0, 1.0) * (xs.len() / 2) as f32).round() as u32;
+        let color = match balance.abs() < MISMATCH {
+            true => self.palette.text(),
+            false => MISMATCH_COLOR,
+        };
+        self.fill(end.min(middle)..end.max(middle), ys.clone(), color);
+        let ss = self.supersampling;
+        let mark = ys.start.saturating_sub(ss * 2)..ys.end + ss * 2;
+        self.fill(middle..middle + ss, mark, self.palette.graticule);
+        next
+    }
+
+    /// Draws the level meters along the left and right edges of the scope, for the X and Y
+    /// channels, with their scales on the inside. The composite layout puts them outside the
+    /// scope instead, with their scales on the outside. Returns how far the left meter and its
+    /// scale reach into the window.
+    fn draw_levels(&mut self, levels: [(f32, f32); 2]) -> u32 {
+        const TRACK_COLOR: (u8, u8, u8) = (40, 40, 40);
+        // levels close to full scale are warned about
+        const HOT_COLOR: (u8, u8, u8) = (224, 128, 0);
+        const HOT_LEVEL: f32 = -6.0;
+        const CLIP_COLOR: (u8, u8, u8) = (224, 32, 32);
+        // how far down the scale goes, and the levels marked on it
+        const RANGE: f32 = 60.0;
+        const MARKS: [f32; 6] = [0.0, -6.0, -12.0, -24.0, -36.0, -48.0];
+        let ss = self.supersampling;
+        let (xs, ys) = self.scope();
+        let (width, margin, scale) = (Self::LEVEL_WIDTH * ss, Self::LEVEL_MARGIN * ss, self.text_scale());
+        let (top, bottom) = match self.composite {
+            true => (ys.start, ys.end),
+            false => (ys.start + margin, ys.end.saturating_sub(Self::METER_BOTTOM * ss)),
+        };
+        let height = bottom.saturating_sub(top);
+        // the height above the bottom a level in dBFS is at
+        let position = |level: f32| {
+            let db = if level > 0.0 { 20.0 * level.log10() } else { -RANGE };
+            ((db / RANGE + 1.0).clamp(0.0, 1.0) * height as f32).round() as u32
+        };
+        let text_color = self.palette.text();
+        let graticule = self.palette.graticule;
+        let scale_width = text::width("-48") * scale;
+        for (channel, &(rms, peak)) in levels.iter().enumerate() {
+            let left = match (channel, self.composite) {
+                (0, false) => xs.start + margin,
+                (_, false) => xs.end.saturating_sub(margin + width),
+                (0, true) => xs.start.saturating_sub(margin + width),
+                (_, true) => xs.end + margin,
+            };
+            self.fill(left..left + width, top..bottom, TRACK_COLOR);
+            let rms_top = bottom - position(rms);
+            let hot_top = bottom - position(10f32.powf(HOT_LEVEL / 20.0));
+            self.fill(left..left + width, rms_top.max(hot_top)..bottom, text_color);
+            self.fill(left..left + width, rms_top..hot_top.max(rms_top), HOT_COLOR);
+            let peak_top = (bottom - position(peak)).min(bottom.saturating_sub(ss));
+            let peak_color = if peak >= 1.0 { CLIP_COLOR } else { text_color };
+            self.fill(left..left + width, peak_top..peak_top + ss, peak_color);
+            // the scale goes on the side facing the picture, or away from it when the meters are
+            // outside of it
+            for mark in MARKS {
+                let y = bottom - position(10f32.powf(mark / 20.0));
+                let label = format!("{}", mark);
+                let label_width = text::width(&label) * scale;
+                let (tick, label_left) = match (channel == 0) != self.composite {
+                    true => (left + width..left + width + ss * 3, left + width + ss * 4),
+                    false => (left.saturating_sub(ss * 3)..left, left.saturating_sub(ss * 4 + label_width)),
+                };
+                self.fill(tick, y..y + ss, graticule);
+                let label_top = y as i32 - (text::GLYPH_HEIGHT * scale) as i32 / 2;
+                self.text(label_left as i32, label_top, &label, text_color);
+            }
+        }
+        match self.composite {
+            true => xs.start,
+            false => xs.start + margin + width + ss * 4 + scale_width,
+        }
+    }
+
+    /// Draws the reference snapshot in a dim version of the trace's color, so that the trace
+    /// stands out over it.
+    fn draw_reference(&mut self, reference: &[f32], exposure: f32) {
+        const REFERENCE_BRIGHTNESS: f32 = 0.35;
+        let ramp = &self.palette.ramps[0];
+        let blend = match self.palette.dark_trace {
+            true => u8::saturating_sub,
+            false => u8::saturating_add,
+        };
+        for y in 0..self.height {
             for x in 0..self.width {
+                let intensity = reference[self.plot_index(x, y)];
+                if intensity <= 0.0 {
+                    continue
+                }
+                let brightness = (1.0 - (-intensity / exposure).exp()) * REFERENCE_BRIGHTNESS;
+                let color = ramp[(brightness * 255.0) as usize];
                 let i = self.pixel_index(x, y);
-                self.pixels[i] = DIV_BRIGHTNESS;
-                self.pixels[i + 1] = DIV_BRIGHTNESS;
-                self.pixels[i + 2] = DIV_BRIGHTNESS;
+                self.pixels[i] = blend(self.pixels[i], color.0);
+                self.pixels[i + 1] = blend(self.pixels[i + 1], color.1);
+                self.pixels[i + 2] = blend(self.pixels[i + 2], color.2);
             }
         }
+    }
 
-        // dots
+    /// Draws the heatmap over the background, on a logarithmic scale up to the most hit pixel,
+    /// so that the spots the beam only passes through now and then still show up.
+    fn draw_heatmap(&mut self, colormap: &[(u8, u8, u8); 256]) {
+        let most = self.density.iter().fold(0.0, |most: f32, &density| most.max(density));
+        if most <= 0.0 {
+            return
+        }
         for y in 0..self.height {
             for x in 0..self.width {
+                let density = self.density[self.plot_index(x, y)];
+                if density <= 0.0 {
+                    continue
+                }
+                let level = density.ln_1p() / most.ln_1p();
+                let color = colormap[(level * 255.0) as usize];
                 let i = self.pixel_index(x, y);
-                let intensity = self.plot[self.plot_index(x, y)];
-                let color = Self::INTENSITY_COLORS[intensity as usize];
-                self.pixels[i] = self.pixels[i].saturating_add(color.0);
-                self.pixels[i + 1] = self.pixels[i + 1].saturating_add(color.1);
-                self.pixels[i + 2] = self.pixels[i + 2].saturating_add(color.2);
+                self.pixels[i] = color.0;
+                self.pixels[i + 1] = color.1;
+                self.pixels[i + 2] = color.2;
             }
         }
+    }
 
-        // fading out
-        for plot in self.plot.iter_mut() {
-            *plot = (*plot as f32 * 0.85) as u8;
+    /// Counts a hit of the beam at a point for the heatmap, if it's on.
+    fn hit(&mut self, x: f32, y: f32, weight: f32) {
+        let (x, y) = (x.round() as i32, y.round() as i32);
+        let inside = (0..self.width as i32).contains(&x) && (0..self.height as i32).contains(&y);
+        if self.colormap.is_some() && inside {
+            let i = self.plot_index(x as u32, y as u32);
+            self.density[i] += weight;
+        }
+    }
+
+    /// Draws lines through the beam's position across the scope, with the values of the channels
+    /// written next to where they cross.
+    fn draw_crosshair(&mut self, cursor: Cursor) {
+        const LABEL_MARGIN: u32 = 6;
+        let (xs, ys) = self.scope();
+        let (x, y) = (cursor.position.0.round() as i32, cursor.position.1.round() as i32);
+        if !self.in_scope(x, y) {
+            return
+        }
+        let (x, y) = (x as u32, y as u32);
+        let color = self.palette.text();
+        let thickness = self.supersampling;
+        self.fill(xs.clone(), y..(y + thickness).min(ys.end), color);
+        self.fill(x..(x + thickness).min(xs.end), ys.clone(), color);
+
+        let dbfs = |value: f32| match value {
+            _ if value == 0.0 => "-inf dBFS".to_owned(),
+            _ => format!("{:.1} dBFS", 20.0 * value.abs().log10()),
+        };
+        let labels = [
+            format!("X {:+.3} {}", cursor.values.0, dbfs(cursor.values.0)),
+            format!("Y {:+.3} {}", cursor.values.1, dbfs(cursor.values.1)),
+        ];
+        // the labels are kept inside the scope, flipping to the other side of the lines when
+        // they'd run off the edge
+        let scale = self.text_scale();
+        let margin = LABEL_MARGIN * self.supersampling;
+        let width = labels.iter().map(|label| text::width(label)).max().unwrap_or(0) * scale;
+        let height = text::LINE_HEIGHT * scale * labels.len() as u32;
+        let left = match x + margin + width <= xs.end {
+            true => x + margin,
+            false => x.saturating_sub(margin + width),
+        };
+        let top = match y + margin + height <= ys.end {
+            true => y + margin,
+            false => y.saturating_sub(margin + height),
+        };
+        for (i, label) in labels.iter().enumerate() {
+            let line_top = top + i as u32 * text::LINE_HEIGHT * scale;
+            self.text(left as i32, line_top as i32, label, color);
+        }
+    }
+
+    /// How many times bigger than the font text is drawn, so that it stays readable in big
+    /// windows.
+    fn text_scale(&self) -> u32 {
+        text_scale(self.width, self.height)
+    }
+
+    /// Draws a line of text with its top left corner at the given position.
+    fn text(&mut self, x: i32, y: i32, text: &str, color: (u8, u8, u8)) {
+        let scale = self.text_scale() as i32;
+        for (column, row) in text::pixels(text) {
+            let (left, top) = (x + column as i32 * scale, y + row as i32 * scale);
+            for (dx, dy) in (0..scale).flat_map(|dy| (0..scale).map(move |dx| (dx, dy))) {
+                self.paint(left + dx, top + dy, color);
+            }
+        }
+    }
+
+    /// Draws a line as thick as the lines of the graticule between two points of the frame.
+    fn line(&mut self, start: (f32, f32), end: (f32, f32), color: (u8, u8, u8)) {
+        // lines that are all off to one side of the frame, eg. when zoomed in, are skipped
+        let (width, height) = (self.width as f32, self.height as f32);
+        let outside = |a: f32, b: f32, size: f32| (a < 0.0 && b < 0.0) || (a >= size && b >= size);
+        if outside(start.0, end.0, width) || outside(start.1, end.1, height) {
+            return
+        }
+        let length = (end.0 - start.0).hypot(end.1 - start.1);
+        let steps = (length.ceil() as i32).max(1);
+        for step in 0..=steps {
+            let t = step as f32 / steps as f32;
+            let (x, y) = (start.0 + (end.0 - start.0) * t, start.1 + (end.1 - start.1) * t);
+            self.stroke(x.round() as i32, y.round() as i32, color);
+        }
+    }
+
+    /// Paints a square as wide as the lines of the graticule, so that they don't get fainter
+    /// with supersampling.
+    fn stroke(&mut self, x: i32, y: i32, color: (u8, u8, u8)) {
+        let thickness = self.supersampling as i32;
+        for (dx, dy) in (0..thickness).flat_map(|dy| (0..thickness).map(move |dx| (dx, dy))) {
+            self.paint(x + dx, y + dy, color);
         }
     }
 
-    fn pixel(&mut self, x: i32, y: i32, intensity: u8) {
+    /// Sets a pixel of the frame to a color, if it's inside the frame.
+    fn paint(&mut self, x: i32, y: i32, color: (u8, u8, u8)) {
+        if (0..self.width as i32).contains(&x) && (0..self.height as i32).contains(&y) {
+            let i = self.pixel_index(x as u32, y as u32);
+            self.pixels[i] = color.0;
+            self.pixels[i + 1] = color.1;
+            self.pixels[i + 2] = color.2;
+        }
+    }
+
+    /// Picks an exposure that gets most of the lit pixels to a good brightness, by finding how
+    /// bright the brightest of them are in a histogram of the picture.
+    fn histogram_exposure(&self) -> f32 {
+        // the share of lit pixels that are allowed to be brighter than the one the exposure is
+        // picked for, so that a few hot spots don't darken everything else
+        const HOT_SHARE: f32 = 0.005;
+        // the most the picture can be brightened, so that noise doesn't get blown up
+        const MAX_GAIN: f32 = 64.0;
+        // the light is counted in bins on a log scale, a few bins every time it doubles
+        const BINS_PER_OCTAVE: f32 = 8.0;
+        const BINS: usize = 256;
+        let mut histogram = [0u32; BINS];
+        let mut lit = 0;
+        for &intensity in self.plot.iter().filter(|&&intensity| intensity >= Self::MIN_INTENSITY) {
+            let bin = ((intensity / Self::MIN_INTENSITY).log2() * BINS_PER_OCTAVE) as usize;
+            histogram[bin.min(BINS - 1)] += 1;
+            lit += 1;
+        }
+        if lit == 0 {
+            return Self::EXPOSURE
+        }
+        let mut brighter = 0;
+        let hot = (lit as f32 * HOT_SHARE) as u32;
+        let bin = (0..BINS).rev()
+            .find(|&bin| {
+                brighter += histogram[bin];
+                brighter > hot
+            })
+            .unwrap_or(0);
+        let intensity = Self::MIN_INTENSITY * (bin as f32 / BINS_PER_OCTAVE).exp2();
+        // with the exposure at half the light, a pixel gets about 86% of the way to full brightness
+        (intensity / 2.0).max(Self::EXPOSURE / MAX_GAIN)
+    }
+
+    /// Fills a rectangle of the frame with a color. Whatever falls outside the frame is cut off.
+    fn fill(&mut self, xs: Range<u32>, ys: Range<u32>, color: (u8, u8, u8)) {
+        for y in ys.start..ys.end.min(self.height) {
+            for x in xs.start..xs.end.min(self.width) {
+                let i = self.pixel_index(x, y);
+                self.pixels[i] = color.0;
+                self.pixels[i + 1] = color.1;
+                self.pixels[i + 2] = color.2;
+            }
+        }
+    }
+
+    fn pixel(&mut self, x: i32, y: i32, intensity: f32) {
         if (0..self.width as i32).contains(&x) && (0..self.height as i32).contains(&y) {
             let (x, y) = (x as u32, y as u32);
+            let frame_size = (self.width * self.height) as usize;
             let i = (x + y * self.width) as usize;
-            self.plot[i] = self.plot[i].saturating_add(intensity);
+            for (layer, plot) in self.layers.iter().zip(self.plot.chunks_exact_mut(frame_size)) {
+                plot[i] += intensity * layer.share;
+            }
+            if let Some(color) = self.color {
+                let tint = &mut self.tint[i * 4..i * 4 + 4];
+                tint[0] += color.0 * intensity;
+                tint[1] += color.1 * intensity;
+                tint[2] += color.2 * intensity;
+                tint[3] += intensity;
+            }
+        }
+    }
+
+    fn point(&mut self, x: i32, y: i32, intensity: f32) {
+        let beam = self.beam;
+        let size = beam.radius * 2 + 1;
+        for (i, weight) in beam.weights.iter().enumerate() {
+            let (dx, dy) = (i as i32 % size - beam.radius, i as i32 / size - beam.radius);
+            self.pixel(x + dx, y + dy, intensity * weight);
         }
     }
 
-    fn point(&mut self, x: i32, y: i32, intensity: u8) {
-        self.pixel(x, y, intensity);
-//         self.pixel(x + 1, y, intensity);
-//         self.pixel(x - 1, y, intensity);
-//         self.pixel(x, y + 1, intensity);
-//         self.pixel(x, y - 1, intensity);
+    /// Lights up a point that falls between pixels, shared out between the four pixels around it
+    /// by how close it is to each, so that small movements of the beam don't snap to the grid.
+    fn spot(&mut self, x: f32, y: f32, intensity: f32) {
+        if let Some(frame) = &mut self.gpu {
+            frame.line((x, y), (x, y), intensity);
+            return
+        }
+        let (left, top) = (x.floor(), y.floor());
+        let (fx, fy) = (x - left, y - top);
+        let (x, y) = (left as i32, top as i32);
+        let shares = [
+            (0, 0, (1.0 - fx) * (1.0 - fy)),
+            (1, 0, fx * (1.0 - fy)),
+            (0, 1, (1.0 - fx) * fy),
+            (1, 1, fx * fy),
+        ];
+        for &(dx, dy, share) in &shares {
+            if share > 0.0 {
+                self.point(x + dx, y + dy, intensity * share);
+            }
+        }
     }
 
-    fn dot(&mut self, x0: i32, y0: i32, x1: i32, y1: i32) {
-        let (x0, y0) = (x0 as i32, y0 as i32);
-        let (x1, y1) = (x1 as i32, y1 as i32);
-        for (x, y) in line_drawing::Bresenham::new((x0, y0), (x1, y1)) {
-            if (x, y) != self.previous_pos1 {
-                self.point(x, y, 16);
+    /// Draws an anti-aliased line, with pixels lit in proportion to how much the line covers
+    /// them and to how short the line is. `energy` is spread over the whole line.
+    fn dot(&mut self, x0: f32, y0: f32, x1: f32, y1: f32, energy: f32) {
+        // garbage input can have samples that are way out of range or not even numbers, which
+        // would take forever to draw or couldn't be drawn at all
+        const LIMIT: f32 = 65536.0;
+        if ![x0, y0, x1, y1].iter().all(|coordinate| coordinate.abs() <= LIMIT) {
+            return
+        }
+        let length = ((x1 - x0).powi(2) + (y1 - y0).powi(2)).sqrt();
+        let intensity = energy / length.max(1.0);
+        if let Some(frame) = &mut self.gpu {
+            frame.line((x0, y0), (x1, y1), intensity);
+            return
+        }
+        // a line starts where the previous one ended, and that's already been drawn. lines are
+        // drawn a column (or row, for steep lines) at a time, so the whole first one is skipped
+        let steep = (y1 - y0).abs() > (x1 - x0).abs();
+        let (start, end) = match steep {
+            true => (y0.round() as i32, y1.round() as i32),
+            false => (x0.round() as i32, x1.round() as i32),
+        };
+        // unless the beam is standing (nearly) still, in which case all of its energy goes into
+        // the one spot
+        if start == end {
+            self.spot(x1, y1, intensity);
+            return
+        }
+        for ((x, y), coverage) in line_drawing::XiaolinWu::<f32, i32>::new((x0, y0), (x1, y1)) {
+            if (if steep { y } else { x }) != start {
+                self.point(x, y, intensity * coverage);
             }
         }
-        self.previous_pos1 = (x1, y1);
     }
 }
 
+/// The range the persistence can be adjusted in.
+const PERSISTENCE_RANGE: (Duration, Duration) = (Duration::from_millis(10), Duration::from_secs(10));
+/// How much the persistence changes with every key press.
+const PERSISTENCE_STEP: f32 = 1.25;
+/// How much the gamma changes with every key press.
+const GAMMA_STEP: f32 = 1.1;
+/// How much the gain of both axes changes with every key press.
+const GAIN_STEP: f32 = 1.25;
+/// How much the timebase changes with every key press.
+const TIMEBASE_STEP: f32 = 1.25;
+/// How much the beam's intensity changes with every key press.
+const INTENSITY_STEP: f32 = 1.25;
+/// How much the gain of an axis changes with every key press.
+const AXIS_GAIN_STEP: f32 = 1.05;
+/// How many degrees the picture turns with every key press.
+const ROTATION_STEP: f32 = 1.0;
+/// How far the picture moves with every key press, where 1 is from the center to the edge.
+const OFFSET_STEP: f32 = 0.02;
+/// How long the indicator bar stays on screen after a setting is changed.
+const INDICATOR_DURATION: Duration = Duration::from_millis(1500);
+
+/// Returns the resolution of a canvas that follows a window of the given size, in physical
+/// pixels. That's the window's size, unless a render scale is set, in which case it's that many
+/// pixels for every logical pixel of the window.
+fn canvas_resolution(size: PhysicalSize<u32>, scale_factor: f64, render_scale: Option<f32>) -> (u32, u32) {
+    let scale = render_scale.map_or(1.0, |render_scale| render_scale as f64 / scale_factor);
+    let scaled = |length: u32| ((length as f64 * scale).round() as u32).max(1);
+    (scaled(size.width), scaled(size.height))
+}
+
+/// Returns the size of the part of a frame the scope takes up, which is all of it unless the
+/// scope is kept square.
+fn scope_size(width: u32, height: u32, square: bool) -> (u32, u32) {
+    match square {
+        true => (width.min(height), width.min(height)),
+        false => (width, height),
+    }
+}
+
+/// Returns the columns and rows of a frame the scope takes up. The composite layout leaves room
+/// for the level meters and their scales on either side, and for the correlation meter below.
+fn scope_area(
+    width: u32,
+    height: u32,
+    square: bool,
+    composite: bool,
+    supersampling: u32,
+) -> (Range<u32>, Range<u32>) {
+    let (side, below) = match composite {
+        true => {
+            let ss = supersampling;
+            let scale_width = text::width("-48") * text_scale(width, height) + ss * 4;
+            let side = (Plot::LEVEL_MARGIN * 2 + Plot::LEVEL_WIDTH) * ss + scale_width;
+            let below = Plot::METER_BOTTOM * ss * 2 + text::GLYPH_HEIGHT * text_scale(width, height);
+            (side.min(width / 4), below.min(height / 4))
+        },
+        false => (0, 0),
+    };
+    let (scope_width, scope_height) = scope_size(width - side * 2, height - below, square);
+    let (left, top) = ((width - scope_width) / 2, (height - below - scope_height) / 2);
+    (left..left + scope_width, top..top + scope_height)
+}
+
+/// Returns the columns and rows of a pane of the dashboard its scope takes up.
+fn pane_scope((xs, ys): &(Range<u32>, Range<u32>), square: bool) -> (Range<u32>, Range<u32>) {
+    let (width, height) = scope_size(xs.len() as u32, ys.len() as u32, square);
+    let left = xs.start + (xs.len() as u32 - width) / 2;
+    let top = ys.start + (ys.len() as u32 - height) / 2;
+    (left..left + width, top..top + height)
+}
+
+/// Splits a frame into a grid of panes, going across and then down. There are as many columns
+/// as rows, or one more.
+fn pane_areas(width: u32, height: u32, count: usize) -> Vec<(Range<u32>, Range<u32>)> {
+    let columns = (count as f32).sqrt().ceil().max(1.0) as u32;
+    let rows = (count as u32).div_ceil(columns).max(1);
+    (0..count as u32)
+        .map(|i| {
+            let (column, row) = (i % columns, i / columns);
+            let xs = width * column / columns..width * (column + 1) / columns;
+            let ys = height * row / rows..height * (row + 1) / rows;
+            (xs, ys)
+        })
+        .collect()
+}
+
+/// Returns how many times bigger than the font text is drawn in a frame, so that it stays
+/// readable in big windows.
+fn text_scale(width: u32, height: u32) -> u32 {
+    (width.min(height) / 640).max(1)
+}
+
+/// Reads the light back from the GPU into the plot if it's kept there, for the CPU to go on
+/// from it.
+fn take_light_back(
+    renderer: Option<&gpu::Renderer>,
+    light_on_gpu: &mut bool,
+    pixels: &Pixels<Window>,
+    plot: &mut [f32],
+) {
+    if let Some(renderer) = renderer.filter(|_| *light_on_gpu) {
+        if let Err(error) = renderer.download(pixels.device(), pixels.queue(), plot) {
+            eprintln!("{:#}", error);
+        }
+    }
+    *light_on_gpu = false;
+}
+
+/// Returns the path of a new file in the given directory (or the current directory), named
+/// after the current time in UTC.
+fn timestamped_path(directory: Option<&Path>, extension: &str) -> PathBuf {
+    let seconds = SystemTime::now()
+        .duration_since(SystemTime::UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs();
+    let (days, time) = (seconds / 86400, seconds % 86400);
+    // converts days since 1970-01-01 to a date, see http://howardhinnant.github.io/date_algorithms.html
+    let days = days + 719468;
+    let (era, day_of_era) = (days / 146097, days % 146097);
+    let year_of_era = (day_of_era - day_of_era / 1460 + day_of_era / 36524 - day_of_era / 146096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let shifted_month = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * shifted_month + 2) / 5 + 1;
+    let month = if shifted_month < 10 { shifted_month + 3 } else { shifted_month - 9 };
+    let year = era * 400 + year_of_era + (month <= 2) as u64;
+    directory.unwrap_or_else(|| Path::new(".")).join(format!(
+        "vectorscope-{:04}{:02}{:02}-{:02}{:02}{:02}.{}",
+        year,
+        month,
+        day,
+        time / 3600,
+        time / 60 % 60,
+        time % 60,
+        extension,
+    ))
+}
+
 fn main() -> anyhow::Result<()> {
+    let mut options = Options::from_env()?;
+    if options.command == Command::ListDevices {
+        return audio::list_devices();
+    }
+
+    // the session manager decides where the options are kept and what the JACK client is
+    // called, so it's joined before the audio is opened
+    let mut session = Session::join(&mut options)?;
+    let mut state = AudioState::new(&options)?;
+
     let event_loop = EventLoop::new();
     let window = WindowBuilder::new()
         .with_title("vectorscope")
         .with_inner_size(LogicalSize::new(800, 800))
         .build(&event_loop)?;
 
-    let window_size = window.inner_size();
+    let mut window_size = window.inner_size();
     let surface_texture = SurfaceTexture::new(window_size.width, window_size.height, &window);
     let mut pixels = Pixels::new(window_size.width, window_size.height, surface_texture)?;
 
-    let mut state = AudioState::new()?;
-    let mut plot = Vec::new();
-    plot.resize((window_size.width * window_size.height) as usize, 0);
+    let layers = options.phosphor.layers();
+    let mut theme = options.theme;
+    let gradient = options.palette.as_deref().map(Gradient::read).transpose()?;
+    if theme == Theme::Custom && gradient.is_none() {
+        bail!("the custom theme needs a palette file, given with --palette");
+    }
+    let mut palette = Palette::new(theme, &layers, gradient.as_ref());
+    let overlay = match &options.overlay {
+        Some(path) => {
+            let opacity = options.overlay_opacity.unwrap_or(1.0);
+            let scale = options.overlay_scale.unwrap_or(1.0);
+            Some(Overlay::read(path, opacity, scale, options.overlay_offset)?)
+        },
+        None => None,
+    };
+    let mut show_overlay = true;
+    let supersampling = options.supersampling.unwrap_or(1);
+    let mut scale_factor = window.scale_factor();
+    let resolution = options.resolution
+        .unwrap_or_else(|| canvas_resolution(window_size, scale_factor, options.render_scale));
+    let mut canvas = Canvas::new(resolution, supersampling);
+    let mut plot = vec![0.0; (canvas.width * canvas.height) as usize * layers.len()];
+    let mut tint = vec![0.0; (canvas.width * canvas.height) as usize * 4];
+    let mut density = vec![0.0; (canvas.width * canvas.height) as usize];
+    // the snapshot of the trace to compare against, if one was taken
+    let mut reference: Option<Vec<f32>> = None;
+    // the beam and the bloom are made as much bigger as the canvas is, so that they look the
+    // same on screen
+    let beam_width = options.beam_width.unwrap_or(1.0) * supersampling as f32;
+    let beam = Beam::new(beam_width);
+    let bloom = options.bloom.map(|intensity| Bloom {
+        intensity,
+        radius: options.bloom_radius.unwrap_or(Bloom::DEFAULT_RADIUS) * supersampling,
+    });
+    // about the same as fading out by 15% every frame at 60 Hz
+    let mut persistence = options.persistence.unwrap_or(Duration::from_millis(70));
+    let mut last_redraw = Instant::now();
+    let mut long_exposure = options.long_exposure;
+    let mut heatmap = options.heatmap;
+    let mut colormap = heatmap.map(Colormap::ramp);
+    let heatmap_window = options.heatmap_window.unwrap_or(Duration::from_secs(2));
+    let mut gamma = options.gamma.unwrap_or(1.0);
+    let mut auto_exposure = options.auto_exposure;
+    let mut graticule = GraticuleSettings {
+        style: options.graticule,
+        divisions: options.divisions.unwrap_or(5),
+        color: options.graticule_color,
+        opacity: options.graticule_opacity.unwrap_or(1.0),
+        emphasize_center: options.emphasize_center,
+        labels: options.graticule_labels,
+    };
+    let mut show_hud = options.hud;
+    let mut show_crosshair = options.crosshair;
+    let mut amplitude_scale = options.amplitude_scale;
+    let mut modifiers = ModifiersState::empty();
+    let mut view = View::UNIT;
+    // where the cursor is, where 1 is the edge of the scope, and whether the picture is being
+    // dragged around
+    let mut cursor = (0.0, 0.0);
+    let mut dragging = false;
+    // the pane of the dashboard the cursor is in, which stays the same while dragging
+    let mut cursor_pane = 0;
+    // the measurement markers, where 1 is the edge of the unzoomed picture, and which of them is
+    // placed next
+    let mut markers = [None; 2];
+    let mut next_marker = 0;
+    let mut crt = Crt::new(window_size.width, window_size.height);
+    let mut crt_enabled = options.crt;
+    // the renderer that draws the trace on the GPU, if it's on, and whether the light is kept on
+    // the GPU rather than in the plot
+    let shares = [layers[0].share, layers[1].share];
+    let new_renderer = move |pixels: &Pixels<Window>, canvas: &Canvas, palette: &Palette| gpu::Renderer::new(
+        pixels.device(),
+        pixels.queue(),
+        (canvas.width, canvas.height),
+        beam_width,
+        shares,
+        palette,
+    );
+    let mut renderer = match options.gpu {
+        true => Some(new_renderer(&pixels, &canvas, &palette)),
+        false => None,
+    };
+    let mut light_on_gpu = false;
+    let mut gpu_frame = gpu::Frame::default();
+    // the picture is saved once it's been drawn on the CPU, which has the whole of it
+    let mut take_screenshot = false;
+    let mut square = options.square;
+    // the indicator's value and label, and when it was last changed
+    let mut indicator: Option<(f32, String, Instant)> = None;
 
     event_loop.run(move |event, _, control_flow| {
         *control_flow = ControlFlow::Poll;
 
         match event {
             Event::WindowEvent { event, .. } => {
+                // moving to a screen with another scale factor resizes the window too, and it's
+                // drawn at its size in physical pixels either way
+                let new_size = match &event {
+                    WindowEvent::Resized(size) => Some(*size),
+                    WindowEvent::ScaleFactorChanged { scale_factor: new_scale_factor, new_inner_size } => {
+                        scale_factor = *new_scale_factor;
+                        Some(**new_inner_size)
+                    },
+                    _ => None,
+                };
+                if let Some(size) = new_size {
+                    // minimizing reports a zero size on some platforms, and the surface
+                    // cannot be resized to that
+                    if size.width > 0 && size.height > 0 {
+                        // the pixel buffer can't be resized, only the surface it's shown on,
+                        // so a new one is made to fit the window
+                        let surface_texture = SurfaceTexture::new(size.width, size.height, &window);
+                        match Pixels::new(size.width, size.height, surface_texture) {
+                            Ok(new_pixels) => {
+                                // the light goes with the old pixel buffer's device, so it's
+                                // read back first
+                                take_light_back(renderer.as_ref(), &mut light_on_gpu, &pixels, &mut plot);
+                                pixels = new_pixels;
+                                window_size = size;
+                                crt = Crt::new(size.width, size.height);
+                                // the canvas follows the window unless it has a resolution
+                                // of its own, and what's been drawn is scaled along with it
+                                if options.resolution.is_none() {
+                                    let old_size = (canvas.width, canvas.height);
+                                    let render_scale = options.render_scale;
+                                    let resolution = canvas_resolution(size, scale_factor, render_scale);
+                                    canvas = Canvas::new(resolution, canvas.supersampling);
+                                    let new_size = (canvas.width, canvas.height);
+                                    plot = rescale(&plot, old_size, new_size, 1);
+                                    tint = rescale(&tint, old_size, new_size, 4);
+                                    density = rescale(&density, old_size, new_size, 1);
+                                    if let Some(snapshot) = &mut reference {
+                                        *snapshot = rescale(snapshot, old_size, new_size, 1);
+                                    }
+                                }
+                                if renderer.is_some() {
+                                    renderer = Some(new_renderer(&pixels, &canvas, &palette));
+                                }
+                            },
+                            Err(error) => {
+                                eprintln!("cannot resize the picture: {}", error);
+                                pixels.resize(size.width, size.height);
+                            },
+                        }
+                    }
+                    // macOS blocks the event loop while the window is being resized, so
+                    // MainEventsCleared doesn't arrive until the user lets go
+                    window.request_redraw();
+                }
                 match event {
                     WindowEvent::CloseRequested =>
                         *control_flow = ControlFlow::Exit,
-                    WindowEvent::Resized { .. } => {
-                        let window_size = window.inner_size();
-                        pixels.resize(window_size.width, window_size.height);
+                    WindowEvent::KeyboardInput {
+                        input: KeyboardInput {
+                            state: ElementState::Pressed,
+                            virtual_keycode: Some(key),
+                            ..
+                        },
+                        ..
+                    } => match key {
+                        VirtualKeyCode::R => state.toggle_recording(),
+                        VirtualKeyCode::P => state.cycle_path_mode(),
+                        VirtualKeyCode::H => state.cycle_hue_mode(),
+                        VirtualKeyCode::N => state.toggle_agc(),
+                        VirtualKeyCode::Y => state.cycle_display_mode(),
+                        VirtualKeyCode::PageUp | VirtualKeyCode::PageDown => {
+                            let factor = match key {
+                                VirtualKeyCode::PageUp => TIMEBASE_STEP,
+                                _ => 1.0 / TIMEBASE_STEP,
+                            };
+                            let timebase = state.scale_timebase(factor);
+                            let (min, max) = Options::TIMEBASE_RANGE;
+                            let (min, max) = (min.as_secs_f32(), max.as_secs_f32());
+                            let value = (timebase.as_secs_f32() / min).ln() / (max / min).ln();
+                            let label = format!("timebase {:.1} ms", timebase.as_secs_f32() * 1000.0);
+                            indicator = Some((value, label, Instant::now()));
+                        },
+                        VirtualKeyCode::E => state.toggle_envelope(),
+                        VirtualKeyCode::J => state.toggle_correlation(),
+                        VirtualKeyCode::W => state.toggle_width(),
+                        VirtualKeyCode::B => state.toggle_levels(),
+                        VirtualKeyCode::U => state.toggle_loudness(),
+                        VirtualKeyCode::Z => state.toggle_balance(),
+                        VirtualKeyCode::Left
+                        | VirtualKeyCode::Right
+                        | VirtualKeyCode::Up
+                        | VirtualKeyCode::Down => {
+                            // left and down shrink or move the picture down, right and up grow it
+                            // or move it up
+                            let direction = match key {
+                                VirtualKeyCode::Left | VirtualKeyCode::Down => -1.0,
+                                _ => 1.0,
+                            };
+                            let horizontal = matches!(key, VirtualKeyCode::Left | VirtualKeyCode::Right);
+                            match modifiers.shift() {
+                                // left turns the picture counterclockwise, the same way the key
+                                // points at the top
+                                _ if modifiers.ctrl() => if horizontal {
+                                    state.rotate(-ROTATION_STEP * direction);
+                                },
+                                true => {
+                                    let factor = AXIS_GAIN_STEP.powf(direction);
+                                    match horizontal {
+                                        true => state.scale_gain(factor, 1.0),
+                                        false => state.scale_gain(1.0, factor),
+                                    }
+                                },
+                                false => {
+                                    let step = OFFSET_STEP * direction;
+                                    match horizontal {
+                                        true => state.nudge_offset(step, 0.0),
+                                        false => state.nudge_offset(0.0, step),
+                                    }
+                                },
+                            }
+                        },
+                        VirtualKeyCode::Key0 => {
+                            view = View::UNIT;
+                            eprintln!("zoom reset");
+                        },
+                        VirtualKeyCode::M => state.flip(Flip::Goniometer),
+                        VirtualKeyCode::X => state.flip(Flip::Swap),
+                        VirtualKeyCode::Key1 => state.flip(Flip::InvertX),
+                        VirtualKeyCode::Key2 => state.flip(Flip::InvertY),
+                        VirtualKeyCode::Key3 => state.flip(Flip::MirrorHorizontally),
+                        VirtualKeyCode::Key4 => state.flip(Flip::MirrorVertically),
+                        VirtualKeyCode::Key5 => state.toggle_mid_side(),
+                        VirtualKeyCode::L => {
+                            long_exposure = !long_exposure;
+                            if long_exposure {
+                                // the exposure starts from a dark screen, so whatever light the
+                                // GPU has is left behind
+                                plot.iter_mut().for_each(|intensity| *intensity = 0.0);
+                                light_on_gpu = false;
+                                eprintln!("long exposure started");
+                            } else {
+                                eprintln!("long exposure stopped");
+                            }
+                        },
+                        VirtualKeyCode::F => {
+                            reference = match reference.take() {
+                                Some(_) => {
+                                    eprintln!("reference trace cleared");
+                                    None
+                                },
+                                None => {
+                                    take_light_back(renderer.as_ref(), &mut light_on_gpu, &pixels, &mut plot);
+                                    let frame_size = (canvas.width * canvas.height) as usize;
+                                    let mut snapshot = vec![0.0; frame_size];
+                                    for layer in plot.chunks_exact(frame_size) {
+                                        snapshot.iter_mut().zip(layer).for_each(|(sum, light)| *sum += light);
+                                    }
+                                    eprintln!("reference trace saved");
+                                    Some(snapshot)
+                                },
+                            };
+                        },
+                        VirtualKeyCode::V => {
+                            // off, and then every colormap in turn
+                            heatmap = match heatmap {
+                                None => Some(Colormap::ALL[0]),
+                                Some(current) => {
+                                    let index = Colormap::ALL.iter().position(|&other| other == current);
+                                    Colormap::ALL.get(index.unwrap_or(0) + 1).copied()
+                                },
+                            };
+                            colormap = heatmap.map(Colormap::ramp);
+                            eprintln!("heatmap: {}", heatmap.map_or("off", Colormap::name));
+                        },
+                        VirtualKeyCode::T => {
+                            let index = Theme::ALL.iter().position(|&other| other == theme).unwrap_or(0);
+                            theme = Theme::ALL[(index + 1) % Theme::ALL.len()];
+                            // the custom theme is only there if a gradient was given for it
+                            if theme == Theme::Custom && gradient.is_none() {
+                                theme = Theme::ALL[0];
+                            }
+                            palette = Palette::new(theme, &layers, gradient.as_ref());
+                            if let Some(renderer) = &mut renderer {
+                                renderer.set_palette(pixels.queue(), &palette);
+                            }
+                            eprintln!("theme: {}", theme.name());
+                        },
+                        VirtualKeyCode::A => {
+                            auto_exposure = !auto_exposure;
+                            eprintln!("automatic exposure: {}", if auto_exposure { "on" } else { "off" });
+                        },
+                        VirtualKeyCode::G => {
+                            let index = Graticule::ALL.iter()
+                                .position(|&style| style == graticule.style)
+                                .unwrap_or(0);
+                            graticule.style = Graticule::ALL[(index + 1) % Graticule::ALL.len()];
+                            eprintln!("graticule: {}", graticule.style.name());
+                        },
+                        VirtualKeyCode::D => {
+                            amplitude_scale = match amplitude_scale {
+                                AmplitudeScale::Linear => AmplitudeScale::Decibels,
+                                AmplitudeScale::Decibels => AmplitudeScale::Linear,
+                            };
+                            eprintln!("amplitude scale: {}", amplitude_scale.name());
+                        },
+                        VirtualKeyCode::I => show_hud = !show_hud,
+                        VirtualKeyCode::K => show_crosshair = !show_crosshair,
+                        VirtualKeyCode::O => show_overlay = !show_overlay,
+                        VirtualKeyCode::Q => {
+                            square = !square;
+                            eprintln!("square scope: {}", if square { "on" } else { "off" });
+                        },
+                        VirtualKeyCode::C => crt_enabled = !crt_enabled,
+                        VirtualKeyCode::S => take_screenshot = true,
+                        VirtualKeyCode::Comma | VirtualKeyCode::Period => {
+                            let (min, max) = Options::GAMMA_RANGE;
+                            gamma = match key {
+                                VirtualKeyCode::Comma => gamma / GAMMA_STEP,
+                                _ => gamma * GAMMA_STEP,
+                            }.clamp(min, max);
+                            eprintln!("gamma: {:.2}", gamma);
+                            let value = (gamma / min).ln() / (max / min).ln();
+                            indicator = Some((value, format!("gamma {:.2}", gamma), Instant::now()));
+                        },
+                        VirtualKeyCode::Equals
+                        | VirtualKeyCode::Plus
+                        | VirtualKeyCode::NumpadAdd
+                        | VirtualKeyCode::Minus
+                        | VirtualKeyCode::NumpadSubtract => {
+                            let factor = match key {
+                                VirtualKeyCode::Minus | VirtualKeyCode::NumpadSubtract => 1.0 / GAIN_STEP,
+                                _ => GAIN_STEP,
+                            };
+                            let gain = state.scale_display_gain(factor);
+                            let (min, max) = Options::GAIN_RANGE;
+                            let value = (gain / min).ln() / (max / min).ln();
+                            indicator = Some((value, format!("gain {:.2}x", gain), Instant::now()));
+                        },
+                        VirtualKeyCode::Semicolon | VirtualKeyCode::Apostrophe => {
+                            let factor = match key {
+                                VirtualKeyCode::Semicolon => 1.0 / INTENSITY_STEP,
+                                _ => INTENSITY_STEP,
+                            };
+                            let intensity = state.scale_intensity(factor);
+                            let (min, max) = Options::INTENSITY_RANGE;
+                            let value = (intensity / min).ln() / (max / min).ln();
+                            indicator = Some((value, format!("intensity {:.2}x", intensity), Instant::now()));
+                        },
+                        VirtualKeyCode::LBracket | VirtualKeyCode::RBracket => {
+                            persistence = match key {
+                                VirtualKeyCode::LBracket => persistence.div_f32(PERSISTENCE_STEP),
+                                _ => persistence.mul_f32(PERSISTENCE_STEP),
+                            }.clamp(PERSISTENCE_RANGE.0, PERSISTENCE_RANGE.1);
+                            eprintln!("persistence: {} ms", persistence.as_millis());
+                            // the bar is on a logarithmic scale, same as the steps
+                            let min = PERSISTENCE_RANGE.0.as_secs_f32();
+                            let max = PERSISTENCE_RANGE.1.as_secs_f32();
+                            let value = (persistence.as_secs_f32() / min).ln() / (max / min).ln();
+                            let label = format!("persistence {} ms", persistence.as_millis());
+                            indicator = Some((value, label, Instant::now()));
+                        },
+                        _ => (),
+                    },
+                    WindowEvent::ModifiersChanged(new_modifiers) => modifiers = new_modifiers,
+                    WindowEvent::CursorMoved { position, .. } => {
+                        let position = (position.x as f32, position.y as f32);
+                        let (x, y) = canvas.window_to_canvas(window_size.width, window_size.height, position);
+                        let (width, height) = (canvas.width, canvas.height);
+                        let composite = state.display_mode() == DisplayMode::Composite;
+                        let ss = canvas.supersampling;
+                        let (xs, ys) = match state.display_mode() {
+                            // the cursor is relative to the scope of the pane it's over
+                            DisplayMode::Dashboard => {
+                                let areas = pane_areas(width, height, state.pane_count());
+                                let over = areas.iter().position(|(xs, ys)| {
+                                    xs.contains(&(x.max(0.0) as u32)) && ys.contains(&(y.max(0.0) as u32))
+                                });
+                                if let (Some(pane), false) = (over, dragging) {
+                                    cursor_pane = pane;
+                                }
+                                match areas.get(cursor_pane) {
+                                    Some(area) => pane_scope(area, square),
+                                    None => scope_area(width, height, square, false, ss),
+                                }
+                            },
+                            _ => scope_area(width, height, square || composite, composite, ss),
+                        };
+                        let new_cursor = (
+                            (x - (xs.start + xs.end) as f32 / 2.0) / (xs.len() as f32 / 2.0),
+                            ((ys.start + ys.end) as f32 / 2.0 - y) / (ys.len() as f32 / 2.0),
+                        );
+                        if dragging {
+                            view.center.0 -= (new_cursor.0 - cursor.0) / view.zoom;
+                            view.center.1 -= (new_cursor.1 - cursor.1) / view.zoom;
+                        }
+                        cursor = new_cursor;
                     },
+                    WindowEvent::MouseInput { state: button_state, button: MouseButton::Left, .. } => {
+                        dragging = button_state == ElementState::Pressed;
+                    },
+                    WindowEvent::MouseInput { state: ElementState::Pressed, button, .. } => match button {
+                        MouseButton::Right => {
+                            markers[next_marker] = Some(view.unapply(cursor));
+                            next_marker = 1 - next_marker;
+                        },
+                        MouseButton::Middle => {
+                            markers = [None; 2];
+                            next_marker = 0;
+                        },
+                        _ => (),
+                    },
+                    WindowEvent::MouseWheel { delta, .. } => {
+                        let steps = match delta {
+                            MouseScrollDelta::LineDelta(_, lines) => lines,
+                            // touchpads scroll by pixels, and this is about one line's worth
+                            MouseScrollDelta::PixelDelta(position) => position.y as f32 / 40.0,
+                        };
+                        view.zoom_at(cursor, steps);
+                        let (min, max) = View::ZOOM_RANGE;
+                        let value = (view.zoom / min).ln() / (max / min).ln();
+                        indicator = Some((value, format!("zoom {:.1}x", view.zoom), Instant::now()));
+                    },
+                    WindowEvent::DroppedFile(path) => state.play_file(path),
                     _ => (),
                 }
             },
 
             Event::MainEventsCleared => {
+                if let Some(session) = &mut session {
+                    // the session keeps the picture as it's been adjusted, not as it started out
+                    let current_options = || {
+                        let mut options = options.clone();
+                        state.record_calibration(&mut options)?;
+                        options.set_value("persistence", &format!("{}s", persistence.as_secs_f64()))?;
+                        Ok(options)
+                    };
+                    if let Err(error) = session.poll(current_options) {
+                        eprintln!("session manager error: {:#}", error);
+                    }
+                }
                 window.request_redraw();
             },
 
             Event::RedrawRequested(_) => {
+                // the trace fades out over time rather than by frame, so that it looks the same at
+                // any refresh rate
+                let elapsed = last_redraw.elapsed();
+                last_redraw = Instant::now();
+                let composite = state.display_mode() == DisplayMode::Composite;
+                // the CPU takes over whenever the picture needs something the GPU doesn't draw,
+                // and the light goes wherever the trace is drawn
+                let cpu_only = long_exposure
+                    || auto_exposure
+                    || bloom.is_some()
+                    || colormap.is_some()
+                    || reference.is_some()
+                    || crt_enabled
+                    || take_screenshot
+                    || state.colors_trace();
+                let on_gpu = renderer.is_some() && !cpu_only;
+                if !on_gpu {
+                    take_light_back(renderer.as_ref(), &mut light_on_gpu, &pixels, &mut plot);
+                } else if let Some(renderer) = renderer.as_ref().filter(|_| !light_on_gpu) {
+                    renderer.upload(pixels.queue(), &plot);
+                    light_on_gpu = true;
+                }
                 state.render(Plot {
-                    width: window_size.width,
-                    height: window_size.height,
-                    pixels: pixels.get_frame(),
+                    width: canvas.width,
+                    height: canvas.height,
+                    pixels: &mut canvas.frame,
                     plot: &mut plot,
-                    previous_pos1: (0, 0),
+                    tint: &mut tint,
+                    gpu: if on_gpu { Some(&mut gpu_frame) } else { None },
+                    reference: reference.as_deref(),
+                    density: &mut density,
+                    density_decay: 0.5f32.powf(elapsed.as_secs_f32() / heatmap_window.as_secs_f32()),
+                    colormap: colormap.as_ref(),
+                    color: None,
+                    beam: &beam,
+                    layers: &layers,
+                    palette: &palette,
+                    image: None,
+                    polar: false,
+                    speakers: Vec::new(),
+                    pane: None,
+                    panes: Vec::new(),
+                    overlay: overlay.as_ref().filter(|_| show_overlay),
+                    no_input: false,
+                    data_lost: false,
+                    frozen: false,
+                    decay: match long_exposure {
+                        true => 1.0,
+                        false => 0.5f32.powf(elapsed.as_secs_f32() / persistence.as_secs_f32()),
+                    },
+                    exposure: match (long_exposure, auto_exposure) {
+                        (true, _) => Exposure::Peak,
+                        (false, true) => Exposure::Histogram,
+                        (false, false) => Exposure::Fixed,
+                    },
+                    amplitude_scale,
+                    db_range: options.db_range.unwrap_or(60.0),
+                    graticule,
+                    view,
+                    square: square || composite,
+                    composite,
+                    supersampling: canvas.supersampling,
+                    transfer: options.transfer,
+                    gamma,
+                    indicator: indicator.as_ref()
+                        .filter(|(_, _, changed)| changed.elapsed() < INDICATOR_DURATION)
+                        .map(|(value, label, _)| (*value, label.clone())),
+                    bloom,
+                    hud: if show_hud { Some(Vec::new()) } else { None },
+                    crosshair: show_crosshair,
+                    cursor: None,
+                    envelope: Vec::new(),
+                    correlation: None,
+                    stereo_width: None,
+                    levels: None,
+                    balance: None,
+                    mid_side: None,
+                    markers,
+                    measurement: None,
                 });
-                match pixels.render() {
-                    Err(x) => eprintln!("{}", x),
-                    _ => (),
+                canvas.present(pixels.get_frame(), window_size.width, window_size.height);
+                if crt_enabled {
+                    crt.apply(pixels.get_frame());
+                }
+                let result = match renderer.as_mut().filter(|_| on_gpu) {
+                    Some(renderer) => {
+                        let placement = canvas.placement(window_size.width, window_size.height);
+                        pixels.render_with(|encoder, target, context| {
+                            renderer.render(encoder, target, context, &mut gpu_frame, placement)
+                        })
+                    },
+                    None => pixels.render(),
+                };
+                if let Err(x) = result {
+                    eprintln!("{}", x);
+                }
+                if take_screenshot {
+                    take_screenshot = false;
+                    let path = timestamped_path(options.record_dir.as_deref(), "png");
+                    let (width, height) = (window_size.width, window_size.height);
+                    match png::write(&path, width, height, pixels.get_frame()) {
+                        Ok(()) => eprintln!("saved the picture to {}", path.display()),
+                        Err(error) => eprintln!("cannot save the picture: {:#}", error),
+                    }
                 }
             },
 
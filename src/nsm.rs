@@ -0,0 +1,222 @@
+use std::{
+    io::ErrorKind,
+    net::{SocketAddr, ToSocketAddrs, UdpSocket},
+    path::PathBuf,
+};
+
+use anyhow::{anyhow, bail, Context};
+
+use crate::cli::Options;
+
+/// A session managed by the Non/New Session Manager (NSM). The session manager talks to its
+/// clients over OSC, and tells them where to keep their state.
+pub struct Session {
+    socket: UdpSocket,
+    /// The file the options are saved to when the session is saved.
+    config_path: PathBuf,
+}
+
+/// An argument of an OSC message.
+enum Argument {
+    Int(i32),
+    Float(f32),
+    String(String),
+}
+
+impl Session {
+    /// The name the scope announces itself with.
+    const APPLICATION_NAME: &'static str = "vectorscope";
+    /// The version of the NSM API that's implemented.
+    const API_VERSION: (i32, i32) = (1, 2);
+
+    /// Joins the session manager given in the `NSM_URL` environment variable, if any, and waits
+    /// for it to open the client. The options saved in the session are loaded on top of the
+    /// given options.
+    pub fn join(options: &mut Options) -> anyhow::Result<Option<Self>> {
+        let url = match std::env::var("NSM_URL") {
+            Ok(url) => url,
+            Err(_) => return Ok(None),
+        };
+        let address = Self::parse_url(&url)?;
+        let socket = UdpSocket::bind(match address {
+            SocketAddr::V4(_) => "0.0.0.0:0",
+            SocketAddr::V6(_) => "[::]:0",
+        })?;
+        socket.connect(address)
+            .with_context(|| format!("cannot connect to the session manager at {}", url))?;
+        let mut session = Session {
+            socket,
+            config_path: PathBuf::new(),
+        };
+
+        let executable = std::env::args().next().unwrap_or_else(|| Self::APPLICATION_NAME.to_owned());
+        session.send("/nsm/server/announce", &[
+            Argument::String(Self::APPLICATION_NAME.to_owned()),
+            // no optional capabilities are supported
+            Argument::String(String::new()),
+            Argument::String(executable),
+            Argument::Int(Self::API_VERSION.0),
+            Argument::Int(Self::API_VERSION.1),
+            Argument::Int(std::process::id() as i32),
+        ])?;
+        loop {
+            let (path, arguments) = session.receive()?;
+            match (path.as_str(), arguments.as_slice()) {
+                ("/reply", [Argument::String(reply_to), _, Argument::String(manager), ..])
+                    if reply_to == "/nsm/server/announce" =>
+                {
+                    eprintln!("joined the session managed by {}", manager);
+                },
+                ("/error", [Argument::String(reply_to), _, Argument::String(message), ..])
+                    if reply_to == "/nsm/server/announce" =>
+                {
+                    bail!("the session manager refused to let the scope join: {}", message);
+                },
+                ("/nsm/client/open", [Argument::String(path), _, Argument::String(client_id), ..]) => {
+                    let result = session.open(options, path.into(), client_id);
+                    session.respond("/nsm/client/open", &result, "opened")?;
+                    result?;
+                    break
+                },
+                _ => (),
+            }
+        }
+        session.socket.set_nonblocking(true)?;
+        Ok(Some(session))
+    }
+
+    /// Handles messages from the session manager that arrived since the last call. The options
+    /// to save are only put together when the session manager asks for them.
+    pub fn poll(&mut self, options: impl Fn() -> anyhow::Result<Options>) -> anyhow::Result<()> {
+        loop {
+            let (path, _) = match self.receive() {
+                Ok(message) => message,
+                Err(error) => match error.downcast_ref::<std::io::Error>() {
+                    Some(error) if error.kind() == ErrorKind::WouldBlock => return Ok(()),
+                    _ => return Err(error),
+                },
+            };
+            if path == "/nsm/client/save" {
+                let result = options().and_then(|options| options.save_config(&self.config_path));
+                self.respond("/nsm/client/save", &result, "saved")?;
+                if let Err(error) = result {
+                    eprintln!("{:#}", error);
+                }
+            }
+        }
+    }
+
+    /// Loads the options saved in the session, or saves the current options if the session
+    /// doesn't have any yet. The JACK client is named after the client ID, so that the session
+    /// manager can restore its connections.
+    fn open(&mut self, options: &mut Options, path: PathBuf, client_id: &str) -> anyhow::Result<()> {
+        std::fs::create_dir_all(&path)
+            .with_context(|| format!("cannot create the session directory {}", path.display()))?;
+        self.config_path = path.join("config");
+        if self.config_path.is_file() {
+            options.read_config(&self.config_path)?;
+        } else {
+            options.save_config(&self.config_path)?;
+        }
+        if options.jack_name.is_none() {
+            options.jack_name = Some(client_id.to_owned());
+        }
+        Ok(())
+    }
+
+    /// Replies to a request from the session manager.
+    fn respond(&self, request: &str, result: &anyhow::Result<()>, message: &str) -> anyhow::Result<()> {
+        match result {
+            Ok(()) => self.send("/reply", &[
+                Argument::String(request.to_owned()),
+                Argument::String(message.to_owned()),
+            ]),
+            Err(error) => self.send("/error", &[
+                Argument::String(request.to_owned()),
+                // general error
+                Argument::Int(-1),
+                Argument::String(format!("{:#}", error)),
+            ]),
+        }
+    }
+
+    /// Parses a session manager URL, in the form `osc.udp://host:port/`.
+    fn parse_url(url: &str) -> anyhow::Result<SocketAddr> {
+        let host_and_port = url.strip_prefix("osc.udp://")
+            .map(|rest| rest.trim_end_matches('/'))
+            .ok_or_else(|| anyhow!("unsupported session manager URL: {}", url))?;
+        host_and_port.to_socket_addrs()
+            .with_context(|| format!("invalid session manager URL: {}", url))?
+            .next()
+            .ok_or_else(|| anyhow!("cannot resolve the session manager URL: {}", url))
+    }
+
+    fn send(&self, path: &str, arguments: &[Argument]) -> anyhow::Result<()> {
+        let mut message = Vec::new();
+        write_osc_string(&mut message, path);
+        let type_tags: String = std::iter::once(',')
+            .chain(arguments.iter().map(|argument| match argument {
+                Argument::Int(_) => 'i',
+                Argument::Float(_) => 'f',
+                Argument::String(_) => 's',
+            }))
+            .collect();
+        write_osc_string(&mut message, &type_tags);
+        for argument in arguments {
+            match argument {
+                Argument::Int(int) => message.extend_from_slice(&int.to_be_bytes()),
+                Argument::Float(float) => message.extend_from_slice(&float.to_be_bytes()),
+                Argument::String(string) => write_osc_string(&mut message, string),
+            }
+        }
+        self.socket.send(&message)?;
+        Ok(())
+    }
+
+    /// Receives a message from the session manager. Arguments of types other than int, float and
+    /// string aren't supported.
+    fn receive(&self) -> anyhow::Result<(String, Vec<Argument>)> {
+        let mut buffer = [0; 4096];
+        let length = self.socket.recv(&mut buffer)?;
+        let mut message = &buffer[..length];
+        let path = read_osc_string(&mut message)?;
+        let type_tags = read_osc_string(&mut message)?;
+        let mut arguments = Vec::new();
+        for tag in type_tags.chars().skip(1) {
+            let argument = match tag {
+                'i' => Argument::Int(i32::from_be_bytes(read_osc_word(&mut message)?)),
+                'f' => Argument::Float(f32::from_be_bytes(read_osc_word(&mut message)?)),
+                's' => Argument::String(read_osc_string(&mut message)?),
+                _ => bail!("unsupported OSC argument type in {}: {}", path, tag),
+            };
+            arguments.push(argument);
+        }
+        Ok((path, arguments))
+    }
+}
+
+/// Writes a null-terminated string padded to a multiple of 4 bytes.
+fn write_osc_string(message: &mut Vec<u8>, string: &str) {
+    message.extend_from_slice(string.as_bytes());
+    let padding = 4 - string.len() % 4;
+    message.resize(message.len() + padding, 0);
+}
+
+fn read_osc_string(message: &mut &[u8]) -> anyhow::Result<String> {
+    let length = message.iter()
+        .position(|&byte| byte == 0)
+        .ok_or_else(|| anyhow!("malformed OSC message"))?;
+    let string = String::from_utf8_lossy(&message[..length]).into_owned();
+    let padded_length = (length / 4 + 1) * 4;
+    *message = message.get(padded_length..).unwrap_or_default();
+    Ok(string)
+}
+
+fn read_osc_word(message: &mut &[u8]) -> anyhow::Result<[u8; 4]> {
+    if message.len() < 4 {
+        bail!("malformed OSC message");
+    }
+    let (word, rest) = message.split_at(4);
+    *message = rest;
+    Ok([word[0], word[1], word[2], word[3]])
+}
@@ -0,0 +1,51 @@
+use std::{ops::Range, path::Path};
+
+use crate::png::{self, Image};
+
+/// An image drawn over the graticule, such as the figure a signal should make or a mask of the
+/// area it should stay inside.
+pub struct Overlay {
+    image: Image,
+    /// How much the overlay covers what's under it, from 0 to 1, on top of the image's own alpha.
+    opacity: f32,
+    /// How big the overlay is, where 1 is as big as fits in the scope.
+    scale: f32,
+    /// Where the center of the overlay is, where 1 is from the center of the scope to its edge.
+    offset: (f32, f32),
+}
+
+impl Overlay {
+    /// Reads the overlay's image from a PNG file.
+    pub fn read(path: &Path, opacity: f32, scale: f32, offset: (f32, f32)) -> anyhow::Result<Self> {
+        Ok(Overlay { image: png::read(path)?, opacity, scale, offset })
+    }
+
+    /// Blends the overlay into an RGBA frame of the given width, fitted into the scope with its
+    /// aspect ratio kept.
+    pub fn draw(&self, frame: &mut [u8], width: u32, (xs, ys): (Range<u32>, Range<u32>)) {
+        let (scope_width, scope_height) = (xs.len() as f32, ys.len() as f32);
+        let (image_width, image_height) = (self.image.width as f32, self.image.height as f32);
+        // how many pixels of the frame a pixel of the image takes up
+        let zoom = (scope_width / image_width).min(scope_height / image_height) * self.scale;
+        let center_x = xs.start as f32 + scope_width / 2.0 * (1.0 + self.offset.0);
+        let center_y = ys.start as f32 + scope_height / 2.0 * (1.0 - self.offset.1);
+        let left = center_x - image_width * zoom / 2.0;
+        let top = center_y - image_height * zoom / 2.0;
+        let height = (frame.len() / 4) as u32 / width.max(1);
+        let right = (left + image_width * zoom).ceil().clamp(0.0, width as f32) as u32;
+        let bottom = (top + image_height * zoom).ceil().clamp(0.0, height as f32) as u32;
+        for y in top.max(0.0) as u32..bottom {
+            let source_y = (((y as f32 + 0.5 - top) / zoom) as u32).min(self.image.height - 1);
+            for x in left.max(0.0) as u32..right {
+                let source_x = (((x as f32 + 0.5 - left) / zoom) as u32).min(self.image.width - 1);
+                let source = ((source_x + source_y * self.image.width) * 4) as usize;
+                let color = &self.image.rgba[source..source + 4];
+                let alpha = color[3] as f32 / 255.0 * self.opacity;
+                let i = ((x + y * width) * 4) as usize;
+                for (value, &overlay) in frame[i..i + 3].iter_mut().zip(&color[..3]) {
+                    *value = (*value as f32 + (overlay as f32 - *value as f32) * alpha).round() as u8;
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,193 @@
+use std::path::Path;
+
+use anyhow::{anyhow, bail, Context};
+
+use crate::{cli::{Colormap, Theme}, phosphor::Layer};
+
+/// The colors everything is drawn in, as worked out from a theme.
+pub struct Palette {
+    pub background: (u8, u8, u8),
+    pub graticule: (u8, u8, u8),
+    /// Whether the trace darkens the background instead of lighting it up, in which case the
+    /// ramps say how much is taken away from every channel.
+    pub dark_trace: bool,
+    /// The color of every layer's light at every brightness, from dark to full brightness.
+    pub ramps: Vec<[(u8, u8, u8); 256]>,
+}
+
+impl Palette {
+    /// Returns the color text is drawn in, which stands out from the background without being
+    /// as bright as the trace.
+    pub fn text(&self) -> (u8, u8, u8) {
+        match self.dark_trace {
+            true => (64, 64, 64),
+            false => (176, 176, 176),
+        }
+    }
+}
+
+/// A user-defined ramp, which goes through a number of colors as the trace gets brighter.
+pub struct Gradient {
+    /// The colors the gradient goes through, each at a position between 0 (dark) and 1 (full
+    /// brightness), sorted by position.
+    stops: Vec<(f32, (u8, u8, u8))>,
+}
+
+impl Palette {
+    /// Works out the colors of a theme. The custom theme needs a gradient, and falls back to
+    /// the phosphor's colors without one.
+    pub fn new(theme: Theme, layers: &[Layer], gradient: Option<&Gradient>) -> Self {
+        // the colors of the light while the beam is on it and of its afterglow
+        let (background, graticule, colors) = match (theme, gradient) {
+            (Theme::Custom, Some(gradient)) => {
+                let ramps = vec![gradient.ramp(); layers.len()];
+                return Palette { background: (0, 0, 0), graticule: (24, 24, 24), dark_trace: false, ramps }
+            },
+            (Theme::Phosphor | Theme::Custom, _) => {
+                let ramps = layers.iter().map(|layer| ramp(layer.color)).collect();
+                return Palette { background: (0, 0, 0), graticule: (24, 24, 24), dark_trace: false, ramps }
+            },
+            (Theme::Green, _) => ((0, 0, 0), (24, 24, 24), [(0.0, 1.0, 0.0), (0.3, 1.0, 0.2)]),
+            (Theme::Amber, _) => ((0, 0, 0), (28, 20, 8), [(1.0, 0.6, 0.0), (1.0, 0.45, 0.0)]),
+            (Theme::White, _) => ((0, 0, 0), (24, 24, 24), [(1.0, 1.0, 1.0), (0.85, 0.9, 1.0)]),
+            (Theme::Blue, _) => ((0, 2, 12), (16, 24, 40), [(0.2, 0.6, 1.0), (0.1, 0.35, 1.0)]),
+            (Theme::Dracula, _) => ((40, 42, 54), (68, 71, 90), [(0.74, 0.58, 0.98), (1.0, 0.47, 0.78)]),
+            // taking away more red and green than blue leaves a dark blue, like ink
+            (Theme::Light, _) => ((250, 248, 240), (216, 216, 216), [(1.0, 0.9, 0.6), (1.0, 0.9, 0.6)]),
+            // the Okabe-Ito colors, which stay apart with any kind of red-green color blindness
+            (Theme::Deuteranopia, _) => ((0, 0, 0), (24, 24, 24), [(0.34, 0.7, 0.91), (0.9, 0.62, 0.0)]),
+            (Theme::Protanopia, _) => ((0, 0, 0), (24, 24, 24), [(0.94, 0.89, 0.26), (0.0, 0.45, 0.7)]),
+            (Theme::HighContrast, _) => ((0, 0, 0), (72, 72, 72), [(1.0, 1.0, 1.0), (1.0, 1.0, 1.0)]),
+        };
+        // the first layer is the one the beam lights up, and any others are afterglow
+        let ramps = (0..layers.len())
+            .map(|i| ramp(colors[i.min(1)]))
+            .collect();
+        Palette { background, graticule, dark_trace: theme == Theme::Light, ramps }
+    }
+}
+
+/// Builds a ramp that goes from black through the given color, whose brightest channel should
+/// be 1, and burns out to white like a real phosphor does.
+fn ramp(color: (f32, f32, f32)) -> [(u8, u8, u8); 256] {
+    let mut colors = [(0, 0, 0); 256];
+    for (i, entry) in colors.iter_mut().enumerate() {
+        *entry = shade(color, i as f32 / 255.0);
+    }
+    colors
+}
+
+/// Parses a color in `#rrggbb` notation.
+pub fn parse_color(color: &str) -> anyhow::Result<(u8, u8, u8)> {
+    let hex = color.strip_prefix('#')
+        .filter(|hex| hex.len() == 6)
+        .and_then(|hex| u32::from_str_radix(hex, 16).ok())
+        .ok_or_else(|| anyhow!("invalid color: {} (expected #rrggbb)", color))?;
+    Ok(((hex >> 16) as u8, (hex >> 8) as u8, hex as u8))
+}
+
+/// Returns the color a pixel lit up in the given color is at a brightness between 0 and 1, the
+/// same as ramps do.
+pub fn shade(color: (f32, f32, f32), brightness: f32) -> (u8, u8, u8) {
+    // a channel gets to full brightness halfway through the ramp if the color is fully made of
+    // it, and the rest of the channels catch up over the second half
+    let n = brightness * 255.0;
+    let channel = |c: f32| (n * 2.0 * c + ((n - 128.0) * 1.5).max(0.0) * (1.0 - c)).min(255.0) as u8;
+    (channel(color.0), channel(color.1), channel(color.2))
+}
+
+/// Returns a fully saturated color of the given hue, in degrees, with the brightest channel at 1.
+pub fn hue(degrees: f32) -> (f32, f32, f32) {
+    let h = degrees.rem_euclid(360.0) / 60.0;
+    let x = 1.0 - (h % 2.0 - 1.0).abs();
+    match h as u32 {
+        0 => (1.0, x, 0.0),
+        1 => (x, 1.0, 0.0),
+        2 => (0.0, 1.0, x),
+        3 => (0.0, x, 1.0),
+        4 => (x, 0.0, 1.0),
+        _ => (1.0, 0.0, x),
+    }
+}
+
+impl Colormap {
+    /// Returns the colors the colormap goes through from the lowest values to the highest.
+    pub fn ramp(self) -> [(u8, u8, u8); 256] {
+        let stops = match self {
+            // samples of the colormaps from matplotlib, which look evenly spaced to the eye
+            Colormap::Viridis => vec![
+                (0.0, (68, 1, 84)),
+                (0.25, (59, 82, 139)),
+                (0.5, (33, 145, 140)),
+                (0.75, (94, 201, 98)),
+                (1.0, (253, 231, 37)),
+            ],
+            Colormap::Inferno => vec![
+                (0.0, (0, 0, 4)),
+                (0.2, (66, 10, 104)),
+                (0.4, (147, 38, 103)),
+                (0.6, (221, 81, 58)),
+                (0.8, (252, 165, 10)),
+                (1.0, (252, 255, 164)),
+            ],
+        };
+        Gradient { stops }.ramp()
+    }
+}
+
+impl Gradient {
+    /// Reads a gradient from a file. Every line is a stop, made of a position and a color in
+    /// `#rrggbb` notation, and lines starting with `#` are comments.
+    pub fn read(path: &Path) -> anyhow::Result<Self> {
+        let text = std::fs::read_to_string(path)
+            .with_context(|| format!("cannot read palette file {}", path.display()))?;
+        let mut stops = Vec::new();
+        for (line_number, line) in text.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue
+            }
+            let stop = Self::parse_stop(line)
+                .with_context(|| format!("{}:{}", path.display(), line_number + 1))?;
+            stops.push(stop);
+        }
+        if stops.is_empty() {
+            bail!("palette file {} does not have any colors", path.display());
+        }
+        stops.sort_by(|(a, _), (b, _)| a.total_cmp(b));
+        Ok(Gradient { stops })
+    }
+
+    fn parse_stop(line: &str) -> anyhow::Result<(f32, (u8, u8, u8))> {
+        let (position, color) = line.split_once(char::is_whitespace)
+            .ok_or_else(|| anyhow!("expected a position and a color, eg. `0.5 #20c040`"))?;
+        let position: f32 = position.parse()
+            .map_err(|_| anyhow!("invalid position: {}", position))?;
+        if !(0.0..=1.0).contains(&position) {
+            bail!("the position must be between 0 and 1");
+        }
+        Ok((position, parse_color(color.trim())?))
+    }
+
+    /// Renders the gradient into a ramp. Before the first stop and after the last one, the ramp
+    /// keeps their colors.
+    fn ramp(&self) -> [(u8, u8, u8); 256] {
+        let mut colors = [(0, 0, 0); 256];
+        for (i, entry) in colors.iter_mut().enumerate() {
+            let position = i as f32 / 255.0;
+            let next = self.stops.iter().position(|&(stop, _)| stop >= position);
+            *entry = match next {
+                None => self.stops[self.stops.len() - 1].1,
+                Some(0) => self.stops[0].1,
+                Some(next) => {
+                    let (start, from) = self.stops[next - 1];
+                    let (end, to) = self.stops[next];
+                    let t = (position - start) / (end - start);
+                    let mix = |a: u8, b: u8| (a as f32 + (b as f32 - a as f32) * t).round() as u8;
+                    (mix(from.0, to.0), mix(from.1, to.1), mix(from.2, to.2))
+                },
+            };
+        }
+        colors
+    }
+}
@@ -0,0 +1,39 @@
+use crate::cli::Phosphor;
+
+/// One of the ways a phosphor gives off light. Phosphors glow brightly while the beam is on them
+/// and keep glowing more faintly afterwards, often in another color, so each is modeled as two
+/// layers that fade out at their own pace.
+pub struct Layer {
+    /// The share of the beam's energy that goes into this layer.
+    pub share: f32,
+    /// How long the layer takes to fade out, relative to the set persistence.
+    pub persistence: f32,
+    /// The color of the layer's light, with the brightest channel at 1.
+    pub color: (f32, f32, f32),
+}
+
+impl Layer {
+    fn new(share: f32, persistence: f32, color: (f32, f32, f32)) -> Self {
+        Layer { share, persistence, color }
+    }
+}
+
+impl Phosphor {
+    /// Returns the layers the phosphor's light is made of.
+    pub fn layers(self) -> [Layer; 2] {
+        match self {
+            Phosphor::P1 => [
+                Layer::new(0.7, 1.0, (0.25, 1.0, 0.2)),
+                Layer::new(0.3, 4.0, (0.2, 1.0, 0.1)),
+            ],
+            Phosphor::P7 => [
+                Layer::new(0.6, 0.5, (0.45, 0.6, 1.0)),
+                Layer::new(0.4, 20.0, (0.8, 1.0, 0.1)),
+            ],
+            Phosphor::P31 => [
+                Layer::new(0.85, 1.0, (0.0, 1.0, 0.0)),
+                Layer::new(0.15, 3.0, (0.3, 1.0, 0.2)),
+            ],
+        }
+    }
+}
@@ -0,0 +1,617 @@
+use std::{fs::File, io::{BufWriter, Write}, path::Path};
+
+use anyhow::{anyhow, bail, Context};
+
+/// The largest amount of data a stored (uncompressed) deflate block can hold.
+const MAX_STORED_BLOCK_SIZE: usize = 65535;
+/// The most pixels an image that's read can have, so that a broken file can't take up all the
+/// memory there is.
+const MAX_PIXELS: u64 = 8192 * 8192;
+
+/// A decoded image.
+pub struct Image {
+    pub width: u32,
+    pub height: u32,
+    /// The image's pixels, in RGBA with straight alpha.
+    pub rgba: Vec<u8>,
+}
+
+/// Reads a PNG file. Any color type and bit depth is supported, but not interlacing, and
+/// 16-bit channels are cut down to 8 bits.
+pub fn read(path: &Path) -> anyhow::Result<Image> {
+    let data = std::fs::read(path).with_context(|| format!("cannot read {}", path.display()))?;
+    decode(&data).with_context(|| format!("cannot decode {}", path.display()))
+}
+
+fn decode(mut data: &[u8]) -> anyhow::Result<Image> {
+    data = data.strip_prefix(b"\x89PNG\r\n\x1a\n").ok_or_else(|| anyhow!("not a PNG file"))?;
+    let mut header = None;
+    let mut palette: &[u8] = &[];
+    let mut transparency: &[u8] = &[];
+    let mut compressed = Vec::new();
+    loop {
+        let length = data.get(..4)
+            .map(|length| be_u32(length) as usize)
+            .ok_or_else(|| anyhow!("the file ends before the image does"))?;
+        let chunk = data.get(4..8 + length).ok_or_else(|| anyhow!("the file ends in the middle of a chunk"))?;
+        let (kind, contents) = chunk.split_at(4);
+        // the checksum is skipped
+        data = data.get(12 + length..).unwrap_or(&[]);
+        match kind {
+            b"IHDR" if contents.len() == 13 => header = Some(contents),
+            b"PLTE" => palette = contents,
+            b"tRNS" => transparency = contents,
+            b"IDAT" => compressed.extend_from_slice(contents),
+            b"IEND" => break,
+            // chunks that are needed to show the image right have an uppercase first letter
+            _ if kind[0].is_ascii_uppercase() => {
+                bail!("unsupported chunk {}", String::from_utf8_lossy(kind));
+            },
+            _ => (),
+        }
+    }
+
+    let header = header.ok_or_else(|| anyhow!("the image header is missing"))?;
+    let width = be_u32(&header[0..4]);
+    let height = be_u32(&header[4..8]);
+    let (depth, color_type, interlace) = (header[8], header[9], header[12]);
+    if width == 0 || height == 0 || width as u64 * height as u64 > MAX_PIXELS {
+        bail!("unsupported image size {}x{}", width, height);
+    }
+    if interlace != 0 {
+        bail!("interlaced images are not supported");
+    }
+    let channels = match (color_type, depth) {
+        (0, 1 | 2 | 4 | 8 | 16) => 1,
+        (3, 1 | 2 | 4 | 8) => 1,
+        (4, 8 | 16) => 2,
+        (2, 8 | 16) => 3,
+        (6, 8 | 16) => 4,
+        _ => bail!("unsupported color type {} with bit depth {}", color_type, depth),
+    };
+    let bits_per_pixel = channels * depth as usize;
+    let row_size = (width as usize * bits_per_pixel).div_ceil(8);
+    let pixel_size = bits_per_pixel.div_ceil(8);
+    // there's no need to decompress more than the image holds, however much more the data says
+    let size = (row_size + 1) * height as usize;
+    let data = unfilter(&inflate(&compressed, size)?, row_size, height as usize, pixel_size)?;
+
+    // the samples of every pixel, with 16-bit ones kept whole to compare them with the
+    // transparent color
+    let mut rgba = Vec::with_capacity(width as usize * height as usize * 4);
+    for row in data.chunks_exact(row_size) {
+        let sample = |i: usize| -> u16 {
+            match depth {
+                16 => u16::from_be_bytes([row[i * 2], row[i * 2 + 1]]),
+                8 => row[i] as u16,
+                _ => {
+                    let bit = i * depth as usize;
+                    let shift = 8 - depth as usize - bit % 8;
+                    (row[bit / 8] as u16 >> shift) & ((1 << depth) - 1)
+                },
+            }
+        };
+        // samples are scaled to 8 bits, except for palette indices
+        let scale = |value: u16| -> u8 {
+            match depth {
+                16 => (value >> 8) as u8,
+                _ => (value as u32 * 255 / ((1 << depth) - 1)) as u8,
+            }
+        };
+        let key = |i: usize| {
+            transparency.get(i * 2..i * 2 + 2).map(|key| u16::from_be_bytes([key[0], key[1]]))
+        };
+        for x in 0..width as usize {
+            let pixel = match color_type {
+                0 => {
+                    let gray = sample(x);
+                    let alpha = if key(0) == Some(gray) { 0 } else { 255 };
+                    [scale(gray), scale(gray), scale(gray), alpha]
+                },
+                2 => {
+                    let (r, g, b) = (sample(x * 3), sample(x * 3 + 1), sample(x * 3 + 2));
+                    let transparent = (key(0), key(1), key(2)) == (Some(r), Some(g), Some(b));
+                    [scale(r), scale(g), scale(b), if transparent { 0 } else { 255 }]
+                },
+                3 => {
+                    let index = sample(x) as usize;
+                    let color = palette.get(index * 3..index * 3 + 3)
+                        .ok_or_else(|| anyhow!("color {} is not in the palette", index))?;
+                    [color[0], color[1], color[2], transparency.get(index).copied().unwrap_or(255)]
+                },
+                4 => {
+                    let (gray, alpha) = (scale(sample(x * 2)), scale(sample(x * 2 + 1)));
+                    [gray, gray, gray, alpha]
+                },
+                _ => {
+                    let channel = |i: usize| scale(sample(x * 4 + i));
+                    [channel(0), channel(1), channel(2), channel(3)]
+                },
+            };
+            rgba.extend_from_slice(&pixel);
+        }
+    }
+    Ok(Image { width, height, rgba })
+}
+
+fn be_u32(bytes: &[u8]) -> u32 {
+    u32::from_be_bytes([bytes[0], bytes[1], bytes[2], bytes[3]])
+}
+
+/// Undoes the filters the rows of an image were encoded with. `pixel_size` is how many bytes
+/// apart the bytes a filter compares are, which is at least 1.
+fn unfilter(data: &[u8], row_size: usize, height: usize, pixel_size: usize) -> anyhow::Result<Vec<u8>> {
+    if data.len() < (row_size + 1) * height {
+        bail!("the image data is cut short");
+    }
+    let mut rows = vec![0u8; row_size * height];
+    for (y, encoded) in data.chunks_exact(row_size + 1).take(height).enumerate() {
+        let (filter, encoded) = (encoded[0], &encoded[1..]);
+        let (previous, current) = rows.split_at_mut(y * row_size);
+        let above = match y {
+            0 => None,
+            _ => Some(&previous[(y - 1) * row_size..]),
+        };
+        let row = &mut current[..row_size];
+        for i in 0..row_size {
+            let left = if i >= pixel_size { row[i - pixel_size] } else { 0 };
+            let up = above.map_or(0, |above| above[i]);
+            let upper_left = match (above, i >= pixel_size) {
+                (Some(above), true) => above[i - pixel_size],
+                _ => 0,
+            };
+            let predicted = match filter {
+                0 => 0,
+                1 => left,
+                2 => up,
+                3 => ((left as u16 + up as u16) / 2) as u8,
+                4 => paeth(left, up, upper_left),
+                _ => bail!("unknown filter type {}", filter),
+            };
+            row[i] = encoded[i].wrapping_add(predicted);
+        }
+    }
+    Ok(rows)
+}
+
+/// Predicts a byte from whichever of its neighbors is closest to their gradient.
+fn paeth(left: u8, up: u8, upper_left: u8) -> u8 {
+    let estimate = left as i16 + up as i16 - upper_left as i16;
+    let distance = |value: u8| (estimate - value as i16).abs();
+    let (a, b, c) = (distance(left), distance(up), distance(upper_left));
+    if a <= b && a <= c {
+        left
+    } else if b <= c {
+        up
+    } else {
+        upper_left
+    }
+}
+
+fn cut_short() -> anyhow::Error {
+    anyhow!("the compressed data is cut short")
+}
+
+fn too_long() -> anyhow::Error {
+    anyhow!("the image data is longer than the image")
+}
+
+/// Reads bits from a deflate stream, starting from the least significant bit of every byte.
+struct Bits<'a> {
+    data: &'a [u8],
+    /// The index of the next bit.
+    position: usize,
+}
+
+impl Bits<'_> {
+    fn bit(&mut self) -> anyhow::Result<u32> {
+        let byte = self.data.get(self.position / 8).ok_or_else(cut_short)?;
+        let bit = (byte >> (self.position % 8)) & 1;
+        self.position += 1;
+        Ok(bit as u32)
+    }
+
+    fn bits(&mut self, count: u32) -> anyhow::Result<u32> {
+        let mut value = 0;
+        for i in 0..count {
+            value |= self.bit()? << i;
+        }
+        Ok(value)
+    }
+}
+
+/// A canonical Huffman code, as described by the lengths of its codes.
+struct Huffman {
+    /// How many codes there are of every length.
+    counts: [u16; 16],
+    /// The symbols, ordered by the length of their codes and then by their value.
+    symbols: Vec<u16>,
+}
+
+impl Huffman {
+    fn new(lengths: &[u8]) -> Self {
+        let mut counts = [0; 16];
+        for &length in lengths {
+            counts[length as usize] += 1;
+        }
+        counts[0] = 0;
+        let mut symbols = Vec::with_capacity(lengths.len());
+        for length in 1..16 {
+            symbols.extend((0..lengths.len() as u16).filter(|&symbol| lengths[symbol as usize] == length));
+        }
+        Huffman { counts, symbols }
+    }
+
+    /// Reads a symbol, a bit at a time. The codes of every length come right after the ones
+    /// of the length before, so the code is looked for among each length in turn.
+    fn decode(&self, bits: &mut Bits) -> anyhow::Result<u16> {
+        let (mut code, mut first, mut index) = (0u32, 0u32, 0u32);
+        for &count in &self.counts[1..] {
+            code |= bits.bit()?;
+            let count = count as u32;
+            if code < first + count {
+                return Ok(self.symbols[(index + code - first) as usize])
+            }
+            index += count;
+            first = (first + count) << 1;
+            code <<= 1;
+        }
+        bail!("invalid Huffman code")
+    }
+}
+
+/// Decompresses a zlib stream of at most `limit` bytes. The checksum isn't checked.
+fn inflate(data: &[u8], limit: usize) -> anyhow::Result<Vec<u8>> {
+    const LENGTH_BASES: [u16; 29] = [
+        3, 4, 5, 6, 7, 8, 9, 10, 11, 13, 15, 17, 19, 23, 27, 31, 35, 43, 51, 59, 67, 83, 99, 115, 131,
+        163, 195, 227, 258,
+    ];
+    const LENGTH_EXTRA_BITS: [u8; 29] = [
+        0, 0, 0, 0, 0, 0, 0, 0, 1, 1, 1, 1, 2, 2, 2, 2, 3, 3, 3, 3, 4, 4, 4, 4, 5, 5, 5, 5, 0,
+    ];
+    const DISTANCE_BASES: [u16; 30] = [
+        1, 2, 3, 4, 5, 7, 9, 13, 17, 25, 33, 49, 65, 97, 129, 193, 257, 385, 513, 769, 1025, 1537,
+        2049, 3073, 4097, 6145, 8193, 12289, 16385, 24577,
+    ];
+    const DISTANCE_EXTRA_BITS: [u8; 30] = [
+        0, 0, 0, 0, 1, 1, 2, 2, 3, 3, 4, 4, 5, 5, 6, 6, 7, 7, 8, 8, 9, 9, 10, 10, 11, 11, 12, 12, 13, 13,
+    ];
+    /// The order the lengths of the code length code are stored in.
+    const CODE_LENGTH_ORDER: [usize; 19] = [16, 17, 18, 0, 8, 7, 9, 6, 10, 5, 11, 4, 12, 3, 13, 2, 14, 1, 15];
+
+    match data {
+        [method, _, ..] if method & 0x0F == 8 => (),
+        _ => bail!("the image data is not deflate compressed"),
+    }
+    let mut bits = Bits { data: &data[2..], position: 0 };
+    let mut output = Vec::new();
+    loop {
+        let is_last = bits.bit()? == 1;
+        let (literals, distances) = match bits.bits(2)? {
+            // stored
+            0 => {
+                let start = bits.position.div_ceil(8);
+                let header = bits.data.get(start..start + 4).ok_or_else(cut_short)?;
+                let length = u16::from_le_bytes([header[0], header[1]]) as usize;
+                let block = bits.data.get(start + 4..start + 4 + length)
+                    .ok_or_else(cut_short)?;
+                if output.len() + block.len() > limit {
+                    return Err(too_long())
+                }
+                output.extend_from_slice(block);
+                bits.position = (start + 4 + length) * 8;
+                if is_last {
+                    return Ok(output)
+                }
+                continue
+            },
+            // fixed Huffman codes
+            1 => {
+                let mut lengths = [8; 288];
+                lengths[144..256].fill(9);
+                lengths[256..280].fill(7);
+                (Huffman::new(&lengths), Huffman::new(&[5; 30]))
+            },
+            // dynamic Huffman codes, which are themselves compressed with a Huffman code
+            2 => {
+                let literal_count = bits.bits(5)? as usize + 257;
+                let distance_count = bits.bits(5)? as usize + 1;
+                let code_length_count = bits.bits(4)? as usize + 4;
+                let mut code_lengths = [0; 19];
+                for &symbol in &CODE_LENGTH_ORDER[..code_length_count] {
+                    code_lengths[symbol] = bits.bits(3)? as u8;
+                }
+                let code_lengths = Huffman::new(&code_lengths);
+                let mut lengths = Vec::with_capacity(literal_count + distance_count);
+                while lengths.len() < literal_count + distance_count {
+                    let (length, repeat) = match code_lengths.decode(&mut bits)? {
+                        symbol @ 0..=15 => (symbol as u8, 1),
+                        16 => {
+                            let previous = *lengths.last().ok_or_else(|| anyhow!("nothing to repeat"))?;
+                            (previous, 3 + bits.bits(2)?)
+                        },
+                        17 => (0, 3 + bits.bits(3)?),
+                        _ => (0, 11 + bits.bits(7)?),
+                    };
+                    lengths.extend(std::iter::repeat_n(length, repeat as usize));
+                }
+                if lengths.len() > literal_count + distance_count {
+                    bail!("too many code lengths");
+                }
+                let (literals, distances) = lengths.split_at(literal_count);
+                (Huffman::new(literals), Huffman::new(distances))
+            },
+            _ => bail!("invalid block type"),
+        };
+        loop {
+            let symbol = literals.decode(&mut bits)? as usize;
+            match symbol {
+                0..=255 if output.len() == limit => return Err(too_long()),
+                0..=255 => output.push(symbol as u8),
+                256 => break,
+                _ => {
+                    let i = symbol - 257;
+                    let length = *LENGTH_BASES.get(i).ok_or_else(|| anyhow!("invalid length"))? as usize
+                        + bits.bits(LENGTH_EXTRA_BITS[i] as u32)? as usize;
+                    let i = distances.decode(&mut bits)? as usize;
+                    let distance = *DISTANCE_BASES.get(i).ok_or_else(|| anyhow!("invalid distance"))? as usize
+                        + bits.bits(DISTANCE_EXTRA_BITS[i] as u32)? as usize;
+                    if distance > output.len() {
+                        bail!("the compressed data refers back past its start");
+                    }
+                    if output.len() + length > limit {
+                        return Err(too_long())
+                    }
+                    // the copy can overlap what it's copying, which repeats it
+                    let start = output.len() - distance;
+                    for i in 0..length {
+                        output.push(output[start + i]);
+                    }
+                },
+            }
+        }
+        if is_last {
+            return Ok(output)
+        }
+    }
+}
+
+/// Writes an RGBA image to a PNG file. The alpha channel is ignored, and the image data isn't
+/// compressed, which keeps the writer small at the cost of bigger files.
+pub fn write(path: &Path, width: u32, height: u32, rgba: &[u8]) -> anyhow::Result<()> {
+    // every row starts with the filter type, which is 0 (none)
+    let mut data = Vec::with_capacity((width as usize * 3 + 1) * height as usize);
+    for row in rgba.chunks_exact(width as usize * 4).take(height as usize) {
+        data.push(0);
+        for pixel in row.chunks_exact(4) {
+            data.extend_from_slice(&pixel[..3]);
+        }
+    }
+
+    let mut header = Vec::new();
+    header.extend_from_slice(&width.to_be_bytes());
+    header.extend_from_slice(&height.to_be_bytes());
+    // 8 bits per channel, truecolor, deflate, no filtering, no interlacing
+    header.extend_from_slice(&[8, 2, 0, 0, 0]);
+
+    let file = File::create(path).with_context(|| format!("cannot create {}", path.display()))?;
+    let mut file = BufWriter::new(file);
+    file.write_all(b"\x89PNG\r\n\x1a\n")?;
+    write_chunk(&mut file, b"IHDR", &header)?;
+    write_chunk(&mut file, b"IDAT", &zlib_stored(&data))?;
+    write_chunk(&mut file, b"IEND", &[])?;
+    file.flush().with_context(|| format!("cannot write {}", path.display()))
+}
+
+fn write_chunk(writer: &mut impl Write, kind: &[u8; 4], data: &[u8]) -> std::io::Result<()> {
+    writer.write_all(&(data.len() as u32).to_be_bytes())?;
+    writer.write_all(kind)?;
+    writer.write_all(data)?;
+    let crc = crc32(kind.iter().chain(data));
+    writer.write_all(&crc.to_be_bytes())
+}
+
+/// Wraps data in a zlib stream made of stored deflate blocks.
+fn zlib_stored(data: &[u8]) -> Vec<u8> {
+    // deflate with a 32 KiB window and no preset dictionary, with the check bits filled in
+    let mut stream = vec![0x78, 0x01];
+    let mut blocks = data.chunks(MAX_STORED_BLOCK_SIZE).peekable();
+    if blocks.peek().is_none() {
+        stream.extend_from_slice(&[1, 0, 0, 0xFF, 0xFF]);
+    }
+    while let Some(block) = blocks.next() {
+        let is_last = blocks.peek().is_none();
+        let length = block.len() as u16;
+        stream.push(is_last as u8);
+        stream.extend_from_slice(&length.to_le_bytes());
+        stream.extend_from_slice(&(!length).to_le_bytes());
+        stream.extend_from_slice(block);
+    }
+    stream.extend_from_slice(&adler32(data).to_be_bytes());
+    stream
+}
+
+fn crc32<'a>(bytes: impl Iterator<Item = &'a u8>) -> u32 {
+    let mut crc = !0u32;
+    for &byte in bytes {
+        crc ^= byte as u32;
+        for _ in 0..8 {
+            crc = match crc & 1 {
+                1 => (crc >> 1) ^ 0xEDB88320,
+                _ => crc >> 1,
+            };
+        }
+    }
+    !crc
+}
+
+fn adler32(bytes: &[u8]) -> u32 {
+    const MODULUS: u32 = 65521;
+    let (mut a, mut b) = (1u32, 0u32);
+    // the sums can go this far without overflowing before they need to be reduced
+    for chunk in bytes.chunks(5552) {
+        for &byte in chunk {
+            a += byte as u32;
+            b += a;
+        }
+        a %= MODULUS;
+        b %= MODULUS;
+    }
+    (b << 16) | a
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Puts together a PNG file out of rows that are already filtered, with the given extra
+    /// chunks before the image data.
+    fn png(
+        width: u32,
+        height: u32,
+        depth: u8,
+        color_type: u8,
+        chunks: &[(&[u8; 4], &[u8])],
+        rows: &[u8],
+    ) -> Vec<u8> {
+        let mut header = Vec::new();
+        header.extend_from_slice(&width.to_be_bytes());
+        header.extend_from_slice(&height.to_be_bytes());
+        header.extend_from_slice(&[depth, color_type, 0, 0, 0]);
+        let mut file = b"\x89PNG\r\n\x1a\n".to_vec();
+        write_chunk(&mut file, b"IHDR", &header).unwrap();
+        for (kind, data) in chunks {
+            write_chunk(&mut file, kind, data).unwrap();
+        }
+        write_chunk(&mut file, b"IDAT", &zlib_stored(rows)).unwrap();
+        write_chunk(&mut file, b"IEND", &[]).unwrap();
+        file
+    }
+
+    fn gray(values: &[u8]) -> Vec<u8> {
+        values.iter().flat_map(|&value| [value, value, value, 255]).collect()
+    }
+
+    #[test]
+    fn round_trip() {
+        let (width, height) = (70, 300);
+        let rgba: Vec<u8> = (0..width * height)
+            .flat_map(|i| [(i % 256) as u8, (i / 7 % 256) as u8, (i * 13 % 256) as u8, 255])
+            .collect();
+        let path = std::env::temp_dir().join(format!("vectorscope-png-test-{}.png", std::process::id()));
+        write(&path, width, height, &rgba).unwrap();
+        let image = read(&path);
+        std::fs::remove_file(&path).unwrap();
+        let image = image.unwrap();
+        assert_eq!((image.width, image.height), (width, height));
+        assert!(image.rgba == rgba);
+    }
+
+    #[test]
+    fn fixed_huffman() {
+        // zlib's output for short data uses the fixed codes
+        let stream = [120, 218, 75, 76, 74, 78, 132, 33, 29, 5, 8, 173, 8, 0, 84, 64, 7, 82];
+        assert_eq!((stream[2] >> 1) & 3, 1);
+        assert_eq!(inflate(&stream, 100).unwrap(), b"abcabcabcabc, abcabc!");
+    }
+
+    #[test]
+    fn dynamic_huffman() {
+        let stream = [
+            120, 218, 29, 205, 201, 9, 0, 49, 8, 0, 64, 33, 32, 4, 2, 66, 170, 136, 168, 15, 251, 255,
+            122, 149, 181, 176, 211, 192, 192, 155, 197, 141, 90, 219, 243, 88, 144, 204, 149, 6, 171,
+            229, 137, 26, 155, 231, 188, 38, 174, 171, 9, 30, 203, 6, 165, 183, 212, 177, 36, 143,
+            171, 3, 220, 235, 21, 114, 110, 141, 227, 67, 214, 87, 10, 36, 151, 5, 250, 108, 237, 195,
+            69, 47, 47, 7, 232, 44, 111, 180, 218, 146, 71, 130, 108, 174, 55, 104, 45, 78, 124, 127,
+            164, 77, 94, 215, 18, 36, 150, 12, 90, 111, 175, 163, 73, 28, 247, 253, 145, 22, 122, 110,
+            139, 15, 152, 210, 58, 194,
+        ];
+        assert_eq!((stream[2] >> 1) & 3, 2);
+        let expected: Vec<u8> = (0..60u32)
+            .flat_map(|i| [(i % 7 * 3) as u8, ((i * i) % 11 + 40) as u8, (200 - i % 5) as u8])
+            .collect();
+        assert_eq!(inflate(&stream, 1000).unwrap(), expected);
+    }
+
+    #[test]
+    fn output_is_capped() {
+        let stream = zlib_stored(&[0; 100]);
+        assert_eq!(inflate(&stream, 100).unwrap().len(), 100);
+        assert!(inflate(&stream, 99).is_err());
+        // a 1x1 image only has room for a filter type and a byte
+        let file = png(1, 1, 8, 0, &[], &[0; 100]);
+        assert!(decode(&file).is_err());
+    }
+
+    #[test]
+    fn gray_below_8_bits() {
+        let file = png(3, 2, 1, 0, &[], &[0, 0b1010_0000, 0, 0b0100_0000]);
+        let image = decode(&file).unwrap();
+        assert!(image.rgba == gray(&[255, 0, 255, 0, 255, 0]));
+
+        let file = png(5, 1, 2, 0, &[], &[0, 0b00_01_10_11, 0b11_000000]);
+        let image = decode(&file).unwrap();
+        assert!(image.rgba == gray(&[0, 85, 170, 255, 255]));
+
+        // a transparent gray level is compared before it's scaled
+        let file = png(2, 1, 4, 0, &[(b"tRNS", &[0, 15])], &[0, 0x0F_u8 << 4 | 0x7]);
+        let image = decode(&file).unwrap();
+        assert_eq!(image.rgba, [255, 255, 255, 0, 119, 119, 119, 255]);
+    }
+
+    #[test]
+    fn palette_below_8_bits() {
+        let palette = [10, 20, 30, 40, 50, 60, 70, 80, 90, 100, 110, 120];
+        let transparency = [0, 128];
+        let chunks: &[(&[u8; 4], &[u8])] = &[(b"PLTE", &palette), (b"tRNS", &transparency)];
+        let file = png(5, 1, 2, 3, chunks, &[0, 0b00_01_10_11, 0b01_000000]);
+        let image = decode(&file).unwrap();
+        assert_eq!(image.rgba, [
+            10, 20, 30, 0,
+            40, 50, 60, 128,
+            70, 80, 90, 255,
+            100, 110, 120, 255,
+            40, 50, 60, 128,
+        ]);
+
+        let file = png(3, 1, 4, 3, chunks, &[0, 0x21, 0x00]);
+        let image = decode(&file).unwrap();
+        assert_eq!(image.rgba, [70, 80, 90, 255, 40, 50, 60, 128, 10, 20, 30, 0]);
+
+        // an index past the end of the palette
+        let file = png(1, 1, 1, 3, &[(b"PLTE", &palette[..3])], &[0, 0x80]);
+        assert!(decode(&file).is_err());
+    }
+
+    #[test]
+    fn all_filter_types() {
+        let (width, height, pixel_size) = (4, 5, 3);
+        let row_size = width * pixel_size;
+        let raw: Vec<u8> = (0..row_size * height).map(|i| (i * 37 % 251) as u8).collect();
+        // every row is filtered differently, from none to Paeth
+        let mut rows = Vec::new();
+        for y in 0..height {
+            rows.push(y as u8);
+            for i in 0..row_size {
+                let at = |x: usize, y: usize| raw[y * row_size + x];
+                let left = if i >= pixel_size { at(i - pixel_size, y) } else { 0 };
+                let up = if y > 0 { at(i, y - 1) } else { 0 };
+                let upper_left = if y > 0 && i >= pixel_size { at(i - pixel_size, y - 1) } else { 0 };
+                let predicted = match y {
+                    0 => 0,
+                    1 => left,
+                    2 => up,
+                    3 => ((left as u16 + up as u16) / 2) as u8,
+                    _ => paeth(left, up, upper_left),
+                };
+                rows.push(at(i, y).wrapping_sub(predicted));
+            }
+        }
+        let image = decode(&png(width as u32, height as u32, 8, 2, &[], &rows)).unwrap();
+        let expected: Vec<u8> = raw.chunks_exact(3).flat_map(|rgb| [rgb[0], rgb[1], rgb[2], 255]).collect();
+        assert!(image.rgba == expected);
+
+        rows[0] = 5;
+        assert!(decode(&png(width as u32, height as u32, 8, 2, &[], &rows)).is_err());
+    }
+}
@@ -0,0 +1,280 @@
+use std::fmt;
+
+use anyhow::{anyhow, bail};
+
+/// A small regular expression, used for matching device names.
+///
+/// Supports literals, `.`, bracketed character classes (with ranges and negation), the `*`, `+`
+/// and `?` quantifiers, `^` and `$` anchors, `|` alternation, and backslash escapes. Groups are
+/// not supported.
+#[derive(Clone)]
+pub struct Regex {
+    source: String,
+    alternatives: Vec<Sequence>,
+}
+
+#[derive(Clone, Default)]
+struct Sequence {
+    anchored_start: bool,
+    anchored_end: bool,
+    items: Vec<Item>,
+}
+
+#[derive(Clone)]
+struct Item {
+    atom: Atom,
+    repeat: Repeat,
+}
+
+#[derive(Clone)]
+enum Atom {
+    Char(char),
+    Any,
+    Class {
+        negated: bool,
+        ranges: Vec<(char, char)>,
+    },
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Repeat {
+    One,
+    Optional,
+    Many,
+    AtLeastOne,
+}
+
+impl Atom {
+    fn matches(&self, c: char) -> bool {
+        match self {
+            Atom::Char(expected) => c == *expected,
+            Atom::Any => true,
+            Atom::Class { negated, ranges } =>
+                ranges.iter().any(|&(start, end)| (start..=end).contains(&c)) != *negated,
+        }
+    }
+}
+
+impl Repeat {
+    /// Returns the minimum and maximum number of repetitions.
+    fn bounds(self) -> (usize, usize) {
+        match self {
+            Repeat::One => (1, 1),
+            Repeat::Optional => (0, 1),
+            Repeat::Many => (0, usize::MAX),
+            Repeat::AtLeastOne => (1, usize::MAX),
+        }
+    }
+}
+
+impl Regex {
+    pub fn new(source: &str) -> anyhow::Result<Self> {
+        let mut chars = source.chars().peekable();
+        let mut alternatives = vec![Sequence::default()];
+        while let Some(c) = chars.next() {
+            let sequence = alternatives.last_mut().unwrap();
+            let atom = match c {
+                '|' => {
+                    alternatives.push(Sequence::default());
+                    continue
+                },
+                '^' if sequence.items.is_empty() => {
+                    sequence.anchored_start = true;
+                    continue
+                },
+                '$' if matches!(chars.peek(), None | Some('|')) => {
+                    sequence.anchored_end = true;
+                    continue
+                },
+                '*' | '+' | '?' => {
+                    let item = sequence.items.last_mut()
+                        .filter(|item| item.repeat == Repeat::One)
+                        .ok_or_else(|| anyhow!("nothing to repeat before {} in {}", c, source))?;
+                    item.repeat = match c {
+                        '*' => Repeat::Many,
+                        '+' => Repeat::AtLeastOne,
+                        _ => Repeat::Optional,
+                    };
+                    continue
+                },
+                '.' => Atom::Any,
+                '\\' => Atom::Char(Self::escaped(&mut chars, source)?),
+                '[' => Self::parse_class(&mut chars, source)?,
+                c => Atom::Char(c),
+            };
+            sequence.items.push(Item { atom, repeat: Repeat::One });
+        }
+        Ok(Regex {
+            source: source.to_owned(),
+            alternatives,
+        })
+    }
+
+    fn escaped(chars: &mut impl Iterator<Item = char>, source: &str) -> anyhow::Result<char> {
+        chars.next().ok_or_else(|| anyhow!("trailing backslash in {}", source))
+    }
+
+    /// Parses a character class, right after its opening bracket.
+    fn parse_class(
+        chars: &mut std::iter::Peekable<impl Iterator<Item = char>>,
+        source: &str,
+    ) -> anyhow::Result<Atom> {
+        let negated = chars.next_if_eq(&'^').is_some();
+        let mut ranges = Vec::new();
+        loop {
+            let start = match chars.next() {
+                // a bracket right at the start of the class is taken literally
+                Some(']') if !ranges.is_empty() => break,
+                Some('\\') => Self::escaped(chars, source)?,
+                Some(c) => c,
+                None => bail!("unterminated character class in {}", source),
+            };
+            let end = if chars.next_if_eq(&'-').is_some() {
+                match chars.next() {
+                    // a dash right before the closing bracket is taken literally
+                    Some(']') => {
+                        ranges.push((start, start));
+                        ranges.push(('-', '-'));
+                        break
+                    },
+                    Some('\\') => Self::escaped(chars, source)?,
+                    Some(c) => c,
+                    None => bail!("unterminated character class in {}", source),
+                }
+            } else {
+                start
+            };
+            ranges.push((start, end));
+        }
+        Ok(Atom::Class { negated, ranges })
+    }
+
+    /// Returns whether the regex matches anywhere in the text.
+    pub fn is_match(&self, text: &str) -> bool {
+        let text: Vec<char> = text.chars().collect();
+        self.alternatives.iter().any(|sequence| {
+            if sequence.anchored_start {
+                Self::match_here(&sequence.items, &text, sequence.anchored_end)
+            } else {
+                (0..=text.len()).any(|start| {
+                    Self::match_here(&sequence.items, &text[start..], sequence.anchored_end)
+                })
+            }
+        })
+    }
+
+    fn match_here(items: &[Item], text: &[char], anchored_end: bool) -> bool {
+        match items.split_first() {
+            None => !anchored_end || text.is_empty(),
+            Some((item, rest)) => {
+                let (min, max) = item.repeat.bounds();
+                let count = text.iter()
+                    .take(max)
+                    .take_while(|&&c| item.atom.matches(c))
+                    .count();
+                // quantifiers are greedy, so the longest repetition is tried first
+                count >= min && (min..=count).rev().any(|n| Self::match_here(rest, &text[n..], anchored_end))
+            },
+        }
+    }
+}
+
+impl fmt::Display for Regex {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.source)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Regex;
+
+    fn is_match(regex: &str, text: &str) -> bool {
+        Regex::new(regex).unwrap().is_match(text)
+    }
+
+    fn error(regex: &str) -> String {
+        match Regex::new(regex) {
+            Ok(_) => panic!("{} should not parse", regex),
+            Err(error) => error.to_string(),
+        }
+    }
+
+    #[test]
+    fn anchors_apply_to_their_own_alternative() {
+        assert!(is_match("^USB|Monitor$", "USB Audio"));
+        assert!(is_match("^USB|Monitor$", "Speakers Monitor"));
+        assert!(!is_match("^USB|Monitor$", "Monitor of USB"));
+        assert!(!is_match("^USB|Monitor$", "Monitor USB Audio"));
+        assert!(is_match("^a$|^b$", "b"));
+        assert!(!is_match("^a$|^b$", "ab"));
+    }
+
+    #[test]
+    fn unanchored_matches_anywhere() {
+        assert!(is_match("card", "USB card 2"));
+        assert!(!is_match("card", "USB cart"));
+        assert!(is_match("", "anything"));
+    }
+
+    #[test]
+    fn bracket_first_in_class_is_literal() {
+        assert!(is_match("[]a]", "]"));
+        assert!(is_match("[]a]", "a"));
+        assert!(!is_match("[]a]", "b"));
+    }
+
+    #[test]
+    fn dash_last_in_class_is_literal() {
+        assert!(is_match("^[a-]$", "-"));
+        assert!(is_match("^[a-]$", "a"));
+        assert!(!is_match("^[a-]$", "b"));
+        assert!(is_match("^[0-9]+$", "1234"));
+        assert!(!is_match("^[0-9]+$", "12a4"));
+    }
+
+    #[test]
+    fn negated_class() {
+        assert!(is_match("^[^0-9]+$", "hw:"));
+        assert!(!is_match("^[^0-9]+$", "hw:1"));
+        assert!(!is_match("[^a]", "aaa"));
+    }
+
+    #[test]
+    fn quantifiers_backtrack() {
+        assert!(is_match("^a*ab$", "aaab"));
+        assert!(is_match("^a*ab$", "ab"));
+        assert!(!is_match("^a*ab$", "aaa"));
+        assert!(is_match("^a+a$", "aa"));
+        assert!(!is_match("^a+a$", "a"));
+        assert!(is_match("^colou?r$", "color"));
+        assert!(is_match("^.*Monitor.*$", "Monitor of Built-in Audio"));
+    }
+
+    #[test]
+    fn escapes_are_literal() {
+        assert!(is_match(r"^hw\.1$", "hw.1"));
+        assert!(!is_match(r"^hw\.1$", "hw:1"));
+        assert!(is_match(r"^[\]]$", "]"));
+    }
+
+    #[test]
+    fn nothing_to_repeat() {
+        assert!(error("*a").contains("nothing to repeat"));
+        assert!(error("a|+b").contains("nothing to repeat"));
+        assert!(error("a**").contains("nothing to repeat"));
+    }
+
+    #[test]
+    fn trailing_backslash() {
+        assert!(error(r"abc\").contains("trailing backslash"));
+        assert!(error(r"[a\").contains("trailing backslash"));
+    }
+
+    #[test]
+    fn unterminated_class() {
+        assert!(error("[abc").contains("unterminated character class"));
+        assert!(error("[a-").contains("unterminated character class"));
+        assert!(error("[]").contains("unterminated character class"));
+    }
+}
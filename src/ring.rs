@@ -0,0 +1,169 @@
+use std::{
+    cell::UnsafeCell,
+    sync::{
+        Arc,
+        atomic::{AtomicUsize, Ordering},
+    },
+};
+
+/// A bounded single-producer single-consumer ring buffer. Neither side ever blocks or allocates,
+/// so the producer can live in a real-time audio callback.
+struct Ring<T> {
+    slots: Box<[UnsafeCell<T>]>,
+    /// The number of elements ever popped. Only written by the consumer.
+    head: AtomicUsize,
+    /// The number of elements ever pushed. Only written by the producer.
+    tail: AtomicUsize,
+    /// The number of elements that were dropped because the buffer was full.
+    overruns: AtomicUsize,
+}
+
+// the producer only writes to slots the consumer is done with, and the consumer only reads slots
+// the producer is done with, as told by the head and tail
+unsafe impl<T: Send> Sync for Ring<T> {}
+
+/// The writing end of a ring buffer.
+pub struct Producer<T> {
+    ring: Arc<Ring<T>>,
+}
+
+/// The reading end of a ring buffer.
+pub struct Consumer<T> {
+    ring: Arc<Ring<T>>,
+}
+
+/// Creates a ring buffer that can hold the given number of elements.
+pub fn ring_buffer<T: Copy + Default>(capacity: usize) -> (Producer<T>, Consumer<T>) {
+    let capacity = capacity.max(1);
+    let ring = Arc::new(Ring {
+        slots: (0..capacity).map(|_| UnsafeCell::new(T::default())).collect(),
+        head: AtomicUsize::new(0),
+        tail: AtomicUsize::new(0),
+        overruns: AtomicUsize::new(0),
+    });
+    (Producer { ring: ring.clone() }, Consumer { ring })
+}
+
+impl<T: Copy> Producer<T> {
+    /// Pushes an element, or drops it and counts an overrun if the buffer is full.
+    pub fn push(&mut self, value: T) {
+        let ring = &*self.ring;
+        let tail = ring.tail.load(Ordering::Relaxed);
+        let head = ring.head.load(Ordering::Acquire);
+        if tail.wrapping_sub(head) == ring.slots.len() {
+            ring.overruns.fetch_add(1, Ordering::Relaxed);
+            return
+        }
+        unsafe { *ring.slots[tail % ring.slots.len()].get() = value };
+        ring.tail.store(tail.wrapping_add(1), Ordering::Release);
+    }
+}
+
+impl<T: Copy> Consumer<T> {
+    /// Moves every element that's currently in the buffer to the end of `out`.
+    pub fn drain_into(&mut self, out: &mut Vec<T>) {
+        let ring = &*self.ring;
+        let head = ring.head.load(Ordering::Relaxed);
+        let tail = ring.tail.load(Ordering::Acquire);
+        let count = tail.wrapping_sub(head);
+        out.extend((0..count).map(|i| unsafe { *ring.slots[head.wrapping_add(i) % ring.slots.len()].get() }));
+        ring.head.store(tail, Ordering::Release);
+    }
+
+    /// Throws away every element that's currently in the buffer, and returns how many there were.
+    pub fn clear(&mut self) -> usize {
+        let ring = &*self.ring;
+        let head = ring.head.load(Ordering::Relaxed);
+        let tail = ring.tail.load(Ordering::Acquire);
+        ring.head.store(tail, Ordering::Release);
+        tail.wrapping_sub(head)
+    }
+
+    /// Returns the number of elements dropped so far because the buffer was full.
+    pub fn overruns(&self) -> usize {
+        self.ring.overruns.load(Ordering::Relaxed)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::ring_buffer;
+
+    #[test]
+    fn wraps_around() {
+        let (mut producer, mut consumer) = ring_buffer(3);
+        let mut out = Vec::new();
+        for i in 0..10 {
+            producer.push(i * 2);
+            producer.push(i * 2 + 1);
+            consumer.drain_into(&mut out);
+        }
+        assert_eq!(out, (0..20).collect::<Vec<_>>());
+        assert_eq!(consumer.overruns(), 0);
+    }
+
+    #[test]
+    fn drops_and_counts_overruns_when_full() {
+        let (mut producer, mut consumer) = ring_buffer(4);
+        for i in 0..7 {
+            producer.push(i);
+        }
+        assert_eq!(consumer.overruns(), 3);
+        let mut out = Vec::new();
+        consumer.drain_into(&mut out);
+        // the oldest elements are kept, and the ones that didn't fit are lost
+        assert_eq!(out, [0, 1, 2, 3]);
+        producer.push(7);
+        consumer.drain_into(&mut out);
+        assert_eq!(out, [0, 1, 2, 3, 7]);
+        assert_eq!(consumer.overruns(), 3);
+    }
+
+    #[test]
+    fn clear_returns_the_dropped_count() {
+        let (mut producer, mut consumer) = ring_buffer(8);
+        for i in 0..5 {
+            producer.push(i);
+        }
+        assert_eq!(consumer.clear(), 5);
+        assert_eq!(consumer.clear(), 0);
+        producer.push(5);
+        let mut out = Vec::new();
+        consumer.drain_into(&mut out);
+        assert_eq!(out, [5]);
+    }
+
+    #[test]
+    fn zero_capacity_holds_one() {
+        let (mut producer, mut consumer) = ring_buffer(0);
+        producer.push(1);
+        producer.push(2);
+        let mut out = Vec::new();
+        consumer.drain_into(&mut out);
+        assert_eq!(out, [1]);
+        assert_eq!(consumer.overruns(), 1);
+    }
+
+    #[test]
+    fn moves_elements_between_threads_in_order() {
+        const COUNT: u64 = 1000000;
+        let (mut producer, mut consumer) = ring_buffer(64);
+        let thread = std::thread::spawn(move || {
+            for i in 0..COUNT {
+                producer.push(i);
+            }
+        });
+        let mut out = Vec::new();
+        let mut received = Vec::new();
+        while !thread.is_finished() || !received.is_empty() {
+            received.clear();
+            consumer.drain_into(&mut received);
+            out.extend_from_slice(&received);
+        }
+        thread.join().unwrap();
+        consumer.drain_into(&mut out);
+        // whatever didn't fit was dropped, but what did arrives in order and is counted
+        assert!(out.windows(2).all(|pair| pair[0] < pair[1]));
+        assert_eq!(out.len() + consumer.overruns(), COUNT as usize);
+    }
+}
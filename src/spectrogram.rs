@@ -0,0 +1,158 @@
+use std::collections::VecDeque;
+
+use crate::png::Image;
+
+/// A spectrum over time, made of the spectra of overlapping windows of the input, with the
+/// newest on the right.
+pub struct Spectrogram {
+    /// How many samples every window has, which is a power of two.
+    size: usize,
+    /// How many samples every window starts after the one before it.
+    hop: usize,
+    /// The Hann window the samples are weighted with, so that the spectra don't leak into
+    /// neighboring frequencies.
+    window: Vec<f32>,
+    /// The samples that haven't been analyzed yet, and those the next window overlaps.
+    pending: Vec<f32>,
+    /// The number of frames the input produced before the end of `pending`.
+    pending_end: u64,
+    /// The level of every frequency bin in every window, in dBFS, oldest first.
+    columns: VecDeque<Vec<f32>>,
+}
+
+impl Spectrogram {
+    /// The lowest frequency shown.
+    const MIN_FREQUENCY: f32 = 20.0;
+    /// How far below full scale the quietest level that shows up is.
+    const DB_RANGE: f32 = 100.0;
+
+    /// Creates a spectrogram of windows of `size` samples, each overlapping the one before it by
+    /// the given fraction.
+    pub fn new(size: usize, overlap: f32) -> Self {
+        let window = (0..size)
+            .map(|i| 0.5 - 0.5 * (i as f32 / size as f32 * std::f32::consts::TAU).cos())
+            .collect();
+        Spectrogram {
+            size,
+            hop: ((size as f32 * (1.0 - overlap)) as usize).max(1),
+            window,
+            pending: Vec::new(),
+            pending_end: 0,
+            columns: VecDeque::new(),
+        }
+    }
+
+    /// Forgets everything analyzed so far, such as when the input is replaced.
+    pub fn clear(&mut self) {
+        self.pending.clear();
+        self.pending_end = 0;
+        self.columns.clear();
+    }
+
+    /// Analyzes the samples being displayed, leaving out the ones it's already been given.
+    /// `start` is the number of frames the input produced before the first of them. No more
+    /// than `max_columns` of the latest windows are kept.
+    pub fn push(&mut self, samples: &[f32], start: u64, max_columns: usize) {
+        let end = start + samples.len() as u64;
+        if start > self.pending_end || end < self.pending_end {
+            // frames were dropped or the input started over
+            self.pending.clear();
+            self.pending.extend_from_slice(samples);
+        } else {
+            self.pending.extend_from_slice(&samples[(self.pending_end - start) as usize..]);
+        }
+        self.pending_end = end;
+        let mut offset = 0;
+        while self.pending.len() - offset >= self.size {
+            let column = self.analyze(&self.pending[offset..offset + self.size]);
+            self.columns.push_back(column);
+            offset += self.hop;
+        }
+        self.pending.drain(..offset);
+        while self.columns.len() > max_columns {
+            self.columns.pop_front();
+        }
+    }
+
+    /// Returns the level of every frequency bin of a window, in dBFS.
+    fn analyze(&self, samples: &[f32]) -> Vec<f32> {
+        let mut bins: Vec<(f32, f32)> = samples.iter()
+            .zip(&self.window)
+            .map(|(&sample, &weight)| (sample * weight, 0.0))
+            .collect();
+        fft(&mut bins);
+        // a full scale sine wave comes out at 0 dBFS, which is where the window's gain and the
+        // half of the energy that goes into negative frequencies are made up for
+        let scale = 4.0 / self.size as f32;
+        bins[..self.size / 2].iter()
+            .map(|&(re, im)| 20.0 * (re.hypot(im) * scale).max(1e-10).log10())
+            .collect()
+    }
+
+    /// Draws the spectrogram into an image, with time going right and frequency going up on a
+    /// logarithmic scale.
+    pub fn draw(
+        &self,
+        width: u32,
+        height: u32,
+        sample_rate: u32,
+        colormap: &[(u8, u8, u8); 256],
+    ) -> Image {
+        let nyquist = sample_rate as f32 / 2.0;
+        let bin_width = sample_rate as f32 / self.size as f32;
+        let bins: Vec<usize> = (0..height)
+            .map(|y| {
+                let position = 1.0 - (y as f32 + 0.5) / height as f32;
+                let frequency = Self::MIN_FREQUENCY * (nyquist / Self::MIN_FREQUENCY).powf(position);
+                ((frequency / bin_width).round() as usize).min(self.size / 2 - 1)
+            })
+            .collect();
+        let mut rgba = vec![0; (width * height * 4) as usize];
+        // the newest column goes at the right edge, and the columns before the first one are
+        // left dark
+        let missing = (width as usize).saturating_sub(self.columns.len());
+        let shown = self.columns.iter().skip(self.columns.len().saturating_sub(width as usize));
+        for (x, column) in (missing..width as usize).zip(shown) {
+            for (y, &bin) in bins.iter().enumerate() {
+                let level = ((column[bin] + Self::DB_RANGE) / Self::DB_RANGE).clamp(0.0, 1.0);
+                let color = colormap[(level * 255.0) as usize];
+                let i = (x + y * width as usize) * 4;
+                rgba[i..i + 4].copy_from_slice(&[color.0, color.1, color.2, 255]);
+            }
+        }
+        Image { width, height, rgba }
+    }
+}
+
+/// Transforms complex values into their discrete Fourier transform, in place. The number of
+/// values must be a power of two.
+fn fft(values: &mut [(f32, f32)]) {
+    let n = values.len();
+    // the values are put in bit-reversed order, so that the butterflies can work in place
+    let mut j = 0;
+    for i in 1..n {
+        let mut bit = n >> 1;
+        while j & bit != 0 {
+            j ^= bit;
+            bit >>= 1;
+        }
+        j |= bit;
+        if i < j {
+            values.swap(i, j);
+        }
+    }
+    let mut length = 2;
+    while length <= n {
+        let angle = -std::f32::consts::TAU / length as f32;
+        let twiddles: Vec<(f32, f32)> = (0..length / 2).map(|k| (angle * k as f32).sin_cos()).collect();
+        for start in (0..n).step_by(length) {
+            for (k, &(sin, cos)) in twiddles.iter().enumerate() {
+                let (a, b) = (values[start + k], values[start + k + length / 2]);
+                let twiddled = (b.0 * cos - b.1 * sin, b.0 * sin + b.1 * cos);
+                values[start + k] = (a.0 + twiddled.0, a.1 + twiddled.1);
+                values[start + k + length / 2] = (a.0 - twiddled.0, a.1 - twiddled.1);
+            }
+        }
+        length *= 2;
+    }
+}
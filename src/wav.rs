@@ -0,0 +1,343 @@
+use std::{
+    fs::File,
+    io::{self, BufWriter, Read, Seek, SeekFrom, Write},
+    path::Path,
+};
+
+use anyhow::{anyhow, bail, Context};
+
+use crate::cli::RawFormat;
+
+/// A decoded WAV file.
+pub struct Wav {
+    pub sample_rate: u32,
+    pub channels: u16,
+    /// The file's samples, interleaved and converted to f32.
+    pub samples: Vec<f32>,
+}
+
+/// Writes a WAV file of 32-bit float samples as they come in. The sizes in the header are filled
+/// in when the writer is finished or dropped.
+pub struct WavWriter {
+    file: BufWriter<File>,
+    /// The size of the sample data written so far, in bytes.
+    data_size: u32,
+}
+
+/// The format of a WAV file's samples, as described by its fmt chunk.
+#[derive(Clone, Copy)]
+pub struct WavFormat {
+    pub sample_format: RawFormat,
+    pub channels: u16,
+    pub sample_rate: u32,
+}
+
+impl Wav {
+    const FORMAT_PCM: u16 = 1;
+    const FORMAT_IEEE_FLOAT: u16 = 3;
+    /// The actual format is stored in the first two bytes of the sub-format GUID.
+    const FORMAT_EXTENSIBLE: u16 = 0xFFFE;
+    /// The largest fmt chunk that's accepted. Even the extensible format's is only 40 bytes long.
+    const MAX_FORMAT_SIZE: u64 = 1024;
+
+    /// Reads and decodes a WAV file. Integer PCM samples of 8 to 32 bits and 32 or 64-bit float
+    /// samples are supported.
+    pub fn read(path: &Path) -> anyhow::Result<Self> {
+        let data = std::fs::read(path).with_context(|| format!("cannot read {}", path.display()))?;
+        Self::decode(&data).with_context(|| format!("cannot decode {}", path.display()))
+    }
+
+    fn decode(data: &[u8]) -> anyhow::Result<Self> {
+        let mut reader = data;
+        let (format, data_size) = Self::read_header(&mut reader)?;
+        // the data chunk's size is sometimes left unset by programs that write WAV files as a
+        // stream, in which case the data goes on until the end of the file
+        let data = match data_size {
+            0 => reader,
+            size => reader.get(..size as usize).unwrap_or(reader),
+        };
+        let sample_size = format.sample_format.sample_size();
+        // a partial frame at the end is dropped
+        let frame_size = sample_size * format.channels as usize;
+        let length = data.len() / frame_size * frame_size;
+        Ok(Wav {
+            sample_rate: format.sample_rate,
+            channels: format.channels,
+            samples: data[..length]
+                .chunks_exact(sample_size)
+                .map(|sample| format.sample_format.decode(sample))
+                .collect(),
+        })
+    }
+
+    /// Reads a WAV file's header, up to the start of the sample data. Returns the sample format
+    /// and the size of the data in bytes, as stated by the data chunk.
+    pub fn read_header(reader: &mut impl Read) -> anyhow::Result<(WavFormat, u32)> {
+        let mut riff = [0; 12];
+        reader.read_exact(&mut riff).context("not a WAV file")?;
+        if &riff[0..4] != b"RIFF" || &riff[8..12] != b"WAVE" {
+            // compressed formats aren't decoded, but it's nicer to say what the file is than to
+            // just say it isn't a WAV file
+            let format = match riff {
+                [b'f', b'L', b'a', b'C', ..] => "FLAC",
+                [b'O', b'g', b'g', b'S', ..] => "Ogg",
+                [b'I', b'D', b'3', ..] | [0xFF, 0xE0..=0xFF, ..] => "MP3",
+                _ => bail!("not a WAV file"),
+            };
+            bail!("{} files are not supported, convert the file to WAV first (eg. with ffmpeg)", format);
+        }
+        let mut format = None;
+        loop {
+            let mut header = [0; 8];
+            reader.read_exact(&mut header).context("the file does not have any audio data")?;
+            let size = u32::from_le_bytes([header[4], header[5], header[6], header[7]]);
+            if &header[0..4] == b"data" {
+                let format = format.ok_or_else(|| anyhow!("the data chunk comes before the fmt chunk"))?;
+                return Ok((format, size))
+            }
+            // chunks are padded to an even size
+            let padded_size = size as u64 + size as u64 % 2;
+            if &header[0..4] == b"fmt " {
+                if padded_size > Self::MAX_FORMAT_SIZE {
+                    bail!("the fmt chunk is too large ({} bytes)", size);
+                }
+                let mut chunk = vec![0; padded_size as usize];
+                reader.read_exact(&mut chunk).context("the file is truncated")?;
+                format = Some(Self::parse_format(&chunk)?);
+            } else {
+                // other chunks (metadata, cue points, embedded pictures) can be arbitrarily large,
+                // so they're skipped without being read into memory
+                let skipped = io::copy(&mut (&mut *reader).take(padded_size), &mut io::sink())?;
+                if skipped != padded_size {
+                    bail!("the file is truncated");
+                }
+            }
+        }
+    }
+
+    fn parse_format(chunk: &[u8]) -> anyhow::Result<WavFormat> {
+        if chunk.len() < 16 {
+            bail!("the fmt chunk is too short");
+        }
+        let u16_at = |offset: usize| u16::from_le_bytes([chunk[offset], chunk[offset + 1]]);
+        let mut tag = u16_at(0);
+        if tag == Self::FORMAT_EXTENSIBLE && chunk.len() >= 26 {
+            tag = u16_at(24);
+        }
+        let channels = u16_at(2);
+        let sample_rate = u32::from_le_bytes([chunk[4], chunk[5], chunk[6], chunk[7]]);
+        let block_align = u16_at(12);
+        if channels == 0 || block_align % channels != 0 {
+            bail!("invalid channel count or block alignment");
+        }
+        if sample_rate == 0 {
+            bail!("invalid sample rate");
+        }
+        let sample_size = block_align / channels;
+        let sample_format = match (tag, sample_size) {
+            (Self::FORMAT_PCM, 1) => RawFormat::U8,
+            (Self::FORMAT_PCM, 2) => RawFormat::S16Le,
+            (Self::FORMAT_PCM, 3) => RawFormat::S24Le,
+            (Self::FORMAT_PCM, 4) => RawFormat::S32Le,
+            (Self::FORMAT_IEEE_FLOAT, 4) => RawFormat::F32Le,
+            (Self::FORMAT_IEEE_FLOAT, 8) => RawFormat::F64Le,
+            _ => bail!("unsupported sample format {} with {}-byte samples", tag, sample_size),
+        };
+        Ok(WavFormat { sample_format, channels, sample_rate })
+    }
+}
+
+impl WavWriter {
+    /// The size of everything in the header that's counted in the RIFF chunk's size.
+    const HEADER_SIZE: u32 = 36;
+    /// Where the data chunk's size is in the header.
+    const DATA_SIZE_OFFSET: u64 = 40;
+
+    /// Creates a WAV file with the given format, overwriting any file that's already there.
+    pub fn create(path: &Path, sample_rate: u32, channels: u16) -> anyhow::Result<Self> {
+        let file = File::create(path).with_context(|| format!("cannot create {}", path.display()))?;
+        let mut file = BufWriter::new(file);
+        let block_align = channels * 4;
+        let mut header = Vec::new();
+        header.extend_from_slice(b"RIFF");
+        header.extend_from_slice(&Self::HEADER_SIZE.to_le_bytes());
+        header.extend_from_slice(b"WAVEfmt ");
+        header.extend_from_slice(&16u32.to_le_bytes());
+        header.extend_from_slice(&Wav::FORMAT_IEEE_FLOAT.to_le_bytes());
+        header.extend_from_slice(&channels.to_le_bytes());
+        header.extend_from_slice(&sample_rate.to_le_bytes());
+        header.extend_from_slice(&(sample_rate * block_align as u32).to_le_bytes());
+        header.extend_from_slice(&block_align.to_le_bytes());
+        header.extend_from_slice(&32u16.to_le_bytes());
+        header.extend_from_slice(b"data");
+        header.extend_from_slice(&0u32.to_le_bytes());
+        file.write_all(&header).with_context(|| format!("cannot write {}", path.display()))?;
+        Ok(WavWriter { file, data_size: 0 })
+    }
+
+    /// Appends interleaved samples to the file.
+    pub fn write(&mut self, samples: impl IntoIterator<Item = f32>) -> anyhow::Result<()> {
+        for sample in samples {
+            self.data_size = self.data_size.checked_add(4)
+                .filter(|&data_size| data_size <= u32::MAX - Self::HEADER_SIZE)
+                .ok_or_else(|| anyhow!("the file has reached the size limit of WAV files"))?;
+            self.file.write_all(&sample.to_le_bytes())?;
+        }
+        Ok(())
+    }
+
+    /// Fills in the sizes in the header and writes out everything that's buffered.
+    pub fn finish(&mut self) -> anyhow::Result<()> {
+        self.file.seek(SeekFrom::Start(4))?;
+        self.file.write_all(&(Self::HEADER_SIZE + self.data_size).to_le_bytes())?;
+        self.file.seek(SeekFrom::Start(Self::DATA_SIZE_OFFSET))?;
+        self.file.write_all(&self.data_size.to_le_bytes())?;
+        self.file.seek(SeekFrom::End(0))?;
+        self.file.flush()?;
+        Ok(())
+    }
+}
+
+impl Drop for WavWriter {
+    fn drop(&mut self) {
+        let _ = self.finish();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Puts together a fmt chunk's contents.
+    fn fmt(tag: u16, channels: u16, sample_rate: u32, sample_size: u16) -> Vec<u8> {
+        let block_align = channels * sample_size;
+        let mut chunk = Vec::new();
+        chunk.extend_from_slice(&tag.to_le_bytes());
+        chunk.extend_from_slice(&channels.to_le_bytes());
+        chunk.extend_from_slice(&sample_rate.to_le_bytes());
+        chunk.extend_from_slice(&(sample_rate * block_align as u32).to_le_bytes());
+        chunk.extend_from_slice(&block_align.to_le_bytes());
+        chunk.extend_from_slice(&(sample_size * 8).to_le_bytes());
+        chunk
+    }
+
+    /// Puts together a WAV file out of chunks, given as their ID, the size written in their
+    /// header, and their contents, which are padded to an even size.
+    fn file(chunks: &[(&[u8; 4], u32, &[u8])]) -> Vec<u8> {
+        let mut body = b"WAVE".to_vec();
+        for &(id, size, contents) in chunks {
+            body.extend_from_slice(id);
+            body.extend_from_slice(&size.to_le_bytes());
+            body.extend_from_slice(contents);
+            if contents.len() % 2 == 1 {
+                body.push(0);
+            }
+        }
+        let mut file = b"RIFF".to_vec();
+        file.extend_from_slice(&(body.len() as u32).to_le_bytes());
+        file.extend_from_slice(&body);
+        file
+    }
+
+    fn s16(samples: &[i16]) -> Vec<u8> {
+        samples.iter().flat_map(|sample| sample.to_le_bytes()).collect()
+    }
+
+    fn error(data: &[u8]) -> String {
+        match Wav::decode(data) {
+            Ok(_) => panic!("the file should not decode"),
+            Err(error) => error.to_string(),
+        }
+    }
+
+    #[test]
+    fn padded_chunks() {
+        let format = fmt(Wav::FORMAT_PCM, 2, 44100, 2);
+        let data = s16(&[16384, -16384, 0, 8192]);
+        let wav = Wav::decode(&file(&[
+            (b"LIST", 3, b"abc"),
+            (b"fmt ", 16, &format[..]),
+            (b"junk", 1, b"x"),
+            (b"data", 8, &data[..]),
+        ])).unwrap();
+        assert_eq!((wav.sample_rate, wav.channels), (44100, 2));
+        assert_eq!(wav.samples, [0.5, -0.5, 0.0, 0.25]);
+    }
+
+    #[test]
+    fn data_before_fmt() {
+        let format = fmt(Wav::FORMAT_PCM, 1, 8000, 1);
+        let data_first = file(&[(b"data", 2, &[128, 255]), (b"fmt ", 16, &format[..])]);
+        assert_eq!(error(&data_first), "the data chunk comes before the fmt chunk");
+    }
+
+    #[test]
+    fn invalid_format() {
+        let format = fmt(Wav::FORMAT_PCM, 1, 0, 1);
+        let zero_rate = file(&[(b"fmt ", 16, &format[..]), (b"data", 2, &[128, 255])]);
+        assert_eq!(error(&zero_rate), "invalid sample rate");
+        let format = fmt(Wav::FORMAT_PCM, 0, 44100, 1);
+        let no_channels = file(&[(b"fmt ", 16, &format[..]), (b"data", 2, &[128, 255])]);
+        assert_eq!(error(&no_channels), "invalid channel count or block alignment");
+    }
+
+    #[test]
+    fn extensible_format() {
+        let mut format = fmt(Wav::FORMAT_EXTENSIBLE, 1, 96000, 3);
+        // the extension's size, the valid bits, the channel mask and the sub-format GUID
+        format.extend_from_slice(&22u16.to_le_bytes());
+        format.extend_from_slice(&24u16.to_le_bytes());
+        format.extend_from_slice(&4u32.to_le_bytes());
+        format.extend_from_slice(&Wav::FORMAT_PCM.to_le_bytes());
+        format.extend_from_slice(&[0, 0, 0, 0, 0x10, 0, 0x80, 0, 0, 0xAA, 0, 0x38, 0x9B, 0x71]);
+        assert_eq!(format.len(), 40);
+        let data = [0x00, 0x00, 0x40, 0x00, 0x00, 0xC0];
+        let wav = Wav::decode(&file(&[(b"fmt ", 40, &format[..]), (b"data", 6, &data[..])])).unwrap();
+        assert_eq!((wav.sample_rate, wav.channels), (96000, 1));
+        assert_eq!(wav.samples, [0.5, -0.5]);
+
+        // an extensible float format
+        let mut format = fmt(Wav::FORMAT_EXTENSIBLE, 1, 48000, 4);
+        format.extend_from_slice(&[22, 0, 32, 0, 4, 0, 0, 0]);
+        format.extend_from_slice(&Wav::FORMAT_IEEE_FLOAT.to_le_bytes());
+        format.extend_from_slice(&[0; 14]);
+        let data = 0.75f32.to_le_bytes();
+        let wav = Wav::decode(&file(&[(b"fmt ", 40, &format[..]), (b"data", 4, &data[..])])).unwrap();
+        assert_eq!(wav.samples, [0.75]);
+    }
+
+    #[test]
+    fn data_size() {
+        let format = fmt(Wav::FORMAT_PCM, 2, 44100, 2);
+        let data = s16(&[16384, -16384, 8192, -8192, 4096]);
+        // an unset size means the data goes on until the end of the file, but the partial frame
+        // at the end is dropped
+        let wav = Wav::decode(&file(&[(b"fmt ", 16, &format[..]), (b"data", 0, &data[..])])).unwrap();
+        assert_eq!(wav.samples, [0.5, -0.5, 0.25, -0.25]);
+        // a size past the end of the file is cut short
+        let wav = Wav::decode(&file(&[(b"fmt ", 16, &format[..]), (b"data", 1000, &data[..])])).unwrap();
+        assert_eq!(wav.samples, [0.5, -0.5, 0.25, -0.25]);
+        // anything past the stated size isn't sample data
+        let wav = Wav::decode(&file(&[(b"fmt ", 16, &format[..]), (b"data", 4, &data[..])])).unwrap();
+        assert_eq!(wav.samples, [0.5, -0.5]);
+
+        assert_eq!(error(&file(&[(b"fmt ", 16, &format[..])])), "the file does not have any audio data");
+        let truncated = file(&[(b"fmt ", 16, &format[..]), (b"LIST", 100, b"abcd")]);
+        assert_eq!(error(&truncated), "the file is truncated");
+    }
+
+    #[test]
+    fn round_trip() {
+        let samples: Vec<f32> = (0..3000).map(|i| (i as f32 * 0.01).sin()).collect();
+        let path = std::env::temp_dir().join(format!("vectorscope-wav-test-{}.wav", std::process::id()));
+        let mut writer = WavWriter::create(&path, 48000, 3).unwrap();
+        writer.write(samples[..1000].iter().copied()).unwrap();
+        writer.write(samples[1000..].iter().copied()).unwrap();
+        drop(writer);
+        let wav = Wav::read(&path);
+        std::fs::remove_file(&path).unwrap();
+        let wav = wav.unwrap();
+        assert_eq!((wav.sample_rate, wav.channels), (48000, 3));
+        assert!(wav.samples == samples);
+    }
+}